@@ -0,0 +1,214 @@
+use crate::auth::Spotify;
+use chrono::{DateTime, Utc};
+use reqwest::{StatusCode, header::CONTENT_TYPE};
+use serde::de::DeserializeOwned;
+use spotify_rs::model::{
+    Page, PlayableItem, player::PlaybackState, playlist::SimplifiedPlaylist, track::Track,
+};
+use std::collections::HashMap;
+
+const API_URL: &str = "https://api.spotify.com/v1";
+
+/// Maximum number of items the playlist add/remove endpoints accept per request.
+pub const BATCH_SIZE: usize = 100;
+/// Maximum number of IDs the several-artists endpoint accepts per request.
+pub const ARTIST_BATCH_SIZE: usize = 50;
+/// Maximum number of IDs the save-tracks and check-saved-tracks endpoints accept per request.
+pub const LIBRARY_BATCH_SIZE: usize = 50;
+
+/// Fetches `path` of the Web API, returning the response both parsed and as
+/// the JSON it was parsed from.
+pub async fn get_json<T: DeserializeOwned>(
+    spotify: &Spotify,
+    path: &str,
+) -> color_eyre::Result<(T, String)> {
+    let json = reqwest::Client::new()
+        .get(format!("{API_URL}{path}"))
+        .bearer_auth(access_token(spotify).await?)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    Ok((serde_json::from_str(&json)?, json))
+}
+
+/// Resumes playback, or plays `uri`: a single track or episode, or every
+/// track of an album, playlist or artist.
+pub async fn play(spotify: &Spotify, uri: Option<&str>) -> color_eyre::Result<()> {
+    let playback = spotify_rs::start_playback();
+    let playback = match uri {
+        Some(uri) if uri.starts_with("spotify:track:") || uri.starts_with("spotify:episode:") => {
+            playback.uris(&[uri])
+        }
+        Some(uri) => playback.context_uri(uri),
+        None => playback,
+    };
+    playback.send(spotify).await?;
+    Ok(())
+}
+
+/// The playback state, or `None` when nothing is playing on any device.
+pub async fn playback_state(spotify: &Spotify) -> color_eyre::Result<Option<PlaybackState>> {
+    playback_state_with_token(&access_token(spotify).await?).await
+}
+
+/// [`playback_state`] with just an access token, for polling without a client.
+pub async fn playback_state_with_token(
+    access_token: &str,
+) -> color_eyre::Result<Option<PlaybackState>> {
+    let response = reqwest::Client::new()
+        .get(format!("{API_URL}/me/player"))
+        .bearer_auth(access_token)
+        .send()
+        .await?
+        .error_for_status()?;
+    // spotify-rs fails on the empty body Spotify answers with when idle.
+    if response.status() == StatusCode::NO_CONTENT {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&response.text().await?)?))
+}
+
+/// The access token of `spotify` for requests made without spotify-rs, which
+/// only refreshes it for its own requests.
+async fn access_token(spotify: &Spotify) -> color_eyre::Result<String> {
+    let expired = spotify
+        .token()
+        .read()
+        .map_or(true, |token| token.is_expired());
+    if expired {
+        spotify.exchange_refresh_token().await?;
+    }
+    Ok(spotify.access_token()?)
+}
+
+pub async fn all_user_playlists(spotify: &Spotify) -> color_eyre::Result<Vec<SimplifiedPlaylist>> {
+    let playlists = spotify_rs::current_user_playlists()
+        .limit(50)
+        .get(spotify)
+        .await?
+        .get_remaining(spotify)
+        .await?;
+    Ok(playlists.into_iter().flatten().collect())
+}
+
+/// Every track of a playlist with the date it was added. Episodes are skipped.
+pub async fn playlist_tracks(
+    spotify: &Spotify,
+    playlist_id: &str,
+) -> color_eyre::Result<Vec<(Track, Option<DateTime<Utc>>)>> {
+    let items = spotify_rs::playlist_items(playlist_id)
+        .limit(50)
+        .get(spotify)
+        .await?
+        .get_remaining(spotify)
+        .await?;
+    Ok(items
+        .into_iter()
+        .flatten()
+        .filter_map(|item| match item.track {
+            PlayableItem::Track(track) => Some((track, item.added_at)),
+            PlayableItem::Episode(_) => None,
+        })
+        .collect())
+}
+
+/// Fetches every page after `page`, handing each to `on_page` as it arrives.
+pub async fn for_each_remaining_page<T: Clone + DeserializeOwned>(
+    spotify: &Spotify,
+    mut page: Page<T>,
+    mut on_page: impl FnMut(Page<T>),
+) -> color_eyre::Result<()> {
+    while page.next.is_some() {
+        page = page.get_next(spotify).await?;
+        on_page(page.clone());
+    }
+    Ok(())
+}
+
+/// Genres keyed by artist ID.
+pub async fn artist_genres(
+    spotify: &Spotify,
+    artist_ids: &[String],
+) -> color_eyre::Result<HashMap<String, Vec<String>>> {
+    let mut genres = HashMap::new();
+    for chunk in artist_ids.chunks(ARTIST_BATCH_SIZE) {
+        for artist in spotify_rs::get_artists(chunk, spotify).await? {
+            genres.insert(artist.id, artist.genres);
+        }
+    }
+    Ok(genres)
+}
+
+/// Whether each of `ids` is in the user's liked songs.
+pub async fn liked_tracks(
+    spotify: &Spotify,
+    ids: &[String],
+) -> color_eyre::Result<Vec<(String, bool)>> {
+    let mut results = Vec::with_capacity(ids.len());
+    for chunk in ids.chunks(LIBRARY_BATCH_SIZE) {
+        let liked = spotify_rs::check_saved_tracks(chunk, spotify).await?;
+        results.extend(chunk.iter().cloned().zip(liked));
+    }
+    Ok(results)
+}
+
+/// Appends `uris` to a playlist [`BATCH_SIZE`] at a time, calling `on_batch`
+/// with the running count after each request.
+pub async fn add_items_in_batches(
+    spotify: &Spotify,
+    playlist_id: &str,
+    uris: &[String],
+    mut on_batch: impl FnMut(usize),
+) -> color_eyre::Result<()> {
+    let mut done = 0;
+    for chunk in uris.chunks(BATCH_SIZE) {
+        spotify_rs::add_items_to_playlist(playlist_id, chunk)
+            .send(spotify)
+            .await?;
+        done += chunk.len();
+        on_batch(done);
+    }
+    Ok(())
+}
+
+/// Removes every occurrence of `uris` from a playlist [`BATCH_SIZE`] at a time,
+/// calling `on_batch` with the running count after each request.
+pub async fn remove_items_in_batches(
+    spotify: &Spotify,
+    playlist_id: &str,
+    uris: &[String],
+    mut on_batch: impl FnMut(usize),
+) -> color_eyre::Result<()> {
+    let mut done = 0;
+    for chunk in uris.chunks(BATCH_SIZE) {
+        spotify_rs::remove_playlist_items(playlist_id, chunk)
+            .send(spotify)
+            .await?;
+        done += chunk.len();
+        on_batch(done);
+    }
+    Ok(())
+}
+
+/// Replaces the items of a playlist with `uris`, in that order. Spotify
+/// takes [`BATCH_SIZE`] in the replacing request and the rest are appended.
+pub async fn replace_items(
+    spotify: &Spotify,
+    playlist_id: &str,
+    uris: &[String],
+) -> color_eyre::Result<()> {
+    let (first, rest) = uris.split_at(uris.len().min(BATCH_SIZE));
+    // spotify-rs sends a range to reorder along with the URIs, which this
+    // endpoint doesn't accept when replacing.
+    reqwest::Client::new()
+        .put(format!("{API_URL}/playlists/{playlist_id}/tracks"))
+        .bearer_auth(access_token(spotify).await?)
+        .header(CONTENT_TYPE, "application/json")
+        .body(serde_json::json!({ "uris": first }).to_string())
+        .send()
+        .await?
+        .error_for_status()?;
+    add_items_in_batches(spotify, playlist_id, rest, |_| {}).await
+}
@@ -1,40 +1,62 @@
-use crate::event::{AppEvent, Event, EventHandler};
-use open;
+use crate::{
+    api,
+    artwork::Artwork,
+    auth::{self, SignIn, Spotify},
+    cache::{self, Cache},
+    command::{self, Argument, Choice, Command, Invocation, PaletteEntry},
+    config::{self, Config},
+    event::{AppEvent, Event, EventHandler, ListKind, ListPage, ProfileSender, Progress},
+    export,
+    ipc::{self, Notice, Repeat, Request},
+    keymap::{Action, Binding, KeyChord, Keymap, Resolved},
+    mpris::Mpris,
+    navigation::{History, View, Visit},
+    notifications::Notifier,
+    panes::{self, PaneLayout},
+    playlist_ops::{self, PlaylistOperation},
+    session::Session,
+    smart_playlist::{SmartPlaylists, sync as smart_sync},
+    status::Status,
+    theme::{self, THEME_NAMES, Theme, ThemeConfig},
+    track_actions::{self, TrackAction, TrackOption},
+    web_remote::WebRemote,
+    widgets::{
+        filter::ListFilter,
+        list::{MultiSelect, scrolled_offset},
+        table::{self, Column, SortOrder, TableRow},
+    },
+};
+use color_eyre::eyre::OptionExt;
 use ratatui::{
     DefaultTerminal,
     crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    layout::{Position, Rect},
     widgets::{ListItem, ListState},
 };
-use reqwest::Url;
-use rouille::{Response, Server};
-use spotify_rs::{
-    AuthCodePkceClient, AuthCodePkceFlow, RedirectUrl, Token,
-    client::Client,
-    model::{
-        Page,
-        artist::Artist,
-        playlist::{Playlist, PlaylistItem, SimplifiedPlaylist},
-        track::Track,
-        user::PrivateUser,
-    },
+use serde::de::DeserializeOwned;
+use spotify_rs::endpoint::player::RepeatMode;
+use spotify_rs::model::{
+    Page, PlayableItem,
+    artist::Artist,
+    player::Device,
+    playlist::{Playlist, PlaylistItem, SimplifiedPlaylist},
+    track::Track,
+    user::PrivateUser,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak, atomic::Ordering},
+    time::{Duration, Instant},
 };
-use std::sync::{Arc, Mutex};
 use tui_logger::TuiWidgetState;
 
-const SCOPES: [&str; 9] = [
-    "user-top-read",
-    "user-follow-read",
-    "user-read-private",
-    "user-read-email",
-    "user-read-playback-state",
-    "user-read-currently-playing",
-    "user-modify-playback-state",
-    "playlist-read-private",
-    "playlist-read-collaborative",
-];
+/// How often the player is polled for the control socket's state and notices.
+const PLAYBACK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long the player is given to carry out a command before it is polled.
+const PLAYBACK_SETTLE_DELAY: Duration = Duration::from_millis(500);
 
 pub const DIRECTORY: [&str; 3] = ["Playlists", "Top Tracks", "Top Artists"];
-pub const TRACK_OPTIONS: [&str; 4] = ["Play", "Add to Queue", "Go to Artist", "Go to Album"];
 
 #[derive(Debug, Clone)]
 pub struct NavList {
@@ -49,6 +71,12 @@ pub struct PageEndpoint<T: Clone> {
     pub page: Option<Page<T>>,
     pub list: Vec<Option<T>>,
     pub list_state: ListState,
+    pub selection: MultiSelect,
+    pub filter: ListFilter,
+    /// Whether the remaining pages are being fetched in the background.
+    pub loading: bool,
+    /// Kept across refreshes, so a view stays sorted the way it was.
+    pub sort: Option<SortOrder>,
 }
 
 impl<T> PageEndpoint<T>
@@ -61,8 +89,112 @@ where
             page: None,
             list: Vec::new(),
             list_state: ListState::default(),
+            selection: MultiSelect::default(),
+            filter: ListFilter::default(),
+            loading: false,
+            sort: None,
+        }
+    }
+
+    /// Replaces the list with a freshly fetched first page.
+    pub fn reset(&mut self, page: Page<T>) {
+        self.total = page.total as usize;
+        self.list = page.items.clone();
+        self.page = Some(page);
+        self.selection.clear();
+        self.loading = false;
+    }
+
+    /// Appends the page following the last loaded one. Pages that don't
+    /// continue the list, e.g. from a load started before a refresh, are ignored.
+    pub fn append(&mut self, page: Page<T>) -> bool {
+        if page.offset as usize != self.list.len() {
+            return false;
+        }
+        self.total = page.total as usize;
+        self.list.extend(page.items.iter().cloned());
+        self.page = Some(page);
+        true
+    }
+
+    pub fn has_more(&self) -> bool {
+        self.page.as_ref().is_some_and(|p| p.next.is_some())
+    }
+
+    /// How much of the list has loaded while the remaining pages are fetched.
+    pub fn loading_progress(&self, label: &str) -> Option<Progress> {
+        self.loading.then(|| Progress {
+            label: format!("Loading {label}"),
+            done: self.list.len(),
+            total: self.total,
+        })
+    }
+}
+
+impl<T> PageEndpoint<T>
+where
+    T: TableRow + Clone + PartialEq,
+{
+    /// Replaces the list with a freshly fetched first page, unless its rows are
+    /// already loaded, in which case later pages are kept too. Returns for each
+    /// old index the new index of the same item, `None` if nothing changed.
+    /// The selection is left for the caller to move.
+    pub fn refresh(&mut self, page: Page<T>) -> Option<Vec<Option<usize>>> {
+        let loaded = if self.sort.is_some() {
+            page.items.iter().all(|item| self.list.contains(item))
+        } else {
+            self.list.get(..page.items.len()) == Some(&page.items[..])
+        };
+        if loaded && page.total as usize == self.total {
+            return None;
         }
+        let old = row_keys(&self.list);
+        let selection = std::mem::take(&mut self.selection);
+        self.reset(page);
+        self.selection = selection;
+        let new: HashMap<_, _> = row_keys(&self.list)
+            .into_iter()
+            .enumerate()
+            .map(|(i, key)| (key, i))
+            .collect();
+        Some(old.iter().map(|key| new.get(key).copied()).collect())
+    }
+}
+
+/// Identifies each row by its item's ID and how many rows before it share it,
+/// so duplicates in a playlist keep their own place.
+fn row_keys<T: TableRow>(items: &[Option<T>]) -> Vec<Option<(String, usize)>> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    items
+        .iter()
+        .map(|item| {
+            let id = item.as_ref()?.id();
+            let count = seen.entry(id).or_default();
+            *count += 1;
+            Some((id.to_owned(), *count - 1))
+        })
+        .collect()
+}
+
+/// Sorts `items` by `sort`, returning for each old index its new index.
+fn sort_items<T: TableRow + Clone>(items: &mut Vec<Option<T>>, sort: SortOrder) -> Vec<usize> {
+    let order = table::sort_order(items, sort);
+    let mut new_index = vec![0; order.len()];
+    for (new, &old) in order.iter().enumerate() {
+        new_index[old] = new;
     }
+    *items = order.into_iter().map(|old| items[old].take()).collect();
+    new_index
+}
+
+/// The cursor, selection and filter of one of the paged lists.
+pub struct ListCursor<'a> {
+    pub list_state: &'a mut ListState,
+    pub selection: &'a mut MultiSelect,
+    pub filter: &'a mut ListFilter,
+    pub sort: &'a mut Option<SortOrder>,
+    pub has_more: bool,
+    pub len: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -70,6 +202,8 @@ pub struct TrackList<T, U: Clone> {
     pub result: Option<T>,
     pub pages: PageEndpoint<U>,
     pub list_state: ListState,
+    pub selection: MultiSelect,
+    pub filter: ListFilter,
 }
 
 impl<T, U> TrackList<T, U>
@@ -81,7 +215,67 @@ where
             result: None,
             pages: PageEndpoint::new(),
             list_state: ListState::default(),
+            selection: MultiSelect::default(),
+            filter: ListFilter::default(),
+        }
+    }
+}
+
+/// The `?` overlay listing the bindings of the block it was opened from.
+#[derive(Debug, Clone)]
+pub struct HelpView {
+    pub rows: Vec<Option<Binding>>,
+    pub list_state: ListState,
+    pub filter: ListFilter,
+    /// The block focused when the overlay was opened, and returned to when it closes.
+    pub context: ActiveBlock,
+}
+
+/// The `:` command palette. The filter query is the text typed so far.
+#[derive(Debug, Clone)]
+pub struct PaletteView {
+    /// The command whose argument is being asked for, `None` while picking a command.
+    pub pending: Option<Command>,
+    pub entries: Vec<Option<PaletteEntry>>,
+    pub list_state: ListState,
+    pub filter: ListFilter,
+    /// Recently run commands, most recent first.
+    pub history: Vec<Invocation>,
+    /// The block focused when the palette was opened, and returned to when it closes.
+    pub context: ActiveBlock,
+}
+
+/// Effect of a key typed while a filter query is being edited.
+enum FilterInput {
+    Changed,
+    Up,
+    Down,
+    Quit,
+    Ignored,
+}
+
+impl FilterInput {
+    fn apply(filter: &mut ListFilter, key_event: KeyEvent) -> Self {
+        match key_event.code {
+            KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
+                return FilterInput::Quit;
+            }
+            KeyCode::Esc => filter.clear(),
+            KeyCode::Enter => {
+                filter.editing = false;
+                if filter.query.trim().is_empty() {
+                    filter.clear();
+                }
+            }
+            KeyCode::Backspace => {
+                filter.query.pop();
+            }
+            KeyCode::Up => return FilterInput::Up,
+            KeyCode::Down => return FilterInput::Down,
+            KeyCode::Char(c) => filter.query.push(c),
+            _ => return FilterInput::Ignored,
         }
+        FilterInput::Changed
     }
 }
 
@@ -92,7 +286,17 @@ pub struct UserLibrary {
     pub user_top_artists: PageEndpoint<Artist>,
 }
 
-#[derive(Debug, Clone, PartialEq, Copy)]
+impl UserLibrary {
+    pub fn new() -> Self {
+        Self {
+            user_playlists: PageEndpoint::new(),
+            user_top_tracks: PageEndpoint::new(),
+            user_top_artists: PageEndpoint::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 pub enum ActiveBlock {
     Directory,
     UserPlaylists,
@@ -101,7 +305,46 @@ pub enum ActiveBlock {
     Playlist,
     Artist,
     Popup,
+    PlaylistPopup,
+    PlaylistPicker,
     Logger,
+    Help,
+    Palette,
+}
+
+impl ActiveBlock {
+    pub const ALL: [ActiveBlock; 12] = [
+        ActiveBlock::Directory,
+        ActiveBlock::UserPlaylists,
+        ActiveBlock::UserTopTracks,
+        ActiveBlock::UserTopArtists,
+        ActiveBlock::Playlist,
+        ActiveBlock::Artist,
+        ActiveBlock::Popup,
+        ActiveBlock::PlaylistPopup,
+        ActiveBlock::PlaylistPicker,
+        ActiveBlock::Logger,
+        ActiveBlock::Help,
+        ActiveBlock::Palette,
+    ];
+
+    /// Name of the block's section in the `[keymap]` config.
+    pub fn config_name(self) -> &'static str {
+        match self {
+            ActiveBlock::Directory => "directory",
+            ActiveBlock::UserPlaylists => "user-playlists",
+            ActiveBlock::UserTopTracks => "user-top-tracks",
+            ActiveBlock::UserTopArtists => "user-top-artists",
+            ActiveBlock::Playlist => "playlist",
+            ActiveBlock::Artist => "artist",
+            ActiveBlock::Popup => "track-options",
+            ActiveBlock::PlaylistPopup => "playlist-operations",
+            ActiveBlock::PlaylistPicker => "playlist-picker",
+            ActiveBlock::Logger => "logger",
+            ActiveBlock::Help => "help",
+            ActiveBlock::Palette => "palette",
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -117,7 +360,9 @@ pub enum SelectedTab {
 pub struct App {
     pub running: bool,
     pub events: EventHandler,
-    pub spotify_client: Client<Token, AuthCodePkceFlow>,
+    /// `None` in offline mode, where only the cache is browsed.
+    pub spotify_client: Option<Arc<Spotify>>,
+    pub cache: Cache,
     pub user_library: UserLibrary,
     pub directory: NavList,
     pub selected_state: ListState,
@@ -125,36 +370,94 @@ pub struct App {
     pub route: Route,
     pub playlist: TrackList<Playlist, PlaylistItem>,
     pub track_popup: NavList,
+    pub playlist_popup: NavList,
+    pub playlist_picker: NavList,
+    /// Writable playlists listed in `playlist_picker`, in display order.
+    pub picker_playlists: Vec<SimplifiedPlaylist>,
+    /// Track URIs waiting for a destination to be picked in `playlist_picker`.
+    pub pending_tracks: Vec<String>,
+    pub progress: Option<Progress>,
+    /// Columns of the track tables, from the config.
+    pub track_columns: Vec<Column>,
+    /// Whether a track ID is in the user's liked songs, for the tracks checked so far.
+    pub liked: HashMap<String, bool>,
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub help: HelpView,
+    pub palette: PaletteView,
+    /// Views visited before and after the shown one.
+    pub history: History,
+    pub panes: PaneLayout,
+    pub artwork: Artwork,
+    /// Size of the last drawn frame, for mapping mouse positions to widgets.
+    pub area: Rect,
+    /// Time and position of the last click that may start a double-click.
+    pub last_click: Option<(Instant, Position)>,
+    /// Playback devices, as last fetched for the palette.
+    pub devices: Vec<Device>,
+    /// The control socket, `None` if it couldn't be opened.
+    pub remote: Option<ipc::Server>,
+    /// The MPRIS service, only registered while online.
+    pub mpris: Option<Mpris>,
+    pub notifier: Notifier,
+    /// The web remote, `None` unless enabled in the config and online.
+    pub web_remote: Option<WebRemote>,
+    /// The playback state as last polled, `None` before the first poll.
+    pub playback: Option<Status>,
+    /// When `playback` was polled, to tell how far it has moved on since.
+    pub playback_polled_at: Instant,
+    /// Keys of a sequence typed so far, e.g. the first `g` of `g g`.
+    pub pending_keys: Vec<KeyChord>,
     pub logger_state: TuiWidgetState,
     pub selected_tab: SelectedTab,
+    pub smart_playlists: SmartPlaylists,
+    /// How many times another profile was switched to, tagging the events
+    /// of background work so that those of an earlier profile are dropped.
+    pub profile_generation: u64,
 }
 impl App {
-    pub async fn new() -> Self {
-        let spotify_client = match Self::get_spotify_client().await {
-            Ok(client) => {
-                log::info!("Successfully obtained Spotify client.");
-                client
-            }
-            Err(e) => {
-                log::error!("Failed to obtain Spotify client: {e}");
-                panic!("Failed to get spotify client: {e}");
+    /// Signs in to Spotify, unless `offline`. When signing in fails the cached
+    /// library is shown offline.
+    pub async fn new(offline: bool, sign_in: SignIn) -> Self {
+        let spotify_client = if offline {
+            log::info!("Starting in offline mode");
+            None
+        } else {
+            match auth::sign_in(sign_in).await {
+                Ok(client) => {
+                    log::info!("Successfully obtained Spotify client.");
+                    Some(Arc::new(client))
+                }
+                Err(e) => {
+                    log::error!("Failed to obtain Spotify client, continuing offline: {e}");
+                    None
+                }
             }
         };
+        let config = load_config();
+        let keymap = load_keymap(&config);
+        let theme = load_theme(&config);
+        let events = EventHandler::new();
+        let remote = ipc::Server::start(events.sender());
+        let (mpris, web_remote) = match &spotify_client {
+            Some(spotify) => (
+                Mpris::start(events.sender()).await,
+                WebRemote::start(&config.web_remote, spotify.clone(), events.sender()),
+            ),
+            None => (None, None),
+        };
         Self {
             running: true,
-            events: EventHandler::new(),
+            events,
             spotify_client,
+            cache: Cache::open(),
             selected_state: ListState::default(),
             directory: NavList {
                 title: "Directory".to_string(),
                 list: DIRECTORY.iter().map(|&item| ListItem::new(item)).collect(),
                 list_state: ListState::default(),
             },
-            user_library: UserLibrary {
-                user_playlists: PageEndpoint::new(),
-                user_top_tracks: PageEndpoint::new(),
-                user_top_artists: PageEndpoint::new(),
-            },
+            user_library: UserLibrary::new(),
             user: None,
             route: Route {
                 active_block: ActiveBlock::Directory,
@@ -163,75 +466,66 @@ impl App {
             playlist: TrackList::new(),
             track_popup: NavList {
                 title: "Options".to_string(),
-                list: TRACK_OPTIONS
+                list: TrackOption::ALL
+                    .iter()
+                    .map(|option| ListItem::new(option.label()))
+                    .collect(),
+                list_state: ListState::default(),
+            },
+            playlist_popup: NavList {
+                title: "Playlist Operations".to_string(),
+                list: PlaylistOperation::ALL
                     .iter()
-                    .map(|&item| ListItem::new(item))
+                    .map(|op| ListItem::new(op.label()))
                     .collect(),
                 list_state: ListState::default(),
             },
+            playlist_picker: NavList {
+                title: "Add to Playlist".to_string(),
+                list: Vec::new(),
+                list_state: ListState::default(),
+            },
+            picker_playlists: Vec::new(),
+            pending_tracks: Vec::new(),
+            progress: None,
+            track_columns: config.columns.tracks,
+            liked: HashMap::new(),
+            keymap,
+            theme,
+            help: HelpView {
+                rows: Vec::new(),
+                list_state: ListState::default(),
+                filter: ListFilter::default(),
+                context: ActiveBlock::Directory,
+            },
+            palette: PaletteView {
+                pending: None,
+                entries: Vec::new(),
+                list_state: ListState::default(),
+                filter: ListFilter::default(),
+                history: Vec::new(),
+                context: ActiveBlock::Directory,
+            },
+            devices: Vec::new(),
+            remote,
+            mpris,
+            notifier: Notifier::from_config(&config.notifications),
+            web_remote,
+            playback: None,
+            playback_polled_at: Instant::now(),
+            history: History::default(),
+            panes: PaneLayout::load(&config.layout),
+            artwork: Artwork::new(&config.artwork),
+            area: Rect::default(),
+            last_click: None,
+            pending_keys: Vec::new(),
             selected_tab: SelectedTab::Main,
             logger_state: TuiWidgetState::default(),
+            smart_playlists: SmartPlaylists::from_config(&config.smart_playlists),
+            profile_generation: 0,
         }
     }
 
-    fn start_server(redirect_url_host: String, tx: std::sync::mpsc::SyncSender<(String, String)>) {
-        tokio::spawn(async move {
-            let sent = Arc::new(Mutex::new(false));
-            let sent2 = sent.clone();
-
-            let server = Server::new(redirect_url_host, move |request| {
-                let url = Url::parse(&format!("http://{}", request.raw_url())).unwrap();
-                let mut queries: Vec<_> = url.query_pairs().into_owned().collect();
-                let auth_code = queries.remove(0).1;
-                let csrf_state = queries.remove(0).1;
-
-                tx.send((auth_code, csrf_state)).unwrap();
-
-                *sent2.lock().unwrap() = true;
-
-                Response::html("<h1>You may close this page</h1><script>window.close()</script>")
-            })
-            .unwrap();
-
-            while !*sent.lock().unwrap() {
-                server.poll();
-            }
-        });
-    }
-
-    pub async fn get_spotify_client() -> color_eyre::Result<AuthCodePkceClient<Token>> {
-        dotenvy::dotenv().ok();
-        let client_id = dotenvy::var("SPOTIFY_CLIENT_ID")?;
-        let redirect_uri = dotenvy::var("SPOTIFY_REDIRECT_URI")?;
-        let redirect_url = Url::parse(&redirect_uri).unwrap();
-        let redirect_url_host = format!(
-            "{}:{}",
-            redirect_url.host_str().unwrap(),
-            redirect_url.port().unwrap()
-        );
-
-        let (tx, rx) = std::sync::mpsc::sync_channel(1);
-
-        Self::start_server(redirect_url_host, tx);
-
-        let auto_refresh = false;
-
-        let (client, url) = AuthCodePkceClient::new(
-            client_id,
-            SCOPES,
-            RedirectUrl::new(redirect_uri)?,
-            auto_refresh,
-        );
-        log::info!("Opening browser for Spotify authentication...");
-        open::that(url.as_str())?;
-
-        let (auth_code, csrf_state) = rx.recv().unwrap();
-
-        let spotify_auth = client.authenticate(auth_code, csrf_state).await?;
-
-        Ok(spotify_auth)
-    }
-
     pub async fn run(mut self, mut terminal: DefaultTerminal) -> color_eyre::Result<()> {
         terminal.clear()?;
 
@@ -239,7 +533,16 @@ impl App {
         self.events.send(AppEvent::Init);
 
         while self.running {
-            terminal.draw(|frame| frame.render_widget(&self, frame.area()))?;
+            self.publish_state();
+            let target = self.artwork_target((Position::ORIGIN, terminal.size()?).into());
+            self.artwork.prepare(target, &self.events.sender());
+            self.area = terminal
+                .draw(|frame| frame.render_widget(&self, frame.area()))?
+                .area;
+            self.keep_scroll_offset();
+            if let Err(e) = self.artwork.write(terminal.backend_mut()) {
+                log::error!("Failed to draw artwork: {e}");
+            }
 
             match self.events.next().await? {
                 Event::Tick => self.tick(),
@@ -247,273 +550,2133 @@ impl App {
                     ratatui::crossterm::event::Event::Key(key_event) => {
                         self.handle_key_events(key_event)?
                     }
+                    ratatui::crossterm::event::Event::Mouse(mouse_event) => {
+                        self.handle_mouse_event(mouse_event)
+                    }
+                    ratatui::crossterm::event::Event::Resize(..) => self.artwork.invalidate(),
                     _ => {}
                 },
-                Event::App(app_event) => match app_event {
-                    AppEvent::Quit => self.quit(),
-                    AppEvent::Init => self.init().await?,
-                    AppEvent::Select => self.select().await,
-                    AppEvent::Next => self.next().await,
-                },
+                Event::App(app_event) => self.handle_app_event(app_event).await,
             }
         }
         Ok(())
     }
 
-    pub fn handle_key_events(&mut self, key_event: KeyEvent) -> color_eyre::Result<()> {
-        log::debug!("Key event: {:#?}", key_event);
-        match key_event.code {
-            KeyCode::Esc | KeyCode::Char('q') => self.events.send(AppEvent::Quit),
-            KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
-                self.events.send(AppEvent::Quit)
-            }
-            KeyCode::Tab => self.events.send(AppEvent::Next),
-            KeyCode::Up | KeyCode::Char('k') => self.up(),
-            KeyCode::Down | KeyCode::Char('j') => self.down(),
-            KeyCode::Left | KeyCode::Char('h') => {}
-            KeyCode::Right | KeyCode::Char('l') => {}
-            KeyCode::Enter => self.events.send(AppEvent::Select),
-            KeyCode::Char('1') => {
-                self.selected_tab = SelectedTab::Main;
-                self.route.active_block = self.route.hovered_block;
-            }
-            KeyCode::Char('2') => {
-                self.selected_tab = SelectedTab::Logger;
-                self.route.active_block = ActiveBlock::Logger;
+    async fn handle_app_event(&mut self, app_event: AppEvent) {
+        match app_event {
+            AppEvent::Quit => self.quit(),
+            AppEvent::Init => {
+                self.init();
+                self.restore_session().await;
             }
-            _ => {}
-        }
-        Ok(())
-    }
-
-    pub async fn next(&mut self) {
-        log::info!("Switching active block from {:#?}", self.route.active_block);
-        self.route.active_block = match self.route.active_block {
-            ActiveBlock::Directory | ActiveBlock::Popup => self.route.hovered_block,
-            _ => ActiveBlock::Directory,
-        };
-        log::info!("to {:#?}", self.route.active_block);
-    }
-
-    pub async fn select(&mut self) {
-        match self.route.active_block {
-            ActiveBlock::Directory => {
-                match self.directory.list_state.selected() {
-                    Some(0) => self.route.active_block = ActiveBlock::UserPlaylists,
-                    Some(1) => self.route.active_block = ActiveBlock::UserTopTracks,
-                    Some(2) => self.route.active_block = ActiveBlock::UserTopArtists,
-                    _ => {}
+            AppEvent::Select => self.select().await,
+            AppEvent::Next => self.next().await,
+            AppEvent::Back => self.go_back().await,
+            AppEvent::Forward => self.go_forward().await,
+            AppEvent::SyncSmartPlaylists => self.sync_smart_playlists(),
+            AppEvent::Progress(progress) => self.progress = Some(progress),
+            AppEvent::ProgressDone(summary) => {
+                log::info!("{summary}");
+                self.progress = None;
+                if let Err(e) = self.refresh_user_playlists().await {
+                    log::error!("Failed to refresh playlists: {e}");
                 }
-                log::info!(
-                    "Directory selected: {}",
-                    DIRECTORY[self.directory.list_state.selected().unwrap()]
-                );
-                self.route.hovered_block = self.route.active_block
-            }
-            ActiveBlock::UserPlaylists => {
-                match self.user_library.user_playlists.list_state.selected() {
-                    Some(i) => {
-                        let playlist = spotify_rs::playlist(
-                            self.user_library.user_playlists.list[i]
-                                .as_ref()
-                                .unwrap()
-                                .clone()
-                                .id,
-                        )
-                        .get(&self.spotify_client)
-                        .await
-                        .unwrap();
-
-                        self.playlist.result = Some(playlist.clone());
-                        self.playlist.pages.list = playlist.tracks.items;
-                        self.playlist.pages.total = usize::try_from(playlist.tracks.total).unwrap();
-                        self.playlist.list_state = ListState::default();
-                        log::debug!(
-                            "Playlist selected: {:#?}",
-                            self.playlist.result.as_ref().unwrap()
-                        );
-                    }
-                    _ => {}
+                self.liked.clear();
+                if let Err(e) = self.reload_playlist().await {
+                    log::error!("Failed to reload playlist: {e}");
                 }
-                self.route.active_block = ActiveBlock::Playlist;
-                self.route.hovered_block = ActiveBlock::Playlist;
-                log::info!("User Playlist selected");
+                self.check_liked(ListKind::UserTopTracks);
             }
-            //TODO: Implement Track Selection
-            ActiveBlock::UserTopTracks => {
-                match self.user_library.user_top_tracks.list_state.selected() {
-                    Some(i) => {
-                        self.track_popup.list_state.select(Some(0));
-                        self.route.active_block = ActiveBlock::Popup;
-                        log::debug!(
-                            "UserTopTracks Track selected: {:#?}",
-                            self.user_library.user_top_tracks.list[i].as_ref().unwrap()
-                        );
-                    }
-                    _ => log::info!("No Track selected"),
-                }
+            AppEvent::UserFetched(user) => self.user = Some(*user),
+            AppEvent::ListFetched(page) => self.replace_list(page),
+            AppEvent::PageLoaded(page) => self.append_page(page),
+            AppEvent::PagesDone(kind) => self.pages_done(kind),
+            AppEvent::LikedChecked(results) => self.liked.extend(results),
+            AppEvent::RunCommand(invocation) => self.run_command(invocation).await,
+            AppEvent::DevicesLoaded(devices) => {
+                self.devices = devices;
+                self.refresh_palette_choices(Argument::Device);
             }
-            //TODO: Implement Artist Selection
-            ActiveBlock::UserTopArtists => match self.selected_state.selected() {
-                _ => log::info!("Artist selected"),
-            },
-            //TODO: Implement Track Selection
-            ActiveBlock::Playlist => match self.playlist.list_state.selected() {
-                Some(i) => {
-                    self.track_popup.list_state.select(Some(0));
-                    self.route.active_block = ActiveBlock::Popup;
-                    log::debug!(
-                        "Playlist Track selected: {:#?}",
-                        self.playlist.pages.list[i].as_ref().unwrap().track
-                    );
+            AppEvent::ArtworkLoaded(url, image) => self.artwork.loaded(url, image),
+            AppEvent::Remote(request) => self.handle_remote(request).await,
+            AppEvent::PlaybackPolled(status) => self.update_playback(*status),
+            AppEvent::ProfileSignedIn(name, spotify) => {
+                self.profile_signed_in(&name, spotify).await
+            }
+            AppEvent::ReloadList(kind) => {
+                if let Err(e) = self.reload_list(kind).await {
+                    log::error!("Failed to reload {kind:?}: {e}");
                 }
-                _ => log::info!("No Playlist Track selected"),
-            },
-            //TODO: Implement Artist block
-            ActiveBlock::Artist => {
-                log::info!("Artist block selected");
             }
-            ActiveBlock::Popup => {
-                log::debug!(
-                    "TRACK_OPTIONS selected: {}",
-                    TRACK_OPTIONS[self.track_popup.list_state.selected().unwrap()]
-                );
-                self.route.active_block = self.route.hovered_block;
+            AppEvent::Profile(generation, app_event) => {
+                if generation == self.profile_generation {
+                    Box::pin(self.handle_app_event(*app_event)).await;
+                } else {
+                    log::debug!("Dropping an event of a profile switched away from");
+                }
             }
-            _ => { /* Do nothing */ }
-        };
+        }
     }
 
-    pub fn up(&mut self) {
-        log::info!("Up in {:#?}", self.route.active_block);
-        match self.route.active_block {
-            ActiveBlock::Directory => {
-                self.directory.list_state.select_previous();
-            }
-            ActiveBlock::UserPlaylists => {
-                self.user_library
-                    .user_playlists
-                    .list_state
-                    .select_previous();
-            }
-            ActiveBlock::UserTopTracks => {
-                self.user_library
-                    .user_top_tracks
-                    .list_state
-                    .select_previous();
-            }
-            ActiveBlock::UserTopArtists => {
-                self.user_library
-                    .user_top_artists
-                    .list_state
-                    .select_previous();
-            }
-            ActiveBlock::Playlist => {
-                if self.playlist.pages.list.is_empty() {
-                    return;
-                }
-                self.playlist.list_state.select_previous();
-            }
-            //TODO: Implement Artist block
-            ActiveBlock::Artist => { /* Not implemented yet */ }
-            ActiveBlock::Popup => {
-                self.track_popup.list_state.select_previous();
+    pub fn handle_key_events(&mut self, key_event: KeyEvent) -> color_eyre::Result<()> {
+        log::debug!("Key event: {:#?}", key_event);
+        if self.route.active_block == ActiveBlock::Palette {
+            self.handle_palette_key(key_event);
+            return Ok(());
+        }
+        if let Some(kind) = self.active_list_kind()
+            && self.list_cursor(kind).filter.editing
+        {
+            self.handle_filter_key(kind, key_event);
+            return Ok(());
+        }
+        if self.route.active_block == ActiveBlock::Help && self.help.filter.editing {
+            match FilterInput::apply(&mut self.help.filter, key_event) {
+                FilterInput::Changed => self.apply_help_filter(),
+                FilterInput::Up => self.up(),
+                FilterInput::Down => self.down(),
+                FilterInput::Quit => self.events.send(AppEvent::Quit),
+                FilterInput::Ignored => {}
             }
-            ActiveBlock::Logger => {
-                self.logger_state
-                    .transition(tui_logger::TuiWidgetEvent::PrevPageKey);
+            return Ok(());
+        }
+        self.pending_keys.push(KeyChord::from(key_event));
+        let block = self.route.active_block;
+        let mut resolved = self.keymap.resolve(block, &self.pending_keys);
+        if resolved == Resolved::Unbound && self.pending_keys.len() > 1 {
+            // A key that doesn't continue the sequence starts a new one.
+            self.pending_keys.drain(..self.pending_keys.len() - 1);
+            resolved = self.keymap.resolve(block, &self.pending_keys);
+        }
+        match resolved {
+            Resolved::Action(action) => {
+                self.pending_keys.clear();
+                self.perform(action);
             }
+            Resolved::Pending => {}
+            Resolved::Unbound => self.pending_keys.clear(),
         }
+        Ok(())
     }
 
-    pub fn down(&mut self) {
-        log::info!("Down in {:#?}", self.route.active_block);
-        match self.route.active_block {
-            ActiveBlock::Directory => {
-                if self.directory.list_state.selected() <= Some(DIRECTORY.len() - 2) {
-                    self.directory.list_state.select_next();
+    pub fn perform(&mut self, action: Action) {
+        log::debug!("Action: {}", action.name());
+        match action {
+            Action::Cancel if self.route.active_block == ActiveBlock::Help => {
+                if self.help.filter.is_active() {
+                    self.help.filter.clear();
+                    self.apply_help_filter();
+                } else {
+                    self.toggle_help();
                 }
             }
-            ActiveBlock::UserPlaylists => {
-                if self.user_library.user_playlists.list_state.selected()
-                    <= Some(self.user_library.user_playlists.total - 2)
+            Action::Cancel if self.active_list().is_some_and(|l| l.filter.is_active()) => {
+                if let Some(list) = self.active_list() {
+                    list.filter.clear();
+                }
+            }
+            Action::Cancel if self.active_list().is_some_and(|l| !l.selection.is_empty()) => {
+                if let Some(list) = self.active_list() {
+                    list.selection.clear();
+                }
+            }
+            Action::Cancel | Action::Quit => self.events.send(AppEvent::Quit),
+            Action::NextBlock => self.events.send(AppEvent::Next),
+            Action::Up => self.up(),
+            Action::Down => self.down(),
+            Action::Top => self.jump(false),
+            Action::Bottom => self.jump(true),
+            Action::Select => self.events.send(AppEvent::Select),
+            Action::ToggleMark => {
+                if let Some(list) = self.active_list()
+                    && let Some(i) = list.list_state.selected()
                 {
-                    self.user_library.user_playlists.list_state.select_next();
+                    list.selection.toggle(i);
                 }
             }
-            ActiveBlock::UserTopTracks => {
-                if self.user_library.user_top_tracks.list_state.selected()
-                    <= Some(self.user_library.user_top_tracks.total - 2)
+            Action::MarkRange => {
+                if let Some(list) = self.active_list()
+                    && let Some(i) = list.list_state.selected()
                 {
-                    self.user_library.user_top_tracks.list_state.select_next();
+                    let filter = &*list.filter;
+                    list.selection.toggle_range(i, |i| filter.is_visible(i));
                 }
             }
-            ActiveBlock::UserTopArtists => {
-                if self.user_library.user_top_artists.list_state.selected()
-                    <= Some(self.user_library.user_top_artists.total - 2)
+            Action::CycleSort => {
+                if let Some(kind) = self.active_list_kind() {
+                    self.cycle_sort(kind);
+                }
+            }
+            Action::ReverseSort => {
+                if let Some(kind) = self.active_list_kind()
+                    && let Some(sort) = self.list_cursor(kind).sort
                 {
-                    self.user_library.user_top_artists.list_state.select_next();
+                    sort.descending = !sort.descending;
+                    self.apply_sort(kind);
                 }
             }
-            ActiveBlock::Playlist => {
-                if self.playlist.pages.list.is_empty() {
-                    return;
+            Action::Filter if self.route.active_block == ActiveBlock::Help => {
+                self.help.filter.editing = true;
+            }
+            Action::Filter => {
+                if let Some(kind) = self.active_list_kind() {
+                    self.list_cursor(kind).filter.editing = true;
+                    self.load_remaining(kind);
                 }
-                if self.playlist.list_state.selected() <= Some(self.playlist.pages.total - 2) {
-                    self.playlist.list_state.select_next();
+            }
+            Action::PlaylistOperations => {
+                if self.route.active_block == ActiveBlock::UserPlaylists {
+                    self.playlist_popup.list_state.select(Some(0));
+                    self.route.active_block = ActiveBlock::PlaylistPopup;
                 }
             }
-            //TODO: Implement Artist block
-            ActiveBlock::Artist => { /* Not implemented yet */ }
-            ActiveBlock::Popup => {
-                if self.track_popup.list_state.selected() <= Some(self.track_popup.list.len() - 2) {
-                    self.track_popup.list_state.select_next();
+            Action::MainTab => {
+                self.selected_tab = SelectedTab::Main;
+                self.route.active_block = self.route.hovered_block;
+            }
+            Action::LogTab => {
+                self.selected_tab = SelectedTab::Logger;
+                self.route.active_block = ActiveBlock::Logger;
+            }
+            Action::Help => self.toggle_help(),
+            Action::CommandPalette => self.open_palette(),
+            Action::Back => self.events.send(AppEvent::Back),
+            Action::Forward => self.events.send(AppEvent::Forward),
+            Action::ToggleSidebar => {
+                self.panes.toggle_sidebar();
+                // A hidden sidebar is still shown while focused.
+                if self.panes.sidebar_hidden() && self.route.active_block == ActiveBlock::Directory
+                {
+                    self.route.active_block = self.route.hovered_block;
                 }
+                self.save_panes();
             }
-            ActiveBlock::Logger => {
-                self.logger_state
-                    .transition(tui_logger::TuiWidgetEvent::NextPageKey);
+            Action::ShrinkSidebar | Action::GrowSidebar => {
+                if self.panes.resize_sidebar(action == Action::GrowSidebar) {
+                    self.save_panes();
+                }
             }
         }
     }
 
-    pub fn tick(&self) {}
+    fn save_panes(&self) {
+        if let Err(e) = self.panes.save() {
+            log::error!("Failed to save pane sizes: {e:#}");
+        }
+    }
 
-    pub fn quit(&mut self) {
-        log::info!("Quitting application");
-        self.running = false;
+    /// The view shown in the main content area, with its cursor and scroll position.
+    pub fn current_visit(&self) -> Option<Visit> {
+        let (view, list_state) = match self.route.hovered_block {
+            ActiveBlock::UserPlaylists => (
+                View::UserPlaylists,
+                &self.user_library.user_playlists.list_state,
+            ),
+            ActiveBlock::UserTopTracks => (
+                View::UserTopTracks,
+                &self.user_library.user_top_tracks.list_state,
+            ),
+            ActiveBlock::UserTopArtists => (
+                View::UserTopArtists,
+                &self.user_library.user_top_artists.list_state,
+            ),
+            ActiveBlock::Playlist => {
+                let playlist = self.playlist.result.as_ref()?;
+                let view = View::Playlist {
+                    id: playlist.id.clone(),
+                    name: playlist.name.clone(),
+                };
+                (view, &self.playlist.list_state)
+            }
+            _ => return None,
+        };
+        Some(Visit {
+            view,
+            selected: list_state.selected(),
+            offset: list_state.offset(),
+        })
     }
 
-    pub async fn init(&mut self) -> color_eyre::Result<()> {
-        let spotify_client = &self.spotify_client;
-        self.user = Some(spotify_rs::get_current_user_profile(spotify_client).await?);
+    /// Shows `view`, recording the view it replaces in the history.
+    pub async fn navigate(&mut self, view: View) {
+        let current = self.current_visit();
+        if let Err(e) = self.show(&view, None).await {
+            log::error!("Failed to open {}: {e}", view.title());
+            return;
+        }
+        if let Some(current) = current.filter(|c| c.view != view) {
+            self.history.push(current);
+        }
+    }
 
-        let playlists = spotify_rs::current_user_playlists()
-            .get(spotify_client)
-            .await?;
-        self.user_library.user_playlists.page = Some(playlists.clone());
-        self.user_library.user_playlists.total = usize::try_from(playlists.total)?;
-        self.user_library.user_playlists.list = playlists.items;
+    async fn go_back(&mut self) {
+        let current = self.current_visit();
+        if let Some(visit) = self.history.back(current) {
+            self.restore(visit).await;
+        }
+    }
 
-        let top_tracks = spotify_rs::current_user_top_tracks()
-            .get(spotify_client)
-            .await?;
-        self.user_library.user_top_tracks.page = Some(top_tracks.clone());
-        self.user_library.user_top_tracks.total = usize::try_from(top_tracks.total)?;
-        self.user_library.user_top_tracks.list = top_tracks.items;
+    async fn go_forward(&mut self) {
+        let current = self.current_visit();
+        if let Some(visit) = self.history.forward(current) {
+            self.restore(visit).await;
+        }
+    }
 
-        let top_artists = spotify_rs::current_user_top_artists()
-            .get(spotify_client)
-            .await?;
-        self.user_library.user_top_artists.page = Some(top_artists.clone());
-        self.user_library.user_top_artists.total = usize::try_from(top_artists.total)?;
-        self.user_library.user_top_artists.list = top_artists.items;
+    async fn restore(&mut self, visit: Visit) {
+        if let Err(e) = self
+            .show(&visit.view, Some((visit.selected, visit.offset)))
+            .await
+        {
+            log::error!("Failed to open {}: {e}", visit.view.title());
+        }
+    }
+
+    /// Focuses `view`, loading it if needed. `position` restores the cursor
+    /// and scroll offset it was left at; without it a playlist starts at the top.
+    async fn show(
+        &mut self,
+        view: &View,
+        position: Option<(Option<usize>, usize)>,
+    ) -> color_eyre::Result<()> {
+        if let View::Playlist { id, .. } = view {
+            let loaded = self.playlist.result.as_ref().is_some_and(|p| p.id == *id);
+            if !loaded || position.is_none() {
+                self.load_playlist(id).await?;
+                self.playlist.list_state = ListState::default();
+            }
+        }
+        let block = view.block();
+        if let Some(i) = [
+            ActiveBlock::UserPlaylists,
+            ActiveBlock::UserTopTracks,
+            ActiveBlock::UserTopArtists,
+        ]
+        .iter()
+        .position(|&b| b == block)
+        {
+            self.directory.list_state.select(Some(i));
+        }
+        self.selected_tab = SelectedTab::Main;
+        self.route.active_block = block;
+        self.route.hovered_block = block;
+        if let (Some((selected, offset)), Some(list)) = (position, self.active_list()) {
+            let selected = selected
+                .filter(|_| list.len > 0)
+                .map(|s| s.min(list.len - 1));
+            *list.list_state = ListState::default()
+                .with_offset(offset)
+                .with_selected(selected);
+        }
+        Ok(())
+    }
+
+    fn open_palette(&mut self) {
+        let palette = &mut self.palette;
+        palette.context = self.route.active_block;
+        palette.pending = None;
+        palette.filter = ListFilter {
+            editing: true,
+            ..ListFilter::default()
+        };
+        let recent = palette.history.iter().map(|invocation| PaletteEntry {
+            text: invocation.text(),
+            detail: "recent".to_string(),
+            invocation: invocation.clone(),
+        });
+        let commands = Command::all().into_iter().map(|command| PaletteEntry {
+            text: command.name().to_string(),
+            detail: command.description(),
+            invocation: Invocation {
+                command,
+                argument: None,
+            },
+        });
+        palette.entries = recent.chain(commands).map(Some).collect();
+        palette.list_state.select(Some(0));
+        self.route.active_block = ActiveBlock::Palette;
+    }
+
+    fn close_palette(&mut self) {
+        self.palette.filter.clear();
+        self.route.active_block = self.palette.context;
+    }
+
+    fn handle_palette_key(&mut self, key_event: KeyEvent) {
+        let control = key_event.modifiers == KeyModifiers::CONTROL;
+        let palette = &mut self.palette;
+        match key_event.code {
+            KeyCode::Char('c' | 'C') if control => self.events.send(AppEvent::Quit),
+            KeyCode::Char('p' | 'P') if control => self.up(),
+            KeyCode::Char('n' | 'N') if control => self.down(),
+            KeyCode::Up => self.up(),
+            KeyCode::Down => self.down(),
+            KeyCode::Esc => self.close_palette(),
+            KeyCode::Enter => self.accept_palette_entry(),
+            KeyCode::Backspace if palette.filter.query.is_empty() && palette.pending.is_some() => {
+                self.open_palette();
+            }
+            KeyCode::Backspace => {
+                palette.filter.query.pop();
+                self.apply_palette_filter();
+            }
+            KeyCode::Char(c) => {
+                palette.filter.query.push(c);
+                self.apply_palette_filter();
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_palette_filter(&mut self) {
+        let palette = &mut self.palette;
+        palette.filter.apply(&palette.entries);
+        let cursor = palette
+            .filter
+            .nearest(palette.list_state.selected(), palette.entries.len());
+        palette.list_state.select(cursor);
+    }
+
+    /// Runs the entry under the cursor, or asks for its argument first.
+    fn accept_palette_entry(&mut self) {
+        let palette = &self.palette;
+        let invocation = match (palette.pending, palette.pending.and_then(Command::argument)) {
+            (Some(command), Some(Argument::Path)) => {
+                let path = palette.filter.query.trim();
+                if path.is_empty() {
+                    return;
+                }
+                Invocation {
+                    command,
+                    argument: Some(Choice {
+                        label: path.to_string(),
+                        value: path.to_string(),
+                    }),
+                }
+            }
+            _ => {
+                let Some(entry) = palette
+                    .list_state
+                    .selected()
+                    .filter(|&i| palette.filter.is_visible(i))
+                    .and_then(|i| palette.entries.get(i).cloned().flatten())
+                else {
+                    return;
+                };
+                entry.invocation
+            }
+        };
+        let command = invocation.command;
+        match command.argument() {
+            Some(argument) if invocation.argument.is_none() => {
+                self.palette.pending = Some(command);
+                self.palette.filter = ListFilter {
+                    editing: true,
+                    ..ListFilter::default()
+                };
+                if argument == Argument::Path {
+                    self.palette.filter.query =
+                        format!("{}.csv", self.route.hovered_block.config_name());
+                }
+                self.prepare_argument(argument);
+                self.refresh_palette_choices(argument);
+            }
+            _ => {
+                let history = &mut self.palette.history;
+                history.retain(|recent| *recent != invocation);
+                history.insert(0, invocation.clone());
+                history.truncate(command::HISTORY_LEN);
+                self.close_palette();
+                self.events.send(AppEvent::RunCommand(invocation));
+            }
+        }
+    }
 
-        log::info!(
-            "Initialized user data for {:#?}",
-            self.user.as_ref().unwrap().display_name
-        );
+    /// Starts fetching whatever the choices for `argument` depend on.
+    fn prepare_argument(&mut self, argument: Argument) {
+        match argument {
+            Argument::Playlist | Argument::WritablePlaylist => {
+                self.load_remaining(ListKind::UserPlaylists)
+            }
+            Argument::Device => {
+                let Some(spotify_client) = self.online("list devices") else {
+                    return;
+                };
+                let sender = self.profile_sender();
+                tokio::spawn(async move {
+                    match spotify_rs::get_available_devices(&spotify_client).await {
+                        Ok(devices) => sender.send(AppEvent::DevicesLoaded(devices)),
+                        Err(e) => log::error!("Failed to load devices: {e}"),
+                    }
+                });
+            }
+            Argument::Section | Argument::Theme | Argument::Profile | Argument::Path => {}
+        }
+    }
+
+    /// Rebuilds the palette's rows if it is asking for `argument`.
+    fn refresh_palette_choices(&mut self, argument: Argument) {
+        let Some(command) = self
+            .palette
+            .pending
+            .filter(|&command| command.argument() == Some(argument))
+        else {
+            return;
+        };
+        let choices: Vec<Choice> = match argument {
+            Argument::Section => [
+                ActiveBlock::UserPlaylists,
+                ActiveBlock::UserTopTracks,
+                ActiveBlock::UserTopArtists,
+            ]
+            .iter()
+            .zip(DIRECTORY)
+            .map(|(block, label)| Choice {
+                label: label.to_string(),
+                value: block.config_name().to_string(),
+            })
+            .collect(),
+            Argument::Playlist => self
+                .user_library
+                .user_playlists
+                .list
+                .iter()
+                .flatten()
+                .map(|p| Choice {
+                    label: p.name.clone(),
+                    value: p.id.clone(),
+                })
+                .collect(),
+            Argument::WritablePlaylist => self
+                .writable_playlists()
+                .into_iter()
+                .map(|p| Choice {
+                    label: p.name,
+                    value: p.id,
+                })
+                .collect(),
+            Argument::Device => self
+                .devices
+                .iter()
+                .filter_map(|d| {
+                    Some(Choice {
+                        label: d.name.clone(),
+                        value: d.id.clone()?,
+                    })
+                })
+                .collect(),
+            Argument::Theme => THEME_NAMES
+                .iter()
+                .map(|name| Choice {
+                    label: name.to_string(),
+                    value: name.to_string(),
+                })
+                .collect(),
+            Argument::Profile => Config::profiles()
+                .unwrap_or_else(|e| {
+                    log::error!("Failed to list profiles: {e:#}");
+                    vec![config::DEFAULT_PROFILE.to_string()]
+                })
+                .into_iter()
+                .map(|name| Choice {
+                    label: name.clone(),
+                    value: name,
+                })
+                .collect(),
+            Argument::Path => Vec::new(),
+        };
+        let palette = &mut self.palette;
+        palette.entries = choices
+            .into_iter()
+            .map(|choice| {
+                Some(PaletteEntry {
+                    text: choice.label.clone(),
+                    detail: String::new(),
+                    invocation: Invocation {
+                        command,
+                        argument: Some(choice),
+                    },
+                })
+            })
+            .collect();
+        self.apply_palette_filter();
+    }
+
+    pub async fn run_command(&mut self, invocation: Invocation) {
+        log::info!("Running command: {}", invocation.text());
+        let value = invocation
+            .argument
+            .map(|choice| (choice.label, choice.value));
+        match (invocation.command, value) {
+            (Command::Action(action), _) => self.perform(action),
+            (Command::GoTo, Some((_, section))) => {
+                let Some(view) = [
+                    View::UserPlaylists,
+                    View::UserTopTracks,
+                    View::UserTopArtists,
+                ]
+                .into_iter()
+                .find(|view| view.block().config_name() == section) else {
+                    return;
+                };
+                self.navigate(view).await;
+            }
+            (Command::OpenPlaylist, Some((name, id))) => {
+                self.navigate(View::Playlist { id, name }).await;
+            }
+            (Command::Track(TrackOption::AddToPlaylist), Some((name, id))) => {
+                let uris = self.take_selected_tracks();
+                self.run_track_action(TrackAction::AddToPlaylist { id, name }, uris);
+            }
+            (Command::Track(option), _) => self.select_track_option(option),
+            (Command::Playlists(op), _) => self.run_playlist_operation(op),
+            (Command::Device, Some((name, id))) => {
+                let Some(spotify_client) = self.online("switch devices") else {
+                    return;
+                };
+                tokio::spawn(async move {
+                    match spotify_rs::transfer_playback(id)
+                        .send(&spotify_client)
+                        .await
+                    {
+                        Ok(_) => log::info!("Playing on {name}"),
+                        Err(e) => log::error!("Failed to switch to {name}: {e}"),
+                    }
+                });
+            }
+            (Command::Theme, Some((name, _))) => match Theme::named(&name) {
+                Some(theme) if theme::no_color() => self.theme = theme.without_colors(),
+                Some(theme) => self.theme = theme,
+                None => log::error!("Unknown theme `{name}`"),
+            },
+            (Command::Export, Some((path, _))) => match self.export_view(&path) {
+                Ok(rows) => log::info!("Exported {rows} rows to {path}"),
+                Err(e) => log::error!("Export failed: {e:#}"),
+            },
+            (Command::SyncSmartPlaylists, _) => self.events.send(AppEvent::SyncSmartPlaylists),
+            (Command::Profile, Some((name, _))) => self.switch_profile(&name),
+            (command, None) => log::warn!("`{}` needs an argument", command.name()),
+        }
+    }
+
+    /// Carries out a command from the control socket. State queries and
+    /// subscriptions are answered by the socket itself.
+    async fn handle_remote(&mut self, request: Request) {
+        log::debug!("Control socket request: {request:?}");
+        let what = match &request {
+            Request::Navigate { view } => return self.navigate(view.clone()).await,
+            Request::State | Request::Subscribe => return,
+            Request::Play { .. } => "play",
+            Request::Pause => "pause",
+            Request::Next => "skip to the next track",
+            Request::Prev => "skip to the previous track",
+            Request::Queue { .. } => "queue",
+            Request::Seek { .. } => "seek",
+            Request::Volume { .. } => "set the volume",
+            Request::Shuffle { .. } => "toggle shuffle",
+            Request::Repeat { .. } => "set the repeat mode",
+        };
+        let Some(spotify_client) = self.online(what) else {
+            return;
+        };
+        let sender = self.profile_sender();
+        tokio::spawn(async move {
+            let result = match request {
+                Request::Play { uri } => api::play(&spotify_client, uri.as_deref()).await,
+                Request::Pause => spotify_rs::pause_playback(None, &spotify_client)
+                    .await
+                    .map(drop)
+                    .map_err(Into::into),
+                Request::Next => spotify_rs::skip_to_next(None, &spotify_client)
+                    .await
+                    .map(drop)
+                    .map_err(Into::into),
+                Request::Prev => spotify_rs::skip_to_previous(None, &spotify_client)
+                    .await
+                    .map(drop)
+                    .map_err(Into::into),
+                Request::Queue { uri } => spotify_rs::add_item_to_queue(uri)
+                    .send(&spotify_client)
+                    .await
+                    .map(drop)
+                    .map_err(Into::into),
+                Request::Seek { position_ms } => spotify_rs::seek_to_position(position_ms)
+                    .send(&spotify_client)
+                    .await
+                    .map(drop)
+                    .map_err(Into::into),
+                Request::Volume { percent } => {
+                    spotify_rs::set_playback_volume(percent.min(100).into())
+                        .send(&spotify_client)
+                        .await
+                        .map(drop)
+                        .map_err(Into::into)
+                }
+                Request::Shuffle { enabled } => spotify_rs::toggle_playback_shuffle(enabled)
+                    .send(&spotify_client)
+                    .await
+                    .map(drop)
+                    .map_err(Into::into),
+                Request::Repeat { mode } => spotify_rs::set_repeat_mode(match mode {
+                    Repeat::Off => RepeatMode::Off,
+                    Repeat::Track => RepeatMode::Track,
+                    Repeat::Context => RepeatMode::Context,
+                })
+                .send(&spotify_client)
+                .await
+                .map(drop)
+                .map_err(Into::into),
+                _ => Ok(()),
+            };
+            match result {
+                Ok(()) => {
+                    // Show the change without waiting for the next poll.
+                    tokio::time::sleep(PLAYBACK_SETTLE_DELAY).await;
+                    poll_playback(&spotify_client, &sender).await;
+                }
+                Err(e) => log::error!("Failed to {what}: {e}"),
+            }
+        });
+    }
+
+    /// Records the polled playback state, showing it over MPRIS and on the web
+    /// remote and telling subscribers of the control socket about a new track
+    /// or a pause. A new track also gets a desktop notification, except for
+    /// the one already playing at startup.
+    fn update_playback(&mut self, status: Status) {
+        let previous = self.playback.replace(status.clone());
+        self.playback_polled_at = Instant::now();
+        if let Some(mpris) = &self.mpris {
+            mpris.update(status.clone());
+        }
+        if let Some(web_remote) = &self.web_remote {
+            web_remote.update(&status);
+        }
+        let first_poll = previous.is_none();
+        let (uri, playing) = previous.map_or((None, false), |p| (p.uri, p.playing));
+        let notice = if uri != status.uri {
+            if !first_poll {
+                self.notifier.track_changed(&status);
+            }
+            Notice::TrackChanged { playback: status }
+        } else if playing != status.playing {
+            Notice::PlaybackState { playback: status }
+        } else {
+            return;
+        };
+        if let Some(remote) = &self.remote {
+            remote.notify(notice);
+        }
+    }
+
+    /// Hands what is shown and played to the control socket for `state` requests.
+    fn publish_state(&self) {
+        let Some(remote) = &self.remote else {
+            return;
+        };
+        let visit = self.current_visit();
+        remote.publish(ipc::State {
+            selected: visit.as_ref().and_then(|visit| visit.selected),
+            view: visit.map(|visit| visit.view),
+            playback: self.playback.clone(),
+        });
+    }
+
+    /// Writes the visible rows of the shown list to `path` as CSV.
+    fn export_view(&self, path: &str) -> color_eyre::Result<usize> {
+        fn write<T: TableRow + Clone>(
+            path: &str,
+            list: &PageEndpoint<T>,
+            filter: &ListFilter,
+            columns: &[Column],
+            liked: &HashMap<String, bool>,
+        ) -> color_eyre::Result<usize> {
+            export::write_csv(path.as_ref(), &list.list, columns, liked, |i| {
+                filter.is_visible(i)
+            })
+        }
+        let library = &self.user_library;
+        match self.route.hovered_block {
+            ActiveBlock::UserPlaylists => write(
+                path,
+                &library.user_playlists,
+                &library.user_playlists.filter,
+                &table::PLAYLIST_COLUMNS,
+                &self.liked,
+            ),
+            ActiveBlock::UserTopTracks => write(
+                path,
+                &library.user_top_tracks,
+                &library.user_top_tracks.filter,
+                &self.track_columns,
+                &self.liked,
+            ),
+            ActiveBlock::UserTopArtists => write(
+                path,
+                &library.user_top_artists,
+                &library.user_top_artists.filter,
+                &table::ARTIST_COLUMNS,
+                &self.liked,
+            ),
+            ActiveBlock::Playlist => write(
+                path,
+                &self.playlist.pages,
+                &self.playlist.filter,
+                &self.track_columns,
+                &self.liked,
+            ),
+            _ => Err(color_eyre::eyre::eyre!("Nothing to export here")),
+        }
+    }
+
+    /// Opens the help overlay for the focused block, or closes it.
+    fn toggle_help(&mut self) {
+        if self.route.active_block == ActiveBlock::Help {
+            self.route.active_block = self.help.context;
+            return;
+        }
+        let context = self.route.active_block;
+        self.help = HelpView {
+            rows: self
+                .keymap
+                .bindings(context)
+                .into_iter()
+                .map(Some)
+                .collect(),
+            list_state: ListState::default().with_selected(Some(0)),
+            filter: ListFilter::default(),
+            context,
+        };
+        self.route.active_block = ActiveBlock::Help;
+    }
+
+    fn apply_help_filter(&mut self) {
+        let help = &mut self.help;
+        help.filter.apply(&help.rows);
+        let cursor = help
+            .filter
+            .nearest(help.list_state.selected(), help.rows.len());
+        help.list_state.select(cursor);
+    }
+
+    pub async fn next(&mut self) {
+        log::info!("Switching active block from {:#?}", self.route.active_block);
+        self.route.active_block = match self.route.active_block {
+            ActiveBlock::Directory
+            | ActiveBlock::Popup
+            | ActiveBlock::PlaylistPopup
+            | ActiveBlock::PlaylistPicker => self.route.hovered_block,
+            _ => ActiveBlock::Directory,
+        };
+        log::info!("to {:#?}", self.route.active_block);
+    }
+
+    pub async fn select(&mut self) {
+        match self.route.active_block {
+            ActiveBlock::Directory => {
+                let view = match self.directory.list_state.selected() {
+                    Some(0) => View::UserPlaylists,
+                    Some(1) => View::UserTopTracks,
+                    Some(2) => View::UserTopArtists,
+                    _ => return,
+                };
+                log::info!("Directory selected: {}", view.title());
+                self.navigate(view).await;
+            }
+            ActiveBlock::UserPlaylists => {
+                let list = &self.user_library.user_playlists;
+                if let Some(playlist) = list
+                    .list_state
+                    .selected()
+                    .and_then(|i| list.list.get(i).cloned().flatten())
+                {
+                    log::info!("User Playlist selected: {}", playlist.name);
+                    self.navigate(View::Playlist {
+                        id: playlist.id,
+                        name: playlist.name,
+                    })
+                    .await;
+                }
+            }
+            //TODO: Implement Track Selection
+            ActiveBlock::UserTopTracks => {
+                match self.user_library.user_top_tracks.list_state.selected() {
+                    Some(i) => {
+                        self.track_popup.list_state.select(Some(0));
+                        self.route.active_block = ActiveBlock::Popup;
+                        log::debug!(
+                            "UserTopTracks Track selected: {:#?}",
+                            self.user_library.user_top_tracks.list[i].as_ref().unwrap()
+                        );
+                    }
+                    _ => log::info!("No Track selected"),
+                }
+            }
+            //TODO: Implement Artist Selection
+            ActiveBlock::UserTopArtists => log::info!("Artist selected"),
+            //TODO: Implement Track Selection
+            ActiveBlock::Playlist => match self.playlist.list_state.selected() {
+                Some(i) => {
+                    self.track_popup.list_state.select(Some(0));
+                    self.route.active_block = ActiveBlock::Popup;
+                    log::debug!(
+                        "Playlist Track selected: {:#?}",
+                        self.playlist.pages.list[i].as_ref().unwrap().track
+                    );
+                }
+                _ => log::info!("No Playlist Track selected"),
+            },
+            //TODO: Implement Artist block
+            ActiveBlock::Artist => {
+                log::info!("Artist block selected");
+            }
+            ActiveBlock::Popup => {
+                let option = TrackOption::ALL[self.track_popup.list_state.selected().unwrap()];
+                log::debug!("Track option selected: {}", option.label());
+                self.route.active_block = self.route.hovered_block;
+                self.select_track_option(option);
+            }
+            ActiveBlock::PlaylistPopup => {
+                if let Some(i) = self.playlist_popup.list_state.selected() {
+                    self.run_playlist_operation(PlaylistOperation::ALL[i]);
+                }
+                self.route.active_block = self.route.hovered_block;
+            }
+            ActiveBlock::PlaylistPicker => {
+                if let Some(playlist) = self
+                    .playlist_picker
+                    .list_state
+                    .selected()
+                    .and_then(|i| self.picker_playlists.get(i))
+                {
+                    let action = TrackAction::AddToPlaylist {
+                        id: playlist.id.clone(),
+                        name: playlist.name.clone(),
+                    };
+                    let uris = std::mem::take(&mut self.pending_tracks);
+                    self.run_track_action(action, uris);
+                }
+                self.route.active_block = self.route.hovered_block;
+            }
+            _ => { /* Do nothing */ }
+        };
+    }
+
+    /// Cover art of the row under the cursor in the shown list.
+    pub fn selected_image_url(&self) -> Option<&str> {
+        fn selected<'a, T: TableRow + Clone>(
+            items: &'a [Option<T>],
+            state: &ListState,
+        ) -> Option<&'a str> {
+            items.get(state.selected()?)?.as_ref()?.image_url()
+        }
+        let library = &self.user_library;
+        match self.route.hovered_block {
+            ActiveBlock::UserPlaylists => selected(
+                &library.user_playlists.list,
+                &library.user_playlists.list_state,
+            ),
+            ActiveBlock::UserTopTracks => selected(
+                &library.user_top_tracks.list,
+                &library.user_top_tracks.list_state,
+            ),
+            ActiveBlock::UserTopArtists => selected(
+                &library.user_top_artists.list,
+                &library.user_top_artists.list_state,
+            ),
+            ActiveBlock::Playlist => selected(&self.playlist.pages.list, &self.playlist.list_state),
+            _ => None,
+        }
+    }
+
+    /// Stores where the shown list was scrolled to when drawn, so scrolling
+    /// carries on from there and the offset is kept with the visit.
+    fn keep_scroll_offset(&mut self) {
+        let (min_width, min_height) = panes::MIN_SIZE;
+        let content = self.areas(self.area).content;
+        if !matches!(self.selected_tab, SelectedTab::Main)
+            || self.area.width < min_width
+            || self.area.height < min_height
+            || content.is_empty()
+        {
+            return;
+        }
+        let kind = match self.route.hovered_block {
+            ActiveBlock::UserPlaylists => ListKind::UserPlaylists,
+            ActiveBlock::UserTopTracks => ListKind::UserTopTracks,
+            ActiveBlock::UserTopArtists => ListKind::UserTopArtists,
+            ActiveBlock::Playlist => ListKind::Playlist,
+            _ => return,
+        };
+        let list = self.list_cursor(kind);
+        let visible: Vec<usize> = (0..list.len)
+            .filter(|&i| list.filter.is_visible(i))
+            .collect();
+        if visible.is_empty() {
+            return;
+        }
+        let cursor = list
+            .list_state
+            .selected()
+            .and_then(|c| visible.iter().position(|&r| r == c));
+        // The table's borders and header take three rows.
+        let height = usize::from(content.height.saturating_sub(3));
+        *list.list_state.offset_mut() =
+            scrolled_offset(list.list_state.offset(), visible.len(), cursor, height);
+    }
+
+    /// The paged list shown in the focused block, if any.
+    pub fn active_list_kind(&self) -> Option<ListKind> {
+        match self.route.active_block {
+            ActiveBlock::UserPlaylists => Some(ListKind::UserPlaylists),
+            ActiveBlock::UserTopTracks => Some(ListKind::UserTopTracks),
+            ActiveBlock::UserTopArtists => Some(ListKind::UserTopArtists),
+            ActiveBlock::Playlist => Some(ListKind::Playlist),
+            _ => None,
+        }
+    }
+
+    pub fn active_list(&mut self) -> Option<ListCursor<'_>> {
+        self.active_list_kind().map(|kind| self.list_cursor(kind))
+    }
+
+    pub fn list_cursor(&mut self, kind: ListKind) -> ListCursor<'_> {
+        fn cursor<T: Clone>(list: &mut PageEndpoint<T>) -> ListCursor<'_> {
+            let has_more = list.has_more();
+            ListCursor {
+                list_state: &mut list.list_state,
+                selection: &mut list.selection,
+                filter: &mut list.filter,
+                sort: &mut list.sort,
+                has_more,
+                len: list.list.len(),
+            }
+        }
+        match kind {
+            ListKind::UserPlaylists => cursor(&mut self.user_library.user_playlists),
+            ListKind::UserTopTracks => cursor(&mut self.user_library.user_top_tracks),
+            ListKind::UserTopArtists => cursor(&mut self.user_library.user_top_artists),
+            ListKind::Playlist => {
+                let has_more = self.playlist.pages.has_more();
+                ListCursor {
+                    list_state: &mut self.playlist.list_state,
+                    selection: &mut self.playlist.selection,
+                    filter: &mut self.playlist.filter,
+                    sort: &mut self.playlist.pages.sort,
+                    has_more,
+                    len: self.playlist.pages.list.len(),
+                }
+            }
+        }
+    }
+
+    fn handle_filter_key(&mut self, kind: ListKind, key_event: KeyEvent) {
+        match FilterInput::apply(self.list_cursor(kind).filter, key_event) {
+            FilterInput::Changed => self.apply_filter(kind),
+            FilterInput::Up => self.up(),
+            FilterInput::Down => self.down(),
+            FilterInput::Quit => self.events.send(AppEvent::Quit),
+            FilterInput::Ignored => {}
+        }
+    }
+
+    /// Re-evaluates the filter of `kind` and moves the cursor onto a visible row.
+    pub fn apply_filter(&mut self, kind: ListKind) {
+        match kind {
+            ListKind::UserPlaylists => {
+                let list = &mut self.user_library.user_playlists;
+                list.filter.apply(&list.list);
+            }
+            ListKind::UserTopTracks => {
+                let list = &mut self.user_library.user_top_tracks;
+                list.filter.apply(&list.list);
+            }
+            ListKind::UserTopArtists => {
+                let list = &mut self.user_library.user_top_artists;
+                list.filter.apply(&list.list);
+            }
+            ListKind::Playlist => self.playlist.filter.apply(&self.playlist.pages.list),
+        }
+        let list = self.list_cursor(kind);
+        list.selection.anchor = None;
+        let cursor = list.filter.nearest(list.list_state.selected(), list.len);
+        list.list_state.select(cursor);
+    }
+
+    /// Fetches the pages of `kind` that haven't been loaded yet in the background.
+    pub fn load_remaining(&mut self, kind: ListKind) {
+        let Some(spotify) = self.online("load the rest of the list") else {
+            return;
+        };
+        let sender = self.profile_sender();
+        match kind {
+            ListKind::UserPlaylists => spawn_page_loader(
+                &mut self.user_library.user_playlists,
+                kind,
+                spotify,
+                sender,
+                ListPage::UserPlaylists,
+            ),
+            ListKind::UserTopTracks => spawn_page_loader(
+                &mut self.user_library.user_top_tracks,
+                kind,
+                spotify,
+                sender,
+                ListPage::UserTopTracks,
+            ),
+            ListKind::UserTopArtists => spawn_page_loader(
+                &mut self.user_library.user_top_artists,
+                kind,
+                spotify,
+                sender,
+                ListPage::UserTopArtists,
+            ),
+            ListKind::Playlist => {
+                let Some(id) = self.playlist.result.as_ref().map(|p| p.id.clone()) else {
+                    return;
+                };
+                spawn_page_loader(
+                    &mut self.playlist.pages,
+                    kind,
+                    spotify,
+                    sender,
+                    move |page| ListPage::Playlist(id.clone(), page),
+                )
+            }
+        }
+    }
+
+    fn append_page(&mut self, page: ListPage) {
+        let (kind, appended) = match page {
+            ListPage::UserPlaylists(page) => (
+                ListKind::UserPlaylists,
+                self.user_library.user_playlists.append(page),
+            ),
+            ListPage::UserTopTracks(page) => (
+                ListKind::UserTopTracks,
+                self.user_library.user_top_tracks.append(page),
+            ),
+            ListPage::UserTopArtists(page) => (
+                ListKind::UserTopArtists,
+                self.user_library.user_top_artists.append(page),
+            ),
+            ListPage::Playlist(id, page) => {
+                let current = self.playlist.result.as_ref().is_some_and(|p| p.id == id);
+                (
+                    ListKind::Playlist,
+                    current && self.playlist.pages.append(page),
+                )
+            }
+        };
+        if appended && self.list_cursor(kind).filter.is_active() {
+            self.apply_filter(kind);
+        }
+        if appended {
+            self.check_liked(kind);
+        }
+        if appended && kind == ListKind::UserPlaylists {
+            self.refresh_palette_choices(Argument::Playlist);
+            self.refresh_palette_choices(Argument::WritablePlaylist);
+        }
+    }
+
+    /// Looks up which tracks of `kind` are liked, if the liked column is shown.
+    /// Tracks that were already looked up are skipped.
+    pub fn check_liked(&mut self, kind: ListKind) {
+        if !self.track_columns.contains(&Column::Liked) {
+            return;
+        }
+        let ids: Vec<String> = match kind {
+            ListKind::UserTopTracks => self
+                .user_library
+                .user_top_tracks
+                .list
+                .iter()
+                .flatten()
+                .map(|t| t.id.clone())
+                .collect(),
+            ListKind::Playlist => self
+                .playlist
+                .pages
+                .list
+                .iter()
+                .flatten()
+                .filter_map(|item| match &item.track {
+                    PlayableItem::Track(t) => Some(t.id.clone()),
+                    PlayableItem::Episode(_) => None,
+                })
+                .collect(),
+            ListKind::UserPlaylists | ListKind::UserTopArtists => return,
+        };
+        let ids: Vec<String> = ids
+            .into_iter()
+            .filter(|id| !self.liked.contains_key(id))
+            .collect();
+        if ids.is_empty() {
+            return;
+        }
+        let Some(spotify) = self.spotify_client.clone() else {
+            return;
+        };
+        // Mark as not liked until the lookup answers, so it isn't repeated.
+        self.liked.extend(ids.iter().map(|id| (id.clone(), false)));
+        let sender = self.profile_sender();
+        tokio::spawn(async move {
+            match api::liked_tracks(&spotify, &ids).await {
+                Ok(results) => sender.send(AppEvent::LikedChecked(results)),
+                Err(e) => log::error!("Failed to check liked tracks: {e}"),
+            }
+        });
+    }
+
+    fn pages_done(&mut self, kind: ListKind) {
+        match kind {
+            ListKind::UserPlaylists => self.user_library.user_playlists.loading = false,
+            ListKind::UserTopTracks => self.user_library.user_top_tracks.loading = false,
+            ListKind::UserTopArtists => self.user_library.user_top_artists.loading = false,
+            ListKind::Playlist => self.playlist.pages.loading = false,
+        }
+        self.apply_sort(kind);
+    }
+
+    /// Re-applies the sort and filter of `kind` after its list was replaced.
+    fn refresh_view(&mut self, kind: ListKind) {
+        let list = self.list_cursor(kind);
+        if list.sort.is_none() && !list.filter.is_active() {
+            return;
+        }
+        if list.has_more {
+            self.load_remaining(kind);
+        } else {
+            self.apply_sort(kind);
+        }
+        if self.list_cursor(kind).filter.is_active() {
+            self.apply_filter(kind);
+        }
+    }
+
+    /// Switches `kind` to the next sortable column, back to API order after the last one.
+    fn cycle_sort(&mut self, kind: ListKind) {
+        let columns: &[Column] = match kind {
+            ListKind::UserPlaylists => &[Column::Title, Column::Owner, Column::Tracks],
+            ListKind::UserTopTracks => &[
+                Column::Title,
+                Column::Artists,
+                Column::Album,
+                Column::Duration,
+                Column::Popularity,
+            ],
+            ListKind::UserTopArtists => &[Column::Title, Column::Popularity],
+            ListKind::Playlist => &[
+                Column::Title,
+                Column::Artists,
+                Column::Album,
+                Column::Duration,
+                Column::Added,
+                Column::Popularity,
+            ],
+        };
+        let list = self.list_cursor(kind);
+        let next = match *list.sort {
+            None => columns.first(),
+            Some(sort) => columns
+                .iter()
+                .position(|&c| c == sort.column)
+                .and_then(|i| columns.get(i + 1)),
+        };
+        *list.sort = next.map(|&column| SortOrder {
+            column,
+            descending: false,
+        });
+        match next {
+            Some(column) => log::info!("Sorting {kind:?} by {}", column.header()),
+            None => log::info!("{kind:?} back in original order"),
+        }
+        if next.is_none() {
+            // The original order is only known by fetching the list again.
+            self.events.send(AppEvent::ReloadList(kind));
+        } else if list.has_more {
+            self.load_remaining(kind);
+        } else {
+            self.apply_sort(kind);
+        }
+    }
 
+    /// Sorts the loaded rows of `kind`, keeping the cursor and selection on the same items.
+    pub fn apply_sort(&mut self, kind: ListKind) {
+        let Some(sort) = *self.list_cursor(kind).sort else {
+            return;
+        };
+        let new_index = match kind {
+            ListKind::UserPlaylists => sort_items(&mut self.user_library.user_playlists.list, sort),
+            ListKind::UserTopTracks => {
+                sort_items(&mut self.user_library.user_top_tracks.list, sort)
+            }
+            ListKind::UserTopArtists => {
+                sort_items(&mut self.user_library.user_top_artists.list, sort)
+            }
+            ListKind::Playlist => sort_items(&mut self.playlist.pages.list, sort),
+        };
+        let list = self.list_cursor(kind);
+        let cursor = list.list_state.selected().and_then(|i| new_index.get(i));
+        list.list_state.select(cursor.copied());
+        list.selection.marked = list
+            .selection
+            .marked
+            .iter()
+            .filter_map(|&i| new_index.get(i).copied())
+            .collect();
+        list.selection.anchor = None;
+        if list.filter.is_active() {
+            self.apply_filter(kind);
+        }
+    }
+
+    /// Takes the selection of the track list under the popup and returns the
+    /// URIs of the selected tracks, or the track under the cursor.
+    fn take_selected_tracks(&mut self) -> Vec<String> {
+        match self.route.hovered_block {
+            ActiveBlock::UserTopTracks => {
+                let list = &mut self.user_library.user_top_tracks;
+                let indices = list
+                    .selection
+                    .indices(list.list_state.selected(), |i| list.filter.is_visible(i));
+                list.selection.clear();
+                indices
+                    .into_iter()
+                    .filter_map(|i| list.list.get(i).cloned().flatten())
+                    .map(|track| track.uri)
+                    .collect()
+            }
+            ActiveBlock::Playlist => {
+                let playlist = &self.playlist;
+                let indices = playlist
+                    .selection
+                    .indices(playlist.list_state.selected(), |i| {
+                        playlist.filter.is_visible(i)
+                    });
+                self.playlist.selection.clear();
+                indices
+                    .into_iter()
+                    .filter_map(|i| self.playlist.pages.list.get(i).cloned().flatten())
+                    .map(|item| match item.track {
+                        PlayableItem::Track(t) => t.uri,
+                        PlayableItem::Episode(e) => e.uri,
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn select_track_option(&mut self, option: TrackOption) {
+        // Checked before the selection is taken, so it survives offline.
+        if !matches!(option, TrackOption::GoToArtist | TrackOption::GoToAlbum)
+            && self.online(option.label()).is_none()
+        {
+            return;
+        }
+        let action = match option {
+            TrackOption::Play => TrackAction::Play,
+            TrackOption::AddToQueue => TrackAction::Queue,
+            TrackOption::Like => TrackAction::Like,
+            TrackOption::AddToPlaylist => {
+                self.open_playlist_picker();
+                return;
+            }
+            TrackOption::RemoveFromPlaylist => {
+                let Some(playlist) = self
+                    .playlist
+                    .result
+                    .as_ref()
+                    .filter(|_| self.route.hovered_block == ActiveBlock::Playlist)
+                else {
+                    log::info!("Tracks can only be removed from an open playlist");
+                    return;
+                };
+                TrackAction::RemoveFromPlaylist {
+                    id: playlist.id.clone(),
+                    name: playlist.name.clone(),
+                }
+            }
+            //TODO: Implement Artist and Album navigation
+            TrackOption::GoToArtist | TrackOption::GoToAlbum => {
+                log::info!("{} is not implemented yet", option.label());
+                return;
+            }
+        };
+        let uris = self.take_selected_tracks();
+        self.run_track_action(action, uris);
+    }
+
+    /// The loaded playlists the user can add tracks to.
+    fn writable_playlists(&self) -> Vec<SimplifiedPlaylist> {
+        let Some(user_id) = self.user.as_ref().map(|u| u.id.as_str()) else {
+            return Vec::new();
+        };
+        self.user_library
+            .user_playlists
+            .list
+            .iter()
+            .flatten()
+            .filter(|p| p.owner.id == user_id || p.collaborative)
+            .cloned()
+            .collect()
+    }
+
+    fn open_playlist_picker(&mut self) {
+        self.picker_playlists = self.writable_playlists();
+        if self.picker_playlists.is_empty() {
+            log::info!("No playlists to add to");
+            return;
+        }
+        self.pending_tracks = self.take_selected_tracks();
+        self.playlist_picker.list = self
+            .picker_playlists
+            .iter()
+            .map(|p| ListItem::new(p.name.clone()))
+            .collect();
+        self.playlist_picker.list_state.select(Some(0));
+        self.route.active_block = ActiveBlock::PlaylistPicker;
+    }
+
+    pub fn run_track_action(&mut self, action: TrackAction, uris: Vec<String>) {
+        if self.progress.is_some() {
+            log::warn!("Another operation is still running");
+            return;
+        }
+        if uris.is_empty() {
+            log::info!("No tracks selected");
+            return;
+        }
+        let Some(spotify_client) = self.online(&action.label()) else {
+            return;
+        };
+        log::info!("{} on {} tracks", action.label(), uris.len());
+        self.progress = Some(Progress {
+            label: action.label(),
+            done: 0,
+            total: uris.len(),
+        });
+        let sender = self.profile_sender();
+        tokio::spawn(async move {
+            track_actions::run(action, &spotify_client, uris, sender).await;
+        });
+    }
+
+    pub fn run_playlist_operation(&mut self, op: PlaylistOperation) {
+        if self.progress.is_some() {
+            log::warn!("Another operation is still running");
+            return;
+        }
+        let Some(user_id) = self.user.as_ref().map(|u| u.id.clone()) else {
+            return;
+        };
+        let Some(spotify_client) = self.online(op.label()) else {
+            return;
+        };
+        let playlists = &mut self.user_library.user_playlists;
+        let indices = playlists
+            .selection
+            .indices(playlists.list_state.selected(), |i| {
+                playlists.filter.is_visible(i)
+            });
+        playlists.selection.clear();
+        let sources: Vec<SimplifiedPlaylist> = indices
+            .iter()
+            .filter_map(|&i| playlists.list.get(i).cloned().flatten())
+            .collect();
+        if sources.is_empty() {
+            log::info!("No playlists selected");
+            return;
+        }
+        log::info!("{} on {} playlists", op.label(), sources.len());
+        self.progress = Some(Progress {
+            label: op.label().to_string(),
+            done: 0,
+            total: 0,
+        });
+        let sender = self.profile_sender();
+        tokio::spawn(async move {
+            playlist_ops::run(op, &spotify_client, &user_id, sources, sender).await;
+        });
+    }
+
+    pub fn up(&mut self) {
+        log::info!("Up in {:#?}", self.route.active_block);
+        match self.route.active_block {
+            ActiveBlock::Directory => {
+                self.directory.list_state.select_previous();
+            }
+            ActiveBlock::UserPlaylists
+            | ActiveBlock::UserTopTracks
+            | ActiveBlock::UserTopArtists
+            | ActiveBlock::Playlist => self.step_cursor(false),
+            //TODO: Implement Artist block
+            ActiveBlock::Artist => { /* Not implemented yet */ }
+            ActiveBlock::Popup => {
+                self.track_popup.list_state.select_previous();
+            }
+            ActiveBlock::PlaylistPopup => {
+                self.playlist_popup.list_state.select_previous();
+            }
+            ActiveBlock::PlaylistPicker => {
+                self.playlist_picker.list_state.select_previous();
+            }
+            ActiveBlock::Logger => {
+                self.logger_state
+                    .transition(tui_logger::TuiWidgetEvent::PrevPageKey);
+            }
+            ActiveBlock::Help => {
+                let help = &mut self.help;
+                let cursor = help
+                    .filter
+                    .step(help.list_state.selected(), help.rows.len(), false);
+                help.list_state.select(cursor);
+            }
+            ActiveBlock::Palette => {
+                let palette = &mut self.palette;
+                let cursor = palette.filter.step(
+                    palette.list_state.selected(),
+                    palette.entries.len(),
+                    false,
+                );
+                palette.list_state.select(cursor);
+            }
+        }
+    }
+
+    pub fn down(&mut self) {
+        log::info!("Down in {:#?}", self.route.active_block);
+        match self.route.active_block {
+            ActiveBlock::Directory => {
+                if self.directory.list_state.selected() <= Some(DIRECTORY.len() - 2) {
+                    self.directory.list_state.select_next();
+                }
+            }
+            ActiveBlock::UserPlaylists
+            | ActiveBlock::UserTopTracks
+            | ActiveBlock::UserTopArtists
+            | ActiveBlock::Playlist => self.step_cursor(true),
+            //TODO: Implement Artist block
+            ActiveBlock::Artist => { /* Not implemented yet */ }
+            ActiveBlock::Popup => {
+                if self.track_popup.list_state.selected() <= Some(self.track_popup.list.len() - 2) {
+                    self.track_popup.list_state.select_next();
+                }
+            }
+            ActiveBlock::PlaylistPopup => {
+                if self.playlist_popup.list_state.selected()
+                    <= Some(self.playlist_popup.list.len() - 2)
+                {
+                    self.playlist_popup.list_state.select_next();
+                }
+            }
+            ActiveBlock::PlaylistPicker => {
+                if self.playlist_picker.list_state.selected()
+                    <= Some(self.playlist_picker.list.len() - 2)
+                {
+                    self.playlist_picker.list_state.select_next();
+                }
+            }
+            ActiveBlock::Logger => {
+                self.logger_state
+                    .transition(tui_logger::TuiWidgetEvent::NextPageKey);
+            }
+            ActiveBlock::Help => {
+                let help = &mut self.help;
+                let cursor = help
+                    .filter
+                    .step(help.list_state.selected(), help.rows.len(), true);
+                help.list_state.select(cursor);
+            }
+            ActiveBlock::Palette => {
+                let palette = &mut self.palette;
+                let cursor =
+                    palette
+                        .filter
+                        .step(palette.list_state.selected(), palette.entries.len(), true);
+                palette.list_state.select(cursor);
+            }
+        }
+    }
+
+    /// Progress of loading the remaining pages of the shown list, if any are loading.
+    pub fn loading_progress(&self) -> Option<Progress> {
+        match self.route.hovered_block {
+            ActiveBlock::UserPlaylists => self
+                .user_library
+                .user_playlists
+                .loading_progress("playlists"),
+            ActiveBlock::UserTopTracks => self
+                .user_library
+                .user_top_tracks
+                .loading_progress("top tracks"),
+            ActiveBlock::UserTopArtists => self
+                .user_library
+                .user_top_artists
+                .loading_progress("top artists"),
+            ActiveBlock::Playlist => self.playlist.pages.loading_progress("playlist"),
+            _ => None,
+        }
+    }
+
+    /// Moves the cursor of the focused list to the next or previous visible row.
+    fn step_cursor(&mut self, forward: bool) {
+        if let Some(list) = self.active_list() {
+            let cursor = list
+                .filter
+                .step(list.list_state.selected(), list.len, forward);
+            list.list_state.select(cursor);
+        }
+    }
+
+    /// Moves the cursor of the focused list to its first or last row.
+    fn jump(&mut self, to_end: bool) {
+        let nav = match self.route.active_block {
+            ActiveBlock::Directory => &mut self.directory,
+            ActiveBlock::Popup => &mut self.track_popup,
+            ActiveBlock::PlaylistPopup => &mut self.playlist_popup,
+            ActiveBlock::PlaylistPicker => &mut self.playlist_picker,
+            ActiveBlock::Help => {
+                let help = &mut self.help;
+                let len = help.rows.len();
+                let cursor = if to_end {
+                    (0..len).rev().find(|&i| help.filter.is_visible(i))
+                } else {
+                    help.filter.nearest(None, len)
+                };
+                help.list_state.select(cursor);
+                return;
+            }
+            _ => {
+                if let Some(list) = self.active_list() {
+                    let cursor = if to_end {
+                        (0..list.len).rev().find(|&i| list.filter.is_visible(i))
+                    } else {
+                        list.filter.nearest(None, list.len)
+                    };
+                    list.list_state.select(cursor);
+                }
+                return;
+            }
+        };
+        if !nav.list.is_empty() {
+            nav.list_state
+                .select(Some(if to_end { nav.list.len() - 1 } else { 0 }));
+        }
+    }
+
+    pub fn tick(&mut self) {
+        if self.user.is_some() && self.smart_playlists.is_due() {
+            self.smart_playlists.last_sync = Some(Instant::now());
+            self.events.send(AppEvent::SyncSmartPlaylists);
+        }
+        if self.notifier.is_due() {
+            self.notifier.send_pending();
+        }
+    }
+
+    pub fn sync_smart_playlists(&mut self) {
+        let Some(spotify_client) = self.spotify_client.clone() else {
+            log::debug!("Not syncing smart playlists offline");
+            return;
+        };
+        let Some(user_id) = self.user.as_ref().map(|u| u.id.clone()) else {
+            return;
+        };
+        let syncing = self.smart_playlists.syncing.clone();
+        if syncing.swap(true, Ordering::Relaxed) {
+            log::debug!("Smart playlist sync already running");
+            return;
+        }
+        let playlists = self.smart_playlists.playlists.clone();
+        log::info!("Syncing {} smart playlists", playlists.len());
+        tokio::spawn(async move {
+            match smart_sync::sync_all(&spotify_client, &user_id, &playlists).await {
+                Ok(reports) => {
+                    for report in reports {
+                        log::info!(
+                            "Smart playlist {} synced{}: +{} -{}",
+                            report.name,
+                            if report.created { " (created)" } else { "" },
+                            report.added,
+                            report.removed
+                        );
+                    }
+                }
+                Err(e) => log::error!("Smart playlist sync failed: {e}"),
+            }
+            syncing.store(false, Ordering::Relaxed);
+        });
+    }
+
+    /// The Spotify client, or `None` with a warning that `what` can't be done offline.
+    fn online(&self, what: &str) -> Option<Arc<Spotify>> {
+        if self.spotify_client.is_none() {
+            log::warn!("{what} isn't available offline");
+        }
+        self.spotify_client.clone()
+    }
+
+    /// Sends the events of background work for the profile in use.
+    fn profile_sender(&self) -> ProfileSender {
+        ProfileSender::new(self.events.sender(), self.profile_generation)
+    }
+
+    /// The Spotify client, or an error in offline mode.
+    fn client(&self) -> color_eyre::Result<&Spotify> {
+        self.spotify_client
+            .as_deref()
+            .ok_or_eyre("Not available offline")
+    }
+
+    pub fn quit(&mut self) {
+        log::info!("Quitting application");
+        self.save_profile();
+        self.running = false;
+    }
+
+    /// Saves the session and token of the profile in use.
+    fn save_profile(&self) {
+        // Before the library has loaded there's nothing worth keeping.
+        if self.user.is_some()
+            && let Err(e) = self.session().save()
+        {
+            log::error!("Failed to save session: {e:#}");
+        }
+        if let Some(spotify) = &self.spotify_client {
+            auth::save_token(spotify);
+        }
+    }
+
+    /// Leaves the profile in use for `name`, clearing everything shown from
+    /// it and signing in to `name` in the background. Its library is loaded
+    /// once that finishes, in [`Self::profile_signed_in`].
+    fn switch_profile(&mut self, name: &str) {
+        if name == Config::profile() {
+            log::info!("Already using profile {name}");
+            return;
+        }
+        self.save_profile();
+        if let Err(e) = Config::set_profile(name) {
+            log::error!("{e}");
+            return;
+        }
+        log::info!("Switching to profile {name}");
+        // Work still running for the old profile goes on, but what it sends
+        // is dropped. The playback poll stops once it notices the client is gone.
+        self.profile_generation += 1;
+        self.spotify_client = None;
+        self.web_remote = None;
+        let config = load_config();
+        self.keymap = load_keymap(&config);
+        self.theme = load_theme(&config);
+        self.track_columns = config.columns.tracks;
+        self.notifier = Notifier::from_config(&config.notifications);
+        self.panes = PaneLayout::load(&config.layout);
+        self.artwork = Artwork::new(&config.artwork);
+        self.smart_playlists = SmartPlaylists::from_config(&config.smart_playlists);
+        self.cache = Cache::open();
+        self.user_library = UserLibrary::new();
+        self.user = None;
+        self.playlist = TrackList::new();
+        self.liked.clear();
+        self.devices.clear();
+        self.picker_playlists.clear();
+        self.pending_tracks.clear();
+        self.progress = None;
+        self.playback = None;
+        self.history = History::default();
+        self.route = Route {
+            active_block: ActiveBlock::Directory,
+            hovered_block: ActiveBlock::UserPlaylists,
+        };
+        let sender = self.profile_sender();
+        let name = name.to_string();
+        tokio::spawn(async move {
+            // The terminal belongs to the interface, so this can only use a
+            // saved token or a browser.
+            let spotify = match auth::sign_in(SignIn::Background).await {
+                Ok(spotify) => Some(Arc::new(spotify)),
+                Err(e) => {
+                    log::error!("Failed to sign in to profile {name}, continuing offline: {e}");
+                    None
+                }
+            };
+            sender.send(AppEvent::ProfileSignedIn(name, spotify));
+        });
+    }
+
+    /// Loads the library of the profile switched to, with `spotify` signed in
+    /// to it or offline.
+    async fn profile_signed_in(&mut self, name: &str, spotify: Option<Arc<Spotify>>) {
+        if let Some(spotify) = &spotify {
+            log::info!("Signed in to profile {name}");
+            if self.mpris.is_none() {
+                self.mpris = Mpris::start(self.events.sender()).await;
+            }
+            self.web_remote = WebRemote::start(
+                &load_config().web_remote,
+                spotify.clone(),
+                self.events.sender(),
+            );
+        }
+        self.spotify_client = spotify;
+        self.init();
+        self.restore_session().await;
+    }
+
+    fn session(&self) -> Session {
+        let library = &self.user_library;
+        let lists = [
+            (View::UserPlaylists, &library.user_playlists.list_state),
+            (View::UserTopTracks, &library.user_top_tracks.list_state),
+            (View::UserTopArtists, &library.user_top_artists.list_state),
+        ];
+        Session {
+            logger: matches!(self.selected_tab, SelectedTab::Logger),
+            directory: self.route.active_block == ActiveBlock::Directory,
+            view: self.current_visit(),
+            lists: lists
+                .into_iter()
+                .map(|(view, state)| Visit {
+                    view,
+                    selected: state.selected(),
+                    offset: state.offset(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Goes back to where the last session ended. A playlist that can no
+    /// longer be opened, e.g. because it was deleted, leaves the playlists shown.
+    async fn restore_session(&mut self) {
+        let session = match Session::load() {
+            Ok(Some(session)) => session,
+            Ok(None) => return,
+            Err(e) => {
+                log::error!("Failed to restore session: {e:#}");
+                return;
+            }
+        };
+        for visit in session.lists {
+            let kind = match visit.view {
+                View::UserPlaylists => ListKind::UserPlaylists,
+                View::UserTopTracks => ListKind::UserTopTracks,
+                View::UserTopArtists => ListKind::UserTopArtists,
+                View::Playlist { .. } => continue,
+            };
+            let list = self.list_cursor(kind);
+            let selected = visit
+                .selected
+                .filter(|_| list.len > 0)
+                .map(|s| s.min(list.len - 1));
+            *list.list_state = ListState::default()
+                .with_offset(visit.offset)
+                .with_selected(selected);
+        }
+        if let Some(visit) = session.view
+            && let Err(e) = self
+                .show(&visit.view, Some((visit.selected, visit.offset)))
+                .await
+        {
+            log::warn!("Couldn't reopen {}: {e}", visit.view.title());
+            let _ = self.show(&View::UserPlaylists, None).await;
+        }
+        if session.directory {
+            self.route.active_block = ActiveBlock::Directory;
+        }
+        if session.logger {
+            self.selected_tab = SelectedTab::Logger;
+            self.route.active_block = ActiveBlock::Logger;
+        }
+    }
+
+    /// Shows the cached library right away, then fetches it again in the background.
+    pub fn init(&mut self) {
+        self.user = self.cache.get(cache::USER);
+        let library = &mut self.user_library;
+        if let Some(page) = self.cache.get(cache::USER_PLAYLISTS) {
+            library.user_playlists.reset(page);
+        }
+        if let Some(page) = self.cache.get(cache::USER_TOP_TRACKS) {
+            library.user_top_tracks.reset(page);
+        }
+        if let Some(page) = self.cache.get(cache::USER_TOP_ARTISTS) {
+            library.user_top_artists.reset(page);
+        }
+        self.check_liked(ListKind::UserTopTracks);
+        match self.spotify_client.clone() {
+            Some(spotify) => {
+                spawn_library_refresh(spotify.clone(), self.cache.clone(), self.profile_sender());
+                spawn_playback_poll(Arc::downgrade(&spotify), self.profile_sender());
+            }
+            None if self.user.is_none() => {
+                log::warn!("Nothing has been cached yet, start spotui online first")
+            }
+            None => log::info!("Showing the cached library offline"),
+        }
+    }
+
+    /// Replaces a list with a freshly fetched first page, keeping the cursor
+    /// and marked rows on the same items.
+    fn replace_list(&mut self, page: ListPage) {
+        let (kind, new_index) = match page {
+            ListPage::UserPlaylists(page) => (
+                ListKind::UserPlaylists,
+                self.user_library.user_playlists.refresh(page),
+            ),
+            ListPage::UserTopTracks(page) => (
+                ListKind::UserTopTracks,
+                self.user_library.user_top_tracks.refresh(page),
+            ),
+            ListPage::UserTopArtists(page) => (
+                ListKind::UserTopArtists,
+                self.user_library.user_top_artists.refresh(page),
+            ),
+            ListPage::Playlist(id, page) => {
+                if self.playlist.result.as_ref().is_none_or(|p| p.id != id) {
+                    return;
+                }
+                (ListKind::Playlist, self.playlist.pages.refresh(page))
+            }
+        };
+        let Some(new_index) = new_index else {
+            return;
+        };
+        let list = self.list_cursor(kind);
+        let moved = |i: usize| new_index.get(i).copied().flatten();
+        list.selection.marked = list
+            .selection
+            .marked
+            .iter()
+            .filter_map(|&i| moved(i))
+            .collect();
+        list.selection.anchor = None;
+        let selected = list
+            .list_state
+            .selected()
+            .map(|s| moved(s).unwrap_or(s))
+            .filter(|_| list.len > 0)
+            .map(|s| s.min(list.len - 1));
+        list.list_state.select(selected);
+        self.refresh_view(kind);
+        self.check_liked(kind);
+    }
+
+    pub async fn refresh_user_playlists(&mut self) -> color_eyre::Result<()> {
+        let playlists = self
+            .cache
+            .fetch(self.client()?, cache::USER_PLAYLISTS, "/me/playlists")
+            .await?;
+        self.user_library.user_playlists.reset(playlists);
+        self.refresh_view(ListKind::UserPlaylists);
         Ok(())
     }
+
+    /// Opens a playlist, from the cache if it hasn't changed since it was cached.
+    pub async fn load_playlist(&mut self, id: &str) -> color_eyre::Result<()> {
+        let cached = self
+            .user_library
+            .user_playlists
+            .list
+            .iter()
+            .flatten()
+            .find(|p| p.id == id)
+            .and_then(|p| self.cache.playlist(id, &p.snapshot_id));
+        let playlist = match cached {
+            Some(playlist) => playlist,
+            None => self.cache.fetch_playlist(self.client()?, id).await?,
+        };
+        self.playlist.pages.reset(playlist.tracks.clone());
+        self.playlist.result = Some(playlist);
+        self.playlist.selection.clear();
+        self.check_liked(ListKind::Playlist);
+        self.refresh_view(ListKind::Playlist);
+        Ok(())
+    }
+
+    pub async fn reload_list(&mut self, kind: ListKind) -> color_eyre::Result<()> {
+        match kind {
+            ListKind::UserPlaylists => self.refresh_user_playlists().await?,
+            ListKind::UserTopTracks => {
+                let top_tracks = self
+                    .cache
+                    .fetch(self.client()?, cache::USER_TOP_TRACKS, "/me/top/tracks")
+                    .await?;
+                self.user_library.user_top_tracks.reset(top_tracks);
+                self.refresh_view(kind);
+            }
+            ListKind::UserTopArtists => {
+                let top_artists = self
+                    .cache
+                    .fetch(self.client()?, cache::USER_TOP_ARTISTS, "/me/top/artists")
+                    .await?;
+                self.user_library.user_top_artists.reset(top_artists);
+                self.refresh_view(kind);
+            }
+            ListKind::Playlist => self.reload_playlist().await?,
+        }
+        Ok(())
+    }
+
+    /// Reloads the open playlist after its tracks may have changed.
+    pub async fn reload_playlist(&mut self) -> color_eyre::Result<()> {
+        let Some(id) = self.playlist.result.as_ref().map(|p| p.id.clone()) else {
+            return Ok(());
+        };
+        self.load_playlist(&id).await?;
+        let last = self.playlist.pages.list.len().checked_sub(1);
+        if let (Some(i), Some(last)) = (self.playlist.list_state.selected(), last) {
+            self.playlist.list_state.select(Some(i.min(last)));
+        } else {
+            self.playlist.list_state.select(None);
+        }
+        Ok(())
+    }
+}
+
+/// Fetches the user and the first page of each library list, caching them
+/// and replacing what's shown as each arrives.
+fn spawn_library_refresh(spotify: Arc<Spotify>, cache: Cache, sender: ProfileSender) {
+    tokio::spawn(async move {
+        let send = |event| sender.send(event);
+        let result = async {
+            let user = cache.fetch(&spotify, cache::USER, "/me").await?;
+            send(AppEvent::UserFetched(Box::new(user)));
+            let playlists = cache
+                .fetch(&spotify, cache::USER_PLAYLISTS, "/me/playlists")
+                .await?;
+            send(AppEvent::ListFetched(ListPage::UserPlaylists(playlists)));
+            let top_tracks = cache
+                .fetch(&spotify, cache::USER_TOP_TRACKS, "/me/top/tracks")
+                .await?;
+            send(AppEvent::ListFetched(ListPage::UserTopTracks(top_tracks)));
+            let top_artists = cache
+                .fetch(&spotify, cache::USER_TOP_ARTISTS, "/me/top/artists")
+                .await?;
+            send(AppEvent::ListFetched(ListPage::UserTopArtists(top_artists)));
+            color_eyre::Result::<()>::Ok(())
+        };
+        if let Err(e) = result.await {
+            log::error!("Failed to refresh the library, showing what's cached: {e}");
+        }
+    });
+}
+
+/// Polls the player every [`PLAYBACK_POLL_INTERVAL`] until the app quits, or
+/// drops the client when switching profiles.
+fn spawn_playback_poll(spotify: Weak<Spotify>, sender: ProfileSender) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PLAYBACK_POLL_INTERVAL);
+        while !sender.is_closed() {
+            interval.tick().await;
+            let Some(spotify) = spotify.upgrade() else {
+                break;
+            };
+            poll_playback(&spotify, &sender).await;
+        }
+    });
+}
+
+/// The profile's config, or the defaults if it can't be loaded.
+fn load_config() -> Config {
+    Config::load().unwrap_or_else(|e| {
+        log::error!("Failed to load config, using defaults: {e:#}");
+        Config::default()
+    })
+}
+
+fn load_keymap(config: &Config) -> Keymap {
+    Keymap::from_config(&config.keymap).unwrap_or_else(|e| {
+        log::error!("{e}\nUsing the default keymap");
+        Keymap::default()
+    })
+}
+
+fn load_theme(config: &Config) -> Theme {
+    Theme::from_config(&config.theme).unwrap_or_else(|e| {
+        log::error!("{e}\nUsing the default theme");
+        Theme::from_config(&ThemeConfig::default()).unwrap_or_default()
+    })
+}
+
+/// Polls the player once, sending the state as [`AppEvent::PlaybackPolled`].
+async fn poll_playback(spotify: &Spotify, sender: &ProfileSender) {
+    match api::playback_state(spotify).await {
+        Ok(state) => {
+            let status = Box::new(Status::new(state.as_ref()));
+            sender.send(AppEvent::PlaybackPolled(status));
+        }
+        Err(e) => log::debug!("Failed to poll the player: {e}"),
+    }
+}
+
+/// Starts fetching the pages after the last loaded page of `list`, unless a
+/// load is already running or there is nothing left to fetch.
+fn spawn_page_loader<T>(
+    list: &mut PageEndpoint<T>,
+    kind: ListKind,
+    spotify: Arc<Spotify>,
+    sender: ProfileSender,
+    wrap: impl Fn(Page<T>) -> ListPage + Send + Sync + 'static,
+) where
+    T: Clone + DeserializeOwned + Send + Sync + 'static,
+{
+    if list.loading || !list.has_more() {
+        return;
+    }
+    let Some(page) = list.page.clone() else {
+        return;
+    };
+    list.loading = true;
+    tokio::spawn(async move {
+        let result = api::for_each_remaining_page(&spotify, page, |page| {
+            sender.send(AppEvent::PageLoaded(wrap(page)));
+        })
+        .await;
+        if let Err(e) = result {
+            log::error!("Failed to load {kind:?}: {e}");
+        }
+        sender.send(AppEvent::PagesDone(kind));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::SignIn;
+
+    /// Going back and forward puts the cursor and scroll position back where
+    /// each view was left.
+    #[tokio::test]
+    async fn history_restores_the_position() {
+        let mut app = App::new(true, SignIn::Headless).await;
+        let library = &mut app.user_library;
+        library.user_playlists.list = vec![None; 30];
+        library.user_top_tracks.list = vec![None; 30];
+        app.navigate(View::UserPlaylists).await;
+        app.user_library.user_playlists.list_state =
+            ListState::default().with_offset(10).with_selected(Some(15));
+        app.navigate(View::UserTopTracks).await;
+        app.user_library.user_top_tracks.list_state.select(Some(3));
+
+        app.go_back().await;
+        let playlists = Visit {
+            view: View::UserPlaylists,
+            selected: Some(15),
+            offset: 10,
+        };
+        assert_eq!(app.current_visit(), Some(playlists.clone()));
+        app.go_forward().await;
+        let top_tracks = Visit {
+            view: View::UserTopTracks,
+            selected: Some(3),
+            offset: 0,
+        };
+        assert_eq!(app.current_visit(), Some(top_tracks));
+        app.go_back().await;
+        assert_eq!(app.current_visit(), Some(playlists));
+    }
 }
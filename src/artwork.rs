@@ -0,0 +1,445 @@
+use crate::{
+    event::{AppEvent, Event},
+    theme,
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use color_eyre::eyre::{OptionExt, eyre};
+use image::{DynamicImage, ImageFormat, RgbImage, imageops::FilterType};
+use ratatui::{
+    buffer::Buffer,
+    crossterm::{cursor::MoveTo, queue},
+    layout::Rect,
+    style::Color,
+};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+    io::{self, Cursor, Write},
+    path::PathBuf,
+    sync::Arc,
+};
+use tokio::sync::mpsc;
+
+/// Names of the protocols in the `[artwork]` config, `auto` picking one at startup.
+pub const PROTOCOL_NAMES: [&str; 6] = ["auto", "kitty", "sixel", "iterm2", "half-blocks", "none"];
+
+/// Cell size in pixels assumed when the terminal doesn't report one.
+const DEFAULT_CELL_SIZE: (u16, u16) = (8, 16);
+
+/// Base64 bytes sent per kitty graphics escape.
+const KITTY_CHUNK: usize = 4096;
+
+/// The `[artwork]` config.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ArtworkConfig {
+    pub protocol: Option<String>,
+}
+
+/// How images are drawn in the terminal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    Kitty,
+    Sixel,
+    Iterm2,
+    /// Two pixels per cell with `▀`, for terminals without a graphics protocol.
+    HalfBlocks,
+    None,
+}
+
+impl Protocol {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "auto" => Self::detect(),
+            "kitty" => Protocol::Kitty,
+            "sixel" => Protocol::Sixel,
+            "iterm2" => Protocol::Iterm2,
+            "half-blocks" => Protocol::HalfBlocks,
+            "none" => Protocol::None,
+            _ => return None,
+        })
+    }
+
+    /// The best protocol the terminal is known to support, judging by its
+    /// environment. Multiplexers get half-blocks since they don't pass
+    /// graphics through without extra setup.
+    pub fn detect() -> Self {
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+        let term = var("TERM");
+        let program = var("TERM_PROGRAM");
+        let multiplexed = std::env::var_os("TMUX").is_some()
+            || term.starts_with("screen")
+            || term.starts_with("tmux");
+        if !multiplexed {
+            if term == "xterm-kitty"
+                || std::env::var_os("KITTY_WINDOW_ID").is_some()
+                || program == "ghostty"
+            {
+                return Protocol::Kitty;
+            }
+            if program == "iTerm.app" || program == "WezTerm" || var("LC_TERMINAL") == "iTerm2" {
+                return Protocol::Iterm2;
+            }
+            if term.contains("sixel")
+                || ["foot", "mlterm", "contour", "yaft"]
+                    .iter()
+                    .any(|name| term.starts_with(name))
+            {
+                return Protocol::Sixel;
+            }
+        }
+        let colorterm = var("COLORTERM");
+        let colors = colorterm == "truecolor" || colorterm == "24bit" || term.contains("256color");
+        if colors && !theme::no_color() {
+            Protocol::HalfBlocks
+        } else {
+            Protocol::None
+        }
+    }
+}
+
+/// An image scaled and encoded for the area it is drawn in.
+#[derive(Debug)]
+struct Prepared {
+    url: String,
+    area: Rect,
+    image: Encoded,
+}
+
+#[derive(Debug)]
+enum Encoded {
+    HalfBlocks(RgbImage),
+    /// A graphics protocol escape, written at the top left of the area.
+    Escape(String),
+}
+
+/// Cover art, fetched once into the cache directory and drawn with the
+/// terminal's graphics protocol.
+#[derive(Debug)]
+pub struct Artwork {
+    pub protocol: Protocol,
+    cell_size: (u16, u16),
+    /// Decoded images by URL, `None` while being fetched or when that failed.
+    images: HashMap<String, Option<Arc<DynamicImage>>>,
+    prepared: Option<Prepared>,
+    /// Whether `prepared` has been written to the terminal.
+    written: bool,
+    /// Whether a graphics protocol image is on screen.
+    on_screen: bool,
+}
+
+impl Artwork {
+    pub fn new(config: &ArtworkConfig) -> Self {
+        let name = config.protocol.as_deref().unwrap_or(PROTOCOL_NAMES[0]);
+        let protocol = Protocol::from_name(name).unwrap_or_else(|| {
+            log::error!(
+                "Unknown artwork protocol `{name}`, expected one of: {}",
+                PROTOCOL_NAMES.join(", ")
+            );
+            Protocol::detect()
+        });
+        log::info!("Drawing artwork with {protocol:?}");
+        Self {
+            protocol,
+            cell_size: cell_size(),
+            images: HashMap::new(),
+            prepared: None,
+            written: false,
+            on_screen: false,
+        }
+    }
+
+    /// Scales the image at `url` to `area` for the next frame, fetching it
+    /// first if needed. `None` when no artwork is to be drawn.
+    pub fn prepare(
+        &mut self,
+        target: Option<(String, Rect)>,
+        sender: &mpsc::UnboundedSender<Event>,
+    ) {
+        let Some((url, area)) = target.filter(|_| self.protocol != Protocol::None) else {
+            self.prepared = None;
+            return;
+        };
+        if self
+            .prepared
+            .as_ref()
+            .is_some_and(|p| p.url == url && p.area == area)
+        {
+            return;
+        }
+        let image = match self.images.get(&url) {
+            Some(image) => image.clone(),
+            None => {
+                self.images.insert(url.clone(), None);
+                spawn_fetch(url.clone(), sender.clone());
+                None
+            }
+        };
+        self.prepared = image.and_then(|image| {
+            let encoded = self.encode(&image, area).unwrap_or_else(|e| {
+                log::error!("Failed to encode artwork: {e}");
+                None
+            })?;
+            Some(Prepared {
+                url,
+                area,
+                image: encoded,
+            })
+        });
+        self.written = false;
+    }
+
+    /// Keeps an image fetched in the background; `None` if fetching failed.
+    pub fn loaded(&mut self, url: String, image: Option<Arc<DynamicImage>>) {
+        self.images.insert(url, image);
+    }
+
+    /// Forgets what was written to the terminal, e.g. after it was resized and cleared.
+    pub fn invalidate(&mut self) {
+        self.cell_size = cell_size();
+        self.prepared = None;
+    }
+
+    /// Draws the prepared image if it belongs in `area`. Graphics protocol
+    /// images are written by [`Artwork::write`] after the frame, so their
+    /// cells are left for it.
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let Some(prepared) = self.prepared.as_ref().filter(|p| p.area == area) else {
+            return;
+        };
+        match &prepared.image {
+            Encoded::HalfBlocks(image) => {
+                let x = area.x + (area.width - image.width() as u16) / 2;
+                for (px, py, pixel) in image.enumerate_pixels() {
+                    let [r, g, b] = pixel.0;
+                    let cell = &mut buf[(x + px as u16, area.y + (py / 2) as u16)];
+                    cell.set_char('▀');
+                    if py % 2 == 0 {
+                        cell.set_fg(Color::Rgb(r, g, b));
+                    } else {
+                        cell.set_bg(Color::Rgb(r, g, b));
+                    }
+                }
+            }
+            Encoded::Escape(_) => {
+                for y in area.top()..area.bottom() {
+                    for x in area.left()..area.right() {
+                        buf[(x, y)].set_skip(true);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes the prepared graphics protocol image to the terminal unless it
+    /// is already there, and removes a kitty image that is no longer drawn.
+    pub fn write(&mut self, out: &mut impl Write) -> io::Result<()> {
+        match &self.prepared {
+            Some(Prepared {
+                area,
+                image: Encoded::Escape(escape),
+                ..
+            }) if !self.written => {
+                if self.protocol == Protocol::Kitty {
+                    out.write_all(KITTY_DELETE.as_bytes())?;
+                } else {
+                    // Sixel and iTerm2 images only paint over what they cover.
+                    let blank = " ".repeat(usize::from(area.width));
+                    for y in area.top()..area.bottom() {
+                        queue!(out, MoveTo(area.x, y))?;
+                        out.write_all(blank.as_bytes())?;
+                    }
+                }
+                queue!(out, MoveTo(area.x, area.y))?;
+                out.write_all(escape.as_bytes())?;
+                out.flush()?;
+                self.written = true;
+                self.on_screen = true;
+            }
+            None if self.on_screen => {
+                if self.protocol == Protocol::Kitty {
+                    out.write_all(KITTY_DELETE.as_bytes())?;
+                    out.flush()?;
+                }
+                self.on_screen = false;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn encode(&self, image: &DynamicImage, area: Rect) -> color_eyre::Result<Option<Encoded>> {
+        if area.is_empty() {
+            return Ok(None);
+        }
+        let (cell_width, cell_height) = self.cell_size;
+        let fit = |width: u16, height: u16| {
+            image.resize(u32::from(width), u32::from(height), FilterType::Triangle)
+        };
+        let pixels = || fit(area.width * cell_width, area.height * cell_height);
+        let encoded = match self.protocol {
+            Protocol::None => return Ok(None),
+            Protocol::HalfBlocks => Encoded::HalfBlocks(fit(area.width, area.height * 2).to_rgb8()),
+            Protocol::Kitty => Encoded::Escape(kitty(&png(&pixels())?)),
+            Protocol::Iterm2 => Encoded::Escape(iterm2(&pixels())?),
+            Protocol::Sixel => Encoded::Escape(sixel(&pixels().to_rgb8())),
+        };
+        Ok(Some(encoded))
+    }
+}
+
+/// Deletes every kitty image placed by spotui.
+const KITTY_DELETE: &str = "\x1b_Ga=d,d=A,q=2\x1b\\";
+
+fn cell_size() -> (u16, u16) {
+    match ratatui::crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.columns > 0 && size.rows > 0 => {
+            (size.width / size.columns, size.height / size.rows)
+        }
+        _ => DEFAULT_CELL_SIZE,
+    }
+}
+
+fn png(image: &DynamicImage) -> color_eyre::Result<Vec<u8>> {
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(png)
+}
+
+fn kitty(png: &[u8]) -> String {
+    let data = STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut escape = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let chunk = String::from_utf8_lossy(chunk);
+        // The first chunk carries the placement: a PNG, quiet, cursor left in place.
+        let keys = if i == 0 { "a=T,f=100,q=2,C=1," } else { "" };
+        let _ = write!(escape, "\x1b_G{keys}m={more};{chunk}\x1b\\");
+    }
+    escape
+}
+
+fn iterm2(image: &DynamicImage) -> color_eyre::Result<String> {
+    let png = png(image)?;
+    Ok(format!(
+        "\x1b]1337;File=inline=1;size={};width={}px;height={}px;preserveAspectRatio=1:{}\x07",
+        png.len(),
+        image.width(),
+        image.height(),
+        STANDARD.encode(&png)
+    ))
+}
+
+/// Encodes `image` as sixels, mapping it onto a 6×6×6 color cube.
+fn sixel(image: &RgbImage) -> String {
+    let (width, height) = image.dimensions();
+    let level = |c: u8| (u32::from(c) * 5 + 127) / 255;
+    let mut escape = format!("\x1bP0;1;0q\"1;1;{width};{height}");
+    for i in 0..216 {
+        let percent = |level: u32| level * 20;
+        let _ = write!(
+            escape,
+            "#{i};2;{};{};{}",
+            percent(i / 36),
+            percent(i / 6 % 6),
+            percent(i % 6)
+        );
+    }
+    for band in (0..height).step_by(6) {
+        // The six-pixel-high columns of each color in the band.
+        let mut colors: BTreeMap<u32, Vec<u8>> = BTreeMap::new();
+        for y in band..(band + 6).min(height) {
+            for x in 0..width {
+                let [r, g, b] = image.get_pixel(x, y).0;
+                let color = level(r) * 36 + level(g) * 6 + level(b);
+                colors
+                    .entry(color)
+                    .or_insert_with(|| vec![0; width as usize])[x as usize] |= 1 << (y - band);
+            }
+        }
+        for (color, columns) in colors {
+            let _ = write!(escape, "#{color}");
+            for run in columns.chunk_by(|a, b| a == b) {
+                let symbol = char::from(63 + run[0]);
+                if run.len() > 3 {
+                    let _ = write!(escape, "!{}{symbol}", run.len());
+                } else {
+                    escape.extend(std::iter::repeat_n(symbol, run.len()));
+                }
+            }
+            escape.push('$');
+        }
+        escape.push('-');
+    }
+    escape.push_str("\x1b\\");
+    escape
+}
+
+fn spawn_fetch(url: String, sender: mpsc::UnboundedSender<Event>) {
+    tokio::spawn(async move {
+        let image = match fetch(&url).await {
+            Ok(image) => Some(Arc::new(image)),
+            Err(e) => {
+                log::error!("Failed to load artwork {url}: {e:#}");
+                None
+            }
+        };
+        let _ = sender.send(Event::App(AppEvent::ArtworkLoaded(url, image)));
+    });
+}
+
+/// The image at `url`, read from the cache or downloaded into it. An entry
+/// that doesn't decode is removed, so the next attempt downloads it again.
+async fn fetch(url: &str) -> color_eyre::Result<DynamicImage> {
+    let path = cached(url).await?;
+    let bytes = tokio::fs::read(&path).await?;
+    match image::load_from_memory(&bytes) {
+        Ok(image) => Ok(image),
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&path).await;
+            Err(e.into())
+        }
+    }
+}
+
+/// Where the image at `url` is cached, downloading it first if it isn't yet.
+pub async fn cached(url: &str) -> color_eyre::Result<PathBuf> {
+    let path = cache_path(url)?;
+    if tokio::fs::try_exists(&path).await? {
+        return Ok(path);
+    }
+    let bytes = reqwest::get(url).await?.error_for_status()?.bytes().await?;
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    // Written aside and moved into place, so an interrupted download never
+    // looks cached. The suffix keeps two downloads of one image apart.
+    let partial = path.with_extension(format!("{:08x}.part", rand::random::<u32>()));
+    let written = match tokio::fs::write(&partial, &bytes).await {
+        Ok(()) => tokio::fs::rename(&partial, &path).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = written {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(e.into());
+    }
+    Ok(path)
+}
+
+/// Where the image at `url` is cached, named after the image ID the URL ends with.
+fn cache_path(url: &str) -> color_eyre::Result<PathBuf> {
+    let name: String = url
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect();
+    if name.is_empty() {
+        return Err(eyre!("No image ID in {url}"));
+    }
+    let dir = dirs::cache_dir().ok_or_eyre("Could not determine cache directory")?;
+    Ok(dir.join("spotui").join("artwork").join(name))
+}
@@ -0,0 +1,533 @@
+use crate::config::{Config, SpotifyConfig};
+use chrono::{Duration, Utc};
+use color_eyre::eyre::{OptionExt, WrapErr, bail, eyre};
+use qrcode::{EcLevel, QrCode, render::unicode::Dense1x2};
+use reqwest::Url;
+use rouille::{Response, Server};
+use spotify_rs::{AuthCodePkceClient, RedirectUrl, Token, UnknownFlow, client::Client};
+use std::{
+    io::{IsTerminal, Write},
+    path::Path,
+    sync::{
+        Arc, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+/// A signed in client. It is always rebuilt from a refresh token, whether that
+/// was saved by an earlier run or just handed out by the browser sign-in.
+pub type Spotify = Client<Token, UnknownFlow>;
+
+/// File in the config directory the token is saved to.
+const TOKEN_FILE: &str = "token.json";
+
+/// Saved access tokens this close to expiring are refreshed rather than used.
+const EXPIRY_MARGIN: Duration = Duration::seconds(60);
+
+/// How long the sign-in in the browser may take.
+const SIGN_IN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// How often the sign-in server checks whether it should stop.
+const CALLBACK_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
+
+const SCOPES: [&str; 13] = [
+    "user-top-read",
+    "user-follow-read",
+    "user-read-private",
+    "user-read-email",
+    "user-read-playback-state",
+    "user-read-currently-playing",
+    "user-modify-playback-state",
+    "playlist-read-private",
+    "playlist-read-collaborative",
+    "playlist-modify-private",
+    "playlist-modify-public",
+    "user-library-read",
+    "user-library-modify",
+];
+
+/// How the user signs in to Spotify when there is no usable saved token.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignIn {
+    /// A browser is opened on this machine, and Spotify sends it back to a
+    /// server spotui runs on the redirect URI.
+    Browser,
+    /// The sign-in URL is printed with a QR code, to be opened on any device,
+    /// and the URL Spotify redirects to is pasted back into the terminal. For
+    /// machines without a display, such as over SSH.
+    Headless,
+    /// Like [`SignIn::Browser`], but without printing anything, for signing in
+    /// while the interface has the terminal. Fails if no browser opens.
+    Background,
+}
+
+impl SignIn {
+    /// [`SignIn::Headless`] when asked for, or when a browser opened by spotui
+    /// couldn't be seen.
+    pub fn new(headless: bool) -> Self {
+        if headless || !has_display() {
+            SignIn::Headless
+        } else {
+            SignIn::Browser
+        }
+    }
+}
+
+/// Signs in with the token saved by the last run, or through the browser when
+/// there is none or Spotify no longer accepts it.
+pub async fn sign_in(sign_in: SignIn) -> color_eyre::Result<Spotify> {
+    dotenvy::dotenv().ok();
+    // Kept for the whole sign-in, so the token goes to the profile it was
+    // started for even if another one is switched to meanwhile.
+    let dir = Config::dir()?;
+    let config = spotify_config();
+    let client_id = setting(config.client_id, "SPOTIFY_CLIENT_ID", "client_id")?;
+    let saved = load_token(&dir).unwrap_or_else(|e| {
+        log::error!("Failed to load the saved token: {e:#}");
+        None
+    });
+    if let Some(refresh_token) = saved.as_ref().and_then(Token::refresh_secret) {
+        match from_refresh_token(&dir, &client_id, refresh_token).await {
+            Ok(spotify) => return Ok(spotify),
+            Err(e) => log::warn!("The saved token was rejected, signing in again: {e}"),
+        }
+    }
+    let redirect_uri = setting(config.redirect_uri, "SPOTIFY_REDIRECT_URI", "redirect_uri")?;
+    let token = authorize(&client_id, &redirect_uri, sign_in).await?;
+    let refresh_token = token
+        .refresh_secret()
+        .ok_or_eyre("Spotify did not hand out a refresh token")?;
+    from_refresh_token(&dir, &client_id, refresh_token).await
+}
+
+/// The `[spotify]` config, or the defaults if the config can't be loaded.
+fn spotify_config() -> SpotifyConfig {
+    Config::load().map_or_else(
+        |e| {
+            log::error!("Failed to load config, signing in with the environment's settings: {e:#}");
+            SpotifyConfig::default()
+        },
+        |config| config.spotify,
+    )
+}
+
+/// `value` from the `[spotify]` config, or else the environment variable `var`.
+fn setting(value: Option<String>, var: &str, key: &str) -> color_eyre::Result<String> {
+    match value {
+        Some(value) => Ok(value),
+        None => dotenvy::var(var)
+            .wrap_err_with(|| format!("Set {var}, or `{key}` under [spotify] in the config")),
+    }
+}
+
+/// Saves the token of `spotify` for the next run. Spotify may hand out a new
+/// refresh token whenever the access token is refreshed, so this is called
+/// again once the client is no longer needed.
+pub fn save_token(spotify: &Spotify) {
+    match Config::dir() {
+        Ok(dir) => save_token_in(&dir, spotify),
+        Err(e) => log::error!("Failed to save the token: {e:#}"),
+    }
+}
+
+fn save_token_in(dir: &Path, spotify: &Spotify) {
+    let token = spotify.token();
+    let token = token.read().unwrap_or_else(PoisonError::into_inner);
+    if let Err(e) = write_token(dir, &token) {
+        log::error!("Failed to save the token: {e:#}");
+    }
+}
+
+/// An access token for a single request, such as polling the player every
+/// few seconds. The token saved to disk is used while it's valid, so this
+/// only reaches Spotify's accounts service when it has to be refreshed. Never
+/// signs in through the browser, which a status bar has no way to show.
+pub async fn access_token() -> color_eyre::Result<String> {
+    dotenvy::dotenv().ok();
+    let dir = Config::dir()?;
+    let Some(token) = load_token(&dir)? else {
+        bail!("Not signed in, run `spotui` to sign in first");
+    };
+    let expires_at = token.created_at + Duration::seconds(token.expires_in as i64);
+    if expires_at - EXPIRY_MARGIN > Utc::now() {
+        return Ok(token.secret().to_string());
+    }
+    let refresh_token = token
+        .refresh_secret()
+        .ok_or_eyre("The saved token can't be refreshed, run `spotui` to sign in again")?;
+    let client_id = setting(spotify_config().client_id, "SPOTIFY_CLIENT_ID", "client_id")?;
+    let spotify = from_refresh_token(&dir, &client_id, refresh_token)
+        .await
+        .wrap_err("The saved token was rejected, run `spotui` to sign in again")?;
+    Ok(spotify.access_token()?)
+}
+
+async fn from_refresh_token(
+    dir: &Path,
+    client_id: &str,
+    refresh_token: &str,
+) -> color_eyre::Result<Spotify> {
+    let auto_refresh = true;
+    let spotify = Client::from_refresh_token(
+        client_id,
+        None,
+        None,
+        auto_refresh,
+        refresh_token.to_string(),
+    )
+    .await?;
+    save_token_in(dir, &spotify);
+    Ok(spotify)
+}
+
+/// Runs the authorization code flow in the browser, returning the token it
+/// ends with.
+async fn authorize(
+    client_id: &str,
+    redirect_uri: &str,
+    sign_in: SignIn,
+) -> color_eyre::Result<Token> {
+    let redirect_url = Url::parse(redirect_uri)
+        .wrap_err_with(|| format!("The redirect URI is not a valid URL: {redirect_uri}"))?;
+
+    let auto_refresh = false;
+    let (client, url) = AuthCodePkceClient::new(
+        client_id,
+        SCOPES,
+        RedirectUrl::new(redirect_uri.to_string())?,
+        auto_refresh,
+    );
+    let state = url
+        .query_pairs()
+        .find(|(name, _)| name == "state")
+        .map(|(_, state)| state.into_owned())
+        .ok_or_eyre("The authorization URL has no state")?;
+
+    let auth_code = match sign_in {
+        SignIn::Browser | SignIn::Background => {
+            browser_code(&url, &redirect_url, &state, sign_in == SignIn::Browser).await?
+        }
+        SignIn::Headless => None,
+    };
+    let auth_code = match auth_code {
+        Some(auth_code) => auth_code,
+        None if sign_in == SignIn::Background => {
+            bail!("No browser to sign in with, sign in with `spotui --headless` first")
+        }
+        None => pasted_code(&url, &state).await?,
+    };
+
+    let spotify_auth = client.authenticate(auth_code, state).await?;
+    let token = spotify_auth.token();
+    let token = token.read().unwrap_or_else(PoisonError::into_inner);
+    Ok(token.clone())
+}
+
+/// Opens the sign-in in a browser and waits for it to be sent back to the
+/// redirect URI, returning the code. `None` if no browser could be opened.
+/// With `print`, the URL is also printed in case the browser hides.
+async fn browser_code(
+    url: &Url,
+    redirect_url: &Url,
+    state: &str,
+    print: bool,
+) -> color_eyre::Result<Option<String>> {
+    let mut callback = CallbackServer::start(redirect_url, state.to_string())?;
+    log::info!("Opening browser for Spotify authentication...");
+    if let Err(e) = open::that(url.as_str()) {
+        log::warn!("Failed to open a browser to sign in: {e}");
+        return Ok(None);
+    }
+    if print {
+        eprintln!(
+            "Sign in to Spotify in the browser that just opened, or open this URL:\n\n{url}\n"
+        );
+    }
+    callback.code().await.map(Some)
+}
+
+/// Prints the sign-in URL and reads back the URL the browser was redirected
+/// to, which carries the code even though nothing listens there.
+async fn pasted_code(url: &Url, state: &str) -> color_eyre::Result<String> {
+    if !std::io::stdin().is_terminal() {
+        bail!("Signing in needs a terminal to paste into, run spotui in one first");
+    }
+    eprintln!("Open this URL on any device to sign in to Spotify:\n\n{url}\n");
+    match QrCode::with_error_correction_level(url.as_str(), EcLevel::L) {
+        Ok(code) => eprintln!(
+            "{}",
+            code.render::<Dense1x2>()
+                .dark_color(Dense1x2::Light)
+                .light_color(Dense1x2::Dark)
+                .build()
+        ),
+        Err(e) => log::debug!("No QR code for the sign-in URL: {e}"),
+    }
+    eprintln!(
+        "Once you have signed in, the browser fails to load a page on the redirect URI. \
+         Paste the address of that page here:"
+    );
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        let line = lines
+            .next_line()
+            .await?
+            .ok_or_eyre("No redirect URL was pasted")?;
+        match Callback::pasted(&line, state) {
+            Callback::Code(code) => return Ok(code),
+            Callback::Failed(message) => bail!(message),
+            Callback::Invalid(message) => eprintln!("{message} Paste the address again:"),
+        }
+    }
+}
+
+/// Whether a browser opened by spotui would show up in front of the user.
+fn has_display() -> bool {
+    let set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+    if set("SSH_CONNECTION") || set("SSH_TTY") {
+        // Only a forwarded X display shows a browser here.
+        return set("DISPLAY");
+    }
+    cfg!(any(target_os = "macos", windows)) || set("DISPLAY") || set("WAYLAND_DISPLAY")
+}
+
+/// What a request to the redirect URI carried.
+#[derive(Debug, PartialEq)]
+enum Callback {
+    /// Spotify handed out an authorization code.
+    Code(String),
+    /// The user denied access, or Spotify reported another error.
+    Failed(String),
+    /// Not a redirect from this sign-in, such as a reloaded tab of an
+    /// earlier one. The sign-in keeps waiting.
+    Invalid(&'static str),
+}
+
+impl Callback {
+    /// Reads the query parameters of the redirect through `param`.
+    fn parse(param: impl Fn(&str) -> Option<String>, expected_state: &str) -> Self {
+        let (code, error) = (param("code"), param("error"));
+        if code.is_none() && error.is_none() {
+            return Callback::Invalid("The page was opened without a response from Spotify.");
+        }
+        if param("state").as_deref() != Some(expected_state) {
+            return Callback::Invalid(
+                "This response is from an earlier sign-in, use the latest URL spotui gave you.",
+            );
+        }
+        match error.as_deref() {
+            Some("access_denied") => {
+                Callback::Failed("Access to your Spotify account was denied.".to_string())
+            }
+            Some(error) => Callback::Failed(format!("Spotify reported an error: {error}")),
+            None => Callback::Code(code.unwrap_or_default()),
+        }
+    }
+
+    /// Reads the redirect from a pasted address. Only the query matters, so a
+    /// bare `?code=…&state=…` works too.
+    fn pasted(line: &str, expected_state: &str) -> Self {
+        let query = line.trim().split_once('?').map_or(line.trim(), |(_, q)| q);
+        let mut pasted = Url::parse("http://localhost/").expect("the URL is valid");
+        pasted.set_query(Some(query));
+        let param = |name: &str| {
+            pasted
+                .query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        Self::parse(param, expected_state)
+    }
+}
+
+/// Serves the redirect URI until Spotify sends the browser back to it. The
+/// server stops once this is dropped.
+struct CallbackServer {
+    outcome: tokio::sync::mpsc::UnboundedReceiver<Result<String, String>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl CallbackServer {
+    fn start(redirect_url: &Url, state: String) -> color_eyre::Result<Self> {
+        let host = redirect_url
+            .host_str()
+            .ok_or_eyre("The redirect URI has no host")?;
+        let port = redirect_url
+            .port_or_known_default()
+            .ok_or_eyre("The redirect URI has no port")?;
+        let path = redirect_url.path().to_string();
+        let (sender, outcome) = tokio::sync::mpsc::unbounded_channel();
+        let server = Server::new((host, port), move |request| {
+            // Browsers also ask for a favicon and the like.
+            if request.url() != path {
+                return Response::empty_404();
+            }
+            match Callback::parse(|name| request.get_param(name), &state) {
+                Callback::Code(code) => {
+                    let _ = sender.send(Ok(code));
+                    page(
+                        "Back to spotui",
+                        "spotui is finishing the sign-in, you may close this page.",
+                        200,
+                    )
+                }
+                Callback::Failed(message) => {
+                    let page = page("Sign-in failed", &message, 403);
+                    let _ = sender.send(Err(message));
+                    page
+                }
+                Callback::Invalid(message) => page("Sign-in failed", message, 400),
+            }
+        })
+        .map_err(|e| eyre!("Failed to listen on {host}:{port} for the sign-in: {e}"))?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        std::thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                server.poll_timeout(CALLBACK_POLL_INTERVAL);
+            }
+            // Let the page of the last request finish loading.
+            server.join();
+        });
+        Ok(Self { outcome, stop })
+    }
+
+    /// Waits for the authorization code, giving up after [`SIGN_IN_TIMEOUT`].
+    async fn code(&mut self) -> color_eyre::Result<String> {
+        match tokio::time::timeout(SIGN_IN_TIMEOUT, self.outcome.recv()).await {
+            Ok(Some(Ok(code))) => Ok(code),
+            Ok(Some(Err(message))) => Err(eyre!(message)),
+            Ok(None) => Err(eyre!("The sign-in server stopped")),
+            Err(_) => Err(eyre!(
+                "Gave up waiting for the sign-in after {} minutes",
+                SIGN_IN_TIMEOUT.as_secs() / 60
+            )),
+        }
+    }
+}
+
+impl Drop for CallbackServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn page(title: &str, message: &str, status: u16) -> Response {
+    let escape = |text: &str| {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    Response::html(format!(
+        "<!doctype html><meta charset=\"utf-8\"><title>{title}</title>\
+         <body style=\"font-family: sans-serif; text-align: center; margin-top: 4em\">\
+         <h1>{title}</h1><p>{}</p></body>",
+        escape(message)
+    ))
+    .with_status_code(status)
+}
+
+fn load_token(dir: &Path) -> color_eyre::Result<Option<Token>> {
+    let path = dir.join(TOKEN_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = std::fs::read_to_string(&path)
+        .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&contents).wrap_err_with(|| format!("Invalid token in {}", path.display()))
+}
+
+fn write_token(dir: &Path, token: &Token) -> color_eyre::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(TOKEN_FILE);
+    let contents = serde_json::to_string(token)?;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // The token grants access to the account, so only the user may read it.
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(&path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .wrap_err_with(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const STATE: &str = "s7aTe";
+
+    #[test]
+    fn reads_the_code_from_a_pasted_address() {
+        let code = Callback::Code("AQD-x_1".to_string());
+        for line in [
+            "http://127.0.0.1:8888/callback?code=AQD-x_1&state=s7aTe",
+            "  http://127.0.0.1:8888/callback?state=s7aTe&code=AQD-x_1\n",
+            "?code=AQD-x_1&state=s7aTe",
+            "code=AQD-x_1&state=s7aTe",
+        ] {
+            assert_eq!(Callback::pasted(line, STATE), code, "{line:?}");
+        }
+        assert_eq!(
+            Callback::pasted("?code=a%2Fb%3D&state=s7aTe", STATE),
+            Callback::Code("a/b=".to_string())
+        );
+    }
+
+    #[test]
+    fn keeps_waiting_without_a_response_or_for_another_sign_in() {
+        for line in [
+            "",
+            "http://127.0.0.1:8888/callback",
+            "?state=s7aTe",
+            "hello",
+        ] {
+            assert!(
+                matches!(Callback::pasted(line, STATE), Callback::Invalid(_)),
+                "{line:?}"
+            );
+        }
+        for line in [
+            "?code=AQD&state=earlier",
+            "?code=AQD",
+            "?error=access_denied&state=earlier",
+        ] {
+            assert_eq!(
+                Callback::pasted(line, STATE),
+                Callback::Invalid(
+                    "This response is from an earlier sign-in, use the latest URL spotui gave you."
+                ),
+                "{line:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn reports_errors_from_spotify() {
+        assert_eq!(
+            Callback::pasted("?error=access_denied&state=s7aTe", STATE),
+            Callback::Failed("Access to your Spotify account was denied.".to_string())
+        );
+        assert_eq!(
+            Callback::pasted("?error=server_error&state=s7aTe", STATE),
+            Callback::Failed("Spotify reported an error: server_error".to_string())
+        );
+    }
+
+    #[test]
+    fn parses_server_requests() {
+        let params = HashMap::from([("code", "AQD"), ("state", STATE)]);
+        let param = |name: &str| params.get(name).map(|value| value.to_string());
+        assert_eq!(
+            Callback::parse(param, STATE),
+            Callback::Code("AQD".to_string())
+        );
+        assert!(matches!(
+            Callback::parse(|_| None, STATE),
+            Callback::Invalid(_)
+        ));
+    }
+}
@@ -0,0 +1,92 @@
+use crate::{api, auth::Spotify, config::Config};
+use serde::de::DeserializeOwned;
+use spotify_rs::model::playlist::Playlist;
+use std::path::PathBuf;
+
+/// Key of the cached user profile.
+pub const USER: &str = "me";
+/// Keys of the first pages of the library lists.
+pub const USER_PLAYLISTS: &str = "playlists";
+pub const USER_TOP_TRACKS: &str = "top-tracks";
+pub const USER_TOP_ARTISTS: &str = "top-artists";
+
+/// API responses kept on disk as returned, so the library shows up before
+/// it's fetched again and can be browsed offline.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    /// `None` when there's no cache directory, which turns caching off.
+    dir: Option<PathBuf>,
+}
+
+impl Cache {
+    pub fn open() -> Self {
+        let dir = Config::cache_dir().map(|dir| dir.join("library"));
+        if dir.is_none() {
+            log::warn!("Could not determine cache directory, the library won't be cached");
+        }
+        Self { dir }
+    }
+
+    /// The response cached under `key`, if there is one that still parses.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let path = self.path(key)?;
+        let contents = std::fs::read_to_string(&path).ok()?;
+        serde_json::from_str(&contents)
+            .inspect_err(|e| log::warn!("Ignoring invalid cache entry {}: {e}", path.display()))
+            .ok()
+    }
+
+    /// Fetches `path` from the Web API, caching the response under `key`.
+    pub async fn fetch<T: DeserializeOwned>(
+        &self,
+        spotify: &Spotify,
+        key: &str,
+        path: &str,
+    ) -> color_eyre::Result<T> {
+        let (value, json) = api::get_json(spotify, path).await?;
+        self.put(key, &json);
+        Ok(value)
+    }
+
+    /// The playlist as cached at `snapshot_id`. Its tracks only change with the snapshot.
+    pub fn playlist(&self, id: &str, snapshot_id: &str) -> Option<Playlist> {
+        self.get(&playlist_key(id, snapshot_id))
+    }
+
+    /// Fetches a playlist, replacing its cached older snapshots.
+    pub async fn fetch_playlist(
+        &self,
+        spotify: &Spotify,
+        id: &str,
+    ) -> color_eyre::Result<Playlist> {
+        let (playlist, json): (Playlist, _) =
+            api::get_json(spotify, &format!("/playlists/{id}")).await?;
+        if let Some(dir) = &self.dir {
+            let _ = std::fs::remove_dir_all(dir.join("playlists").join(id));
+        }
+        self.put(&playlist_key(id, &playlist.snapshot_id), &json);
+        Ok(playlist)
+    }
+
+    fn put(&self, key: &str, json: &str) {
+        let Some(path) = self.path(key) else {
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(&path, json));
+        if let Err(e) = result {
+            log::warn!("Failed to cache {key}: {e}");
+        }
+    }
+
+    fn path(&self, key: &str) -> Option<PathBuf> {
+        Some(self.dir.as_ref()?.join(format!("{key}.json")))
+    }
+}
+
+fn playlist_key(id: &str, snapshot_id: &str) -> String {
+    // Snapshot IDs are base64 and may contain `/`.
+    format!("playlists/{id}/{}", snapshot_id.replace('/', "_"))
+}
@@ -0,0 +1,576 @@
+use crate::{
+    api,
+    auth::{self, SignIn, Spotify},
+    config::Config,
+    export,
+    ipc::{self, Repeat, Request},
+    navigation::View,
+    status::{Status, Template},
+    widgets::table::{Column, TableRow},
+};
+use clap::{Parser, Subcommand, ValueEnum};
+use color_eyre::eyre::{OptionExt, bail};
+use serde_json::{Map, Value, json};
+use spotify_rs::model::{PlayableItem, playlist::SimplifiedPlaylist, search::Item};
+use std::{collections::HashMap, time::Duration};
+
+/// Columns the rows of search results and playlists are printed with.
+const TRACK_COLUMNS: [Column; 3] = [Column::Title, Column::Artists, Column::Album];
+const ARTIST_COLUMNS: [Column; 2] = [Column::Title, Column::Genres];
+const PLAYLIST_COLUMNS: [Column; 3] = [Column::Title, Column::Owner, Column::Tracks];
+
+#[derive(Debug, Parser)]
+#[command(version, about = "A Spotify client for the terminal")]
+#[command(after_help = "Without a command the terminal interface is started.")]
+pub struct Cli {
+    /// Browse the cached library without signing in
+    #[arg(long)]
+    pub offline: bool,
+    /// Print the output of a command as JSON
+    #[arg(long, global = true)]
+    pub json: bool,
+    /// Sign in by opening a printed URL on any device and pasting back the
+    /// address it ends on, instead of in a browser on this machine. The
+    /// default without a display, such as over SSH
+    #[arg(long, global = true)]
+    pub headless: bool,
+    /// Use the client ID, redirect URI, token, config and state of a named
+    /// profile instead of the default one
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Resume playback, or play a track, album, playlist or artist
+    Play {
+        /// A Spotify URI or open.spotify.com link
+        uri: Option<String>,
+    },
+    /// Pause playback
+    Pause,
+    /// Skip to the next track
+    Next,
+    /// Skip to the previous track
+    Prev,
+    /// Set the volume of the active device
+    Volume {
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=100))]
+        percent: u8,
+    },
+    /// Show what is playing
+    Status {
+        /// Template of the printed line, with fields such as {artist}, {title}, {album},
+        /// {progress}, {duration}, {percent}, {state}, {status}, {device} and {volume}
+        #[arg(long)]
+        format: Option<Template>,
+        /// Keep polling and print a new line whenever the output changes
+        #[arg(long)]
+        follow: bool,
+        /// Seconds between polls with --follow
+        #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// Print JSON for a waybar custom module
+        #[arg(long, conflicts_with = "json")]
+        waybar: bool,
+    },
+    /// Save the playing track, or the given tracks, to Liked Songs
+    Like {
+        /// Spotify URIs or open.spotify.com links of tracks
+        uris: Vec<String>,
+    },
+    /// Add a track or episode to the queue
+    Queue {
+        /// A Spotify URI or open.spotify.com link
+        uri: String,
+    },
+    /// Search the catalog, printing each result with its URI
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// What to search for
+        #[arg(long = "type", value_enum, default_value_t = SearchKind::Track)]
+        kind: SearchKind,
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..=50))]
+        limit: u32,
+    },
+    /// List or export your playlists
+    Playlist {
+        #[command(subcommand)]
+        command: PlaylistCommand,
+    },
+    /// Control a running spotui through its socket
+    Ctl {
+        #[command(subcommand)]
+        command: CtlCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CtlCommand {
+    /// Resume playback, or play a track, album, playlist or artist
+    Play {
+        /// A Spotify URI or open.spotify.com link
+        uri: Option<String>,
+    },
+    /// Pause playback
+    Pause,
+    /// Skip to the next track
+    Next,
+    /// Skip to the previous track
+    Prev,
+    /// Add a track or episode to the queue
+    Queue {
+        /// A Spotify URI or open.spotify.com link
+        uri: String,
+    },
+    /// Jump to a position in the playing track
+    Seek {
+        /// Seconds from the start
+        seconds: u32,
+    },
+    /// Set the volume of the active device
+    Volume {
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=100))]
+        percent: u8,
+    },
+    /// Turn shuffle on or off
+    Shuffle {
+        #[arg(action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    /// Repeat nothing, the track, or the album or playlist
+    Repeat {
+        #[arg(value_enum)]
+        mode: Repeat,
+    },
+    /// Show one of the lists, or a playlist
+    Navigate {
+        view: CtlView,
+        /// The URI, link or ID of the playlist to show
+        #[arg(required_if_eq("view", "playlist"))]
+        playlist: Option<String>,
+    },
+    /// Print the shown view and the playback state as JSON
+    State,
+    /// Print a line of JSON for every track change, pause and resume
+    Subscribe,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CtlView {
+    Playlists,
+    TopTracks,
+    TopArtists,
+    Playlist,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PlaylistCommand {
+    /// List your playlists with their URIs
+    List,
+    /// Print the tracks of a playlist as CSV, with the configured track columns
+    Export {
+        /// The name, URI or link of the playlist
+        playlist: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SearchKind {
+    Track,
+    Artist,
+    Playlist,
+}
+
+/// What a command prints: text for people, or a JSON value with `--json`.
+struct Output {
+    text: String,
+    json: Value,
+}
+
+impl Output {
+    fn message(text: impl Into<String>) -> Self {
+        let text = text.into();
+        let json = json!({ "message": text });
+        Self { text, json }
+    }
+}
+
+/// Signs in, runs `command` and prints what it returns.
+pub async fn run(command: Command, json: bool, sign_in: SignIn) -> color_eyre::Result<()> {
+    let command = match command {
+        Command::Status {
+            format,
+            follow,
+            interval,
+            waybar,
+        } => {
+            let interval = Duration::from_secs(interval);
+            return status(format, follow, interval, waybar, json).await;
+        }
+        Command::Ctl { command } => return ctl(command, json).await,
+        command => command,
+    };
+    let spotify = auth::sign_in(sign_in).await?;
+    let output = execute(command, &spotify).await;
+    auth::save_token(&spotify);
+    let output = output?;
+    if json {
+        println!("{}", output.json);
+    } else if !output.text.is_empty() {
+        println!("{}", output.text);
+    }
+    Ok(())
+}
+
+async fn execute(command: Command, spotify: &Spotify) -> color_eyre::Result<Output> {
+    match command {
+        Command::Play { uri } => {
+            let uri = uri.as_deref().map(parse_uri);
+            api::play(spotify, uri.as_deref()).await?;
+            Ok(Output::message(match uri {
+                Some(uri) => format!("Playing {uri}"),
+                None => "Resumed playback".to_string(),
+            }))
+        }
+        Command::Pause => {
+            spotify_rs::pause_playback(None, spotify).await?;
+            Ok(Output::message("Paused playback"))
+        }
+        Command::Next => {
+            spotify_rs::skip_to_next(None, spotify).await?;
+            Ok(Output::message("Skipped to the next track"))
+        }
+        Command::Prev => {
+            spotify_rs::skip_to_previous(None, spotify).await?;
+            Ok(Output::message("Skipped to the previous track"))
+        }
+        Command::Volume { percent } => {
+            spotify_rs::set_playback_volume(u32::from(percent))
+                .send(spotify)
+                .await?;
+            Ok(Output::message(format!("Set the volume to {percent}%")))
+        }
+        Command::Status { .. } | Command::Ctl { .. } => unreachable!("run without a client"),
+        Command::Like { uris } => {
+            let uris = if uris.is_empty() {
+                let state = api::playback_state(spotify).await?;
+                let uri = Status::new(state.as_ref()).uri;
+                vec![uri.ok_or_eyre("Nothing is playing")?]
+            } else {
+                uris.iter().map(|uri| parse_uri(uri)).collect()
+            };
+            let mut ids = Vec::with_capacity(uris.len());
+            for uri in &uris {
+                let Some(id) = uri.strip_prefix("spotify:track:") else {
+                    bail!("Only tracks can be liked, not {uri}");
+                };
+                ids.push(id);
+            }
+            for chunk in ids.chunks(api::LIBRARY_BATCH_SIZE) {
+                spotify_rs::save_tracks(chunk, spotify).await?;
+            }
+            Ok(Output::message(format!("Liked {}", uris.join(", "))))
+        }
+        Command::Queue { uri } => {
+            let uri = parse_uri(&uri);
+            spotify_rs::add_item_to_queue(&uri).send(spotify).await?;
+            Ok(Output::message(format!("Queued {uri}")))
+        }
+        Command::Search { query, kind, limit } => {
+            let query = query.join(" ");
+            let item = match kind {
+                SearchKind::Track => Item::Track,
+                SearchKind::Artist => Item::Artist,
+                SearchKind::Playlist => Item::Playlist,
+            };
+            let results = spotify_rs::search(query, &[item])
+                .limit(limit)
+                .get(spotify)
+                .await?;
+            let liked = HashMap::new();
+            Ok(match kind {
+                SearchKind::Track => {
+                    let tracks = results.tracks.map(|page| page.items).unwrap_or_default();
+                    rows(tracks.iter().flatten(), &TRACK_COLUMNS, &liked, |t| &t.uri)
+                }
+                SearchKind::Artist => {
+                    let artists = results.artists.map(|page| page.items).unwrap_or_default();
+                    rows(artists.iter().flatten(), &ARTIST_COLUMNS, &liked, |a| {
+                        &a.uri
+                    })
+                }
+                SearchKind::Playlist => {
+                    let playlists = results.playlists.map(|page| page.items);
+                    let playlists = playlists.unwrap_or_default();
+                    rows(playlists.iter().flatten(), &PLAYLIST_COLUMNS, &liked, |p| {
+                        &p.uri
+                    })
+                }
+            })
+        }
+        Command::Playlist {
+            command: PlaylistCommand::List,
+        } => {
+            let playlists = api::all_user_playlists(spotify).await?;
+            Ok(rows(&playlists, &PLAYLIST_COLUMNS, &HashMap::new(), |p| {
+                &p.uri
+            }))
+        }
+        Command::Playlist {
+            command: PlaylistCommand::Export { playlist },
+        } => export_playlist(spotify, &playlist).await,
+    }
+}
+
+/// Prints the playback state, or with `follow` a new line whenever it changes.
+/// The player is polled with the saved access token, so this is cheap enough
+/// to run from a status bar every few seconds.
+async fn status(
+    format: Option<Template>,
+    follow: bool,
+    interval: Duration,
+    waybar: bool,
+    json: bool,
+) -> color_eyre::Result<()> {
+    let template = format.clone().unwrap_or_default();
+    let render = |status: &Status| -> color_eyre::Result<String> {
+        Ok(if json {
+            serde_json::to_string(status)?
+        } else if waybar {
+            status.waybar(&template).to_string()
+        } else if status.title.is_some() {
+            template.render(status)
+        } else if format.is_some() {
+            // Leaves the bar empty rather than showing an empty template.
+            String::new()
+        } else {
+            "Nothing is playing".to_string()
+        })
+    };
+    let mut last = None;
+    loop {
+        match poll_status().await.and_then(|status| render(&status)) {
+            Ok(line) if last.as_ref() != Some(&line) => {
+                println!("{line}");
+                last = Some(line);
+            }
+            Ok(_) => {}
+            // A bar keeps the last line shown while the network is down.
+            Err(e) if follow => eprintln!("Failed to get the playback state: {e}"),
+            Err(e) => return Err(e),
+        }
+        if !follow {
+            return Ok(());
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn poll_status() -> color_eyre::Result<Status> {
+    let access_token = auth::access_token().await?;
+    let state = api::playback_state_with_token(&access_token).await?;
+    Ok(Status::new(state.as_ref()))
+}
+
+/// Sends a request to the running spotui and prints the JSON it answers with.
+/// Without `--json`, plain acknowledgements are left out.
+async fn ctl(command: CtlCommand, json: bool) -> color_eyre::Result<()> {
+    let request = match command {
+        CtlCommand::Play { uri } => Request::Play {
+            uri: uri.as_deref().map(parse_uri),
+        },
+        CtlCommand::Pause => Request::Pause,
+        CtlCommand::Next => Request::Next,
+        CtlCommand::Prev => Request::Prev,
+        CtlCommand::Queue { uri } => Request::Queue {
+            uri: parse_uri(&uri),
+        },
+        CtlCommand::Seek { seconds } => Request::Seek {
+            position_ms: seconds.saturating_mul(1000),
+        },
+        CtlCommand::Volume { percent } => Request::Volume { percent },
+        CtlCommand::Shuffle { enabled } => Request::Shuffle { enabled },
+        CtlCommand::Repeat { mode } => Request::Repeat { mode },
+        CtlCommand::Navigate { view, playlist } => Request::Navigate {
+            view: match view {
+                CtlView::Playlists => View::UserPlaylists,
+                CtlView::TopTracks => View::UserTopTracks,
+                CtlView::TopArtists => View::UserTopArtists,
+                CtlView::Playlist => {
+                    let playlist = playlist.unwrap_or_default();
+                    let uri = parse_uri(&playlist);
+                    View::Playlist {
+                        id: uri
+                            .strip_prefix("spotify:playlist:")
+                            .unwrap_or(&uri)
+                            .to_string(),
+                        name: String::new(),
+                    }
+                }
+            },
+        },
+        CtlCommand::State => Request::State,
+        CtlCommand::Subscribe => Request::Subscribe,
+    };
+    ipc::send(&request, |line| {
+        let reply: Value = serde_json::from_str(line)?;
+        if let Some(error) = reply.get("error").and_then(Value::as_str) {
+            bail!("{error}");
+        }
+        if json || reply != json!({ "ok": true }) {
+            println!("{line}");
+        }
+        Ok(())
+    })
+    .await
+}
+
+async fn export_playlist(spotify: &Spotify, name: &str) -> color_eyre::Result<Output> {
+    let columns = Config::load()?.columns.tracks;
+    let playlist = find_playlist(spotify, name).await?;
+    let items = spotify_rs::playlist_items(&playlist.id)
+        .limit(50)
+        .get(spotify)
+        .await?
+        .get_remaining(spotify)
+        .await?;
+    let mut liked = HashMap::new();
+    if columns.contains(&Column::Liked) {
+        let ids: Vec<String> = items
+            .iter()
+            .flatten()
+            .filter_map(|item| match &item.track {
+                PlayableItem::Track(track) => Some(track.id.clone()),
+                PlayableItem::Episode(_) => None,
+            })
+            .collect();
+        liked.extend(api::liked_tracks(spotify, &ids).await?);
+    }
+    let (csv, _) = export::to_csv(&items, &columns, &liked, |_| true);
+    let json_columns: Vec<Column> = columns
+        .into_iter()
+        .filter(|&column| column != Column::Index)
+        .collect();
+    let json = rows(
+        items.iter().flatten(),
+        &json_columns,
+        &liked,
+        |item| match &item.track {
+            PlayableItem::Track(track) => &track.uri,
+            PlayableItem::Episode(episode) => &episode.uri,
+        },
+    )
+    .json;
+    Ok(Output {
+        text: csv.trim_end().to_string(),
+        json,
+    })
+}
+
+/// The user's playlist called `name`, ignoring case, or with `name` as its
+/// URI, link or ID.
+async fn find_playlist(spotify: &Spotify, name: &str) -> color_eyre::Result<SimplifiedPlaylist> {
+    let uri = parse_uri(name);
+    let id = uri.strip_prefix("spotify:playlist:").unwrap_or(name);
+    let playlists = api::all_user_playlists(spotify).await?;
+    playlists
+        .into_iter()
+        .find(|p| p.id == id || p.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| color_eyre::eyre::eyre!("No playlist named {name}"))
+}
+
+/// One line per item with its `columns` separated by tabs and its URI last,
+/// or a JSON array of objects keyed by column name.
+fn rows<'a, T: TableRow + 'a>(
+    items: impl IntoIterator<Item = &'a T>,
+    columns: &[Column],
+    liked: &HashMap<String, bool>,
+    uri: impl Fn(&T) -> &str,
+) -> Output {
+    let mut lines = Vec::new();
+    let mut objects = Vec::new();
+    for item in items {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|&column| item.cell(column, liked).unwrap_or_default())
+            .collect();
+        lines.push(format!("{}\t{}", cells.join("\t"), uri(item)));
+        let mut object: Map<String, Value> = columns
+            .iter()
+            .zip(cells)
+            .map(|(column, cell)| (column.name().to_string(), Value::String(cell)))
+            .collect();
+        object.insert("uri".to_string(), Value::String(uri(item).to_string()));
+        objects.push(Value::Object(object));
+    }
+    Output {
+        text: lines.join("\n"),
+        json: Value::Array(objects),
+    }
+}
+
+/// Turns an open.spotify.com link into a Spotify URI. Anything else is
+/// returned as is.
+fn parse_uri(uri: &str) -> String {
+    let Some(path) = uri
+        .strip_prefix("https://open.spotify.com/")
+        .map(|path| path.split(['?', '#']).next().unwrap_or(path))
+    else {
+        return uri.to_string();
+    };
+    // Localized links start with a segment such as `intl-de`.
+    let segments: Vec<&str> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty() && !segment.starts_with("intl-"))
+        .collect();
+    match segments[..] {
+        [kind, id] => format!("spotify:{kind}:{id}"),
+        _ => uri.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turns_links_into_uris() {
+        for (link, uri) in [
+            (
+                "https://open.spotify.com/track/4vLYewWIvqHfKtJDk8c8tq",
+                "spotify:track:4vLYewWIvqHfKtJDk8c8tq",
+            ),
+            (
+                "https://open.spotify.com/album/1weenld61qoidwYuZ1GESA?si=ab12#top",
+                "spotify:album:1weenld61qoidwYuZ1GESA",
+            ),
+            (
+                "https://open.spotify.com/intl-de/playlist/37i9dQZF1DXcBWIGoYBM5M",
+                "spotify:playlist:37i9dQZF1DXcBWIGoYBM5M",
+            ),
+            (
+                "https://open.spotify.com/artist/0kbYTNQb4Pb1rPbbaF0pT4/",
+                "spotify:artist:0kbYTNQb4Pb1rPbbaF0pT4",
+            ),
+        ] {
+            assert_eq!(parse_uri(link), uri);
+        }
+    }
+
+    #[test]
+    fn leaves_anything_else_alone() {
+        for uri in [
+            "spotify:track:4vLYewWIvqHfKtJDk8c8tq",
+            "https://open.spotify.com/",
+            "https://open.spotify.com/user/someone/playlist/37i9dQZF1DXcBWIGoYBM5M",
+            "https://example.com/track/4vLYewWIvqHfKtJDk8c8tq",
+            "Road Trip",
+        ] {
+            assert_eq!(parse_uri(uri), uri);
+        }
+    }
+}
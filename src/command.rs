@@ -0,0 +1,176 @@
+use crate::{keymap::Action, playlist_ops::PlaylistOperation, track_actions::TrackOption};
+
+/// Most recent commands kept in the palette history.
+pub const HISTORY_LEN: usize = 20;
+
+/// Something that can be run from the command palette.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    /// Anything a key can be bound to.
+    Action(Action),
+    GoTo,
+    OpenPlaylist,
+    /// Applied to the selected tracks of the shown list.
+    Track(TrackOption),
+    /// Applied to the selected playlists.
+    Playlists(PlaylistOperation),
+    Device,
+    Theme,
+    Export,
+    SyncSmartPlaylists,
+    Profile,
+}
+
+/// What a command asks for before it runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Argument {
+    Section,
+    Playlist,
+    /// A playlist owned by the user or collaborative.
+    WritablePlaylist,
+    Device,
+    Theme,
+    Profile,
+    /// Free text, offered no choices.
+    Path,
+}
+
+impl Argument {
+    pub fn prompt(self) -> &'static str {
+        match self {
+            Argument::Section => "Go to",
+            Argument::Playlist => "Playlist",
+            Argument::WritablePlaylist => "Add to playlist",
+            Argument::Device => "Device",
+            Argument::Theme => "Theme",
+            Argument::Profile => "Profile",
+            Argument::Path => "Export to file",
+        }
+    }
+}
+
+/// One choice for an [`Argument`]: what is shown and what the command receives.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Choice {
+    pub label: String,
+    pub value: String,
+}
+
+/// A row of the palette: a command, a recent invocation or an argument choice.
+#[derive(Debug, Clone)]
+pub struct PaletteEntry {
+    pub text: String,
+    pub detail: String,
+    pub invocation: Invocation,
+}
+
+/// A command with its argument, ready to run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation {
+    pub command: Command,
+    pub argument: Option<Choice>,
+}
+
+impl Invocation {
+    /// The invocation as typed, e.g. `theme nord`.
+    pub fn text(&self) -> String {
+        match &self.argument {
+            Some(choice) => format!("{} {}", self.command.name(), choice.label),
+            None => self.command.name().to_string(),
+        }
+    }
+}
+
+impl Command {
+    /// Every command, in the order the palette lists them.
+    pub fn all() -> Vec<Command> {
+        let track_options = TrackOption::ALL
+            .into_iter()
+            .filter(|option| !matches!(option, TrackOption::GoToArtist | TrackOption::GoToAlbum))
+            .map(Command::Track);
+        [Command::GoTo, Command::OpenPlaylist]
+            .into_iter()
+            .chain(track_options)
+            .chain(PlaylistOperation::ALL.into_iter().map(Command::Playlists))
+            .chain([
+                Command::Device,
+                Command::Theme,
+                Command::Export,
+                Command::SyncSmartPlaylists,
+                Command::Profile,
+            ])
+            .chain(
+                Action::ALL
+                    .into_iter()
+                    .filter(|&action| action != Action::CommandPalette)
+                    .map(Command::Action),
+            )
+            .collect()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::Action(action) => action.name(),
+            Command::GoTo => "go-to",
+            Command::OpenPlaylist => "open-playlist",
+            Command::Track(option) => match option {
+                TrackOption::Play => "play",
+                TrackOption::AddToQueue => "queue",
+                TrackOption::AddToPlaylist => "add-to-playlist",
+                TrackOption::Like => "like",
+                TrackOption::RemoveFromPlaylist => "remove-from-playlist",
+                TrackOption::GoToArtist => "go-to-artist",
+                TrackOption::GoToAlbum => "go-to-album",
+            },
+            Command::Playlists(op) => match op {
+                PlaylistOperation::Merge => "merge-playlists",
+                PlaylistOperation::SplitByArtist => "split-by-artist",
+                PlaylistOperation::SplitByDecade => "split-by-decade",
+                PlaylistOperation::SplitByGenre => "split-by-genre",
+                PlaylistOperation::ShuffledCopy => "shuffled-copy",
+            },
+            Command::Device => "device",
+            Command::Theme => "theme",
+            Command::Export => "export",
+            Command::SyncSmartPlaylists => "sync-smart-playlists",
+            Command::Profile => "profile",
+        }
+    }
+
+    pub fn description(self) -> String {
+        match self {
+            Command::Action(action) => action.description().to_string(),
+            Command::GoTo => "Show playlists, top tracks or top artists".to_string(),
+            Command::OpenPlaylist => "Open one of your playlists".to_string(),
+            Command::Track(option) => match option {
+                TrackOption::Play => "Play the selected tracks",
+                TrackOption::AddToQueue => "Add the selected tracks to the queue",
+                TrackOption::AddToPlaylist => "Add the selected tracks to a playlist",
+                TrackOption::Like => "Save the selected tracks to liked songs",
+                TrackOption::RemoveFromPlaylist => "Remove the selected tracks from the playlist",
+                TrackOption::GoToArtist => "Show the artist of the track",
+                TrackOption::GoToAlbum => "Show the album of the track",
+            }
+            .to_string(),
+            Command::Playlists(op) => format!("{} (selected playlists)", op.label()),
+            Command::Device => "Move playback to another device".to_string(),
+            Command::Theme => "Switch to a bundled theme".to_string(),
+            Command::Export => "Write the shown list to a CSV file".to_string(),
+            Command::SyncSmartPlaylists => "Sync smart playlists now".to_string(),
+            Command::Profile => "Switch to another account's profile".to_string(),
+        }
+    }
+
+    pub fn argument(self) -> Option<Argument> {
+        match self {
+            Command::GoTo => Some(Argument::Section),
+            Command::OpenPlaylist => Some(Argument::Playlist),
+            Command::Track(TrackOption::AddToPlaylist) => Some(Argument::WritablePlaylist),
+            Command::Device => Some(Argument::Device),
+            Command::Theme => Some(Argument::Theme),
+            Command::Profile => Some(Argument::Profile),
+            Command::Export => Some(Argument::Path),
+            _ => None,
+        }
+    }
+}
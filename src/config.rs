@@ -0,0 +1,164 @@
+use crate::{
+    artwork::ArtworkConfig, keymap::KeymapConfig, notifications::NotificationsConfig,
+    panes::LayoutConfig, theme::ThemeConfig, web_remote::WebRemoteConfig, widgets::table::Column,
+};
+use color_eyre::eyre::{OptionExt, WrapErr, bail};
+use serde::Deserialize;
+use std::{
+    path::PathBuf,
+    sync::{PoisonError, RwLock},
+};
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// Name of the profile kept directly in spotui's directories.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Directory holding the other profiles, in both the config and cache directory.
+const PROFILES_DIR: &str = "profiles";
+
+/// The profile in use, `None` for the default one.
+static PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub smart_playlists: SmartPlaylistsConfig,
+    pub columns: ColumnsConfig,
+    pub keymap: KeymapConfig,
+    pub theme: ThemeConfig,
+    pub layout: LayoutConfig,
+    pub artwork: ArtworkConfig,
+    pub notifications: NotificationsConfig,
+    pub web_remote: WebRemoteConfig,
+    pub spotify: SpotifyConfig,
+}
+
+/// The `[spotify]` config, for profiles signing in with their own Spotify app.
+/// What isn't set is taken from the environment.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SpotifyConfig {
+    /// Instead of `SPOTIFY_CLIENT_ID`.
+    pub client_id: Option<String>,
+    /// Instead of `SPOTIFY_REDIRECT_URI`.
+    pub redirect_uri: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ColumnsConfig {
+    /// Columns of track tables, in display order.
+    pub tracks: Vec<Column>,
+}
+
+impl Default for ColumnsConfig {
+    fn default() -> Self {
+        Self {
+            tracks: Column::DEFAULT_TRACK_COLUMNS.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct SmartPlaylistsConfig {
+    /// Seconds between background syncs. `0` only syncs once at startup.
+    pub sync_interval_secs: u64,
+    pub playlist: Vec<SmartPlaylistConfig>,
+}
+
+impl Default for SmartPlaylistsConfig {
+    fn default() -> Self {
+        Self {
+            sync_interval_secs: 30 * 60,
+            playlist: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SmartPlaylistConfig {
+    pub name: String,
+    pub rule: String,
+    pub description: Option<String>,
+}
+
+impl Config {
+    /// Directory holding the config file and anything else spotui persists
+    /// for the profile in use.
+    pub fn dir() -> color_eyre::Result<PathBuf> {
+        let dir = dirs::config_dir().ok_or_eyre("Could not determine config directory")?;
+        Ok(profile_dir(dir.join("spotui")))
+    }
+
+    /// Directory the profile in use caches API responses in, `None` if
+    /// there's no cache directory.
+    pub fn cache_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| profile_dir(dir.join("spotui")))
+    }
+
+    /// Name of the profile in use.
+    pub fn profile() -> String {
+        let profile = PROFILE.read().unwrap_or_else(PoisonError::into_inner);
+        profile.as_deref().unwrap_or(DEFAULT_PROFILE).to_string()
+    }
+
+    /// Makes `name` the profile whose directories are used from now on.
+    pub fn set_profile(name: &str) -> color_eyre::Result<()> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!("Invalid profile name `{name}`, use letters, digits, `-` and `_`");
+        }
+        let profile = (name != DEFAULT_PROFILE).then(|| name.to_string());
+        *PROFILE.write().unwrap_or_else(PoisonError::into_inner) = profile;
+        Ok(())
+    }
+
+    /// The default profile followed by every profile with a directory, by name.
+    pub fn profiles() -> color_eyre::Result<Vec<String>> {
+        let dir = dirs::config_dir()
+            .ok_or_eyre("Could not determine config directory")?
+            .join("spotui")
+            .join(PROFILES_DIR);
+        let mut profiles = Vec::new();
+        if dir.exists() {
+            for entry in std::fs::read_dir(&dir)
+                .wrap_err_with(|| format!("Failed to list {}", dir.display()))?
+            {
+                let entry = entry?;
+                if entry.file_type()?.is_dir()
+                    && let Some(name) = entry.file_name().to_str()
+                {
+                    profiles.push(name.to_string());
+                }
+            }
+        }
+        profiles.sort();
+        profiles.retain(|name| name != DEFAULT_PROFILE);
+        profiles.insert(0, DEFAULT_PROFILE.to_string());
+        Ok(profiles)
+    }
+
+    pub fn load() -> color_eyre::Result<Self> {
+        let path = Self::dir()?.join(CONFIG_FILE);
+        if !path.exists() {
+            log::info!("No config file at {}, using defaults", path.display());
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&contents).wrap_err_with(|| format!("Invalid config in {}", path.display()))
+    }
+}
+
+/// `dir`, or the directory of the profile in use under it.
+fn profile_dir(dir: PathBuf) -> PathBuf {
+    match &*PROFILE.read().unwrap_or_else(PoisonError::into_inner) {
+        Some(name) => dir.join(PROFILES_DIR).join(name),
+        None => dir,
+    }
+}
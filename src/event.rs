@@ -1,7 +1,17 @@
+use crate::{auth::Spotify, command::Invocation, ipc::Request, status::Status};
 use color_eyre::eyre::OptionExt;
 use futures::{FutureExt, StreamExt};
+use image::DynamicImage;
 use ratatui::crossterm::event::Event as CrosstermEvent;
-use std::time::Duration;
+use spotify_rs::model::{
+    Page,
+    artist::Artist,
+    player::Device,
+    playlist::{PlaylistItem, SimplifiedPlaylist},
+    track::Track,
+    user::PrivateUser,
+};
+use std::{sync::Arc, time::Duration};
 use tokio::sync::mpsc;
 
 const TICK_FPS: f64 = 30.0;
@@ -13,12 +23,126 @@ pub enum Event {
     App(AppEvent),
 }
 
+/// Progress of a long-running background operation.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    pub label: String,
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Sends the events of background work started for the profile in use, as
+/// [`AppEvent::Profile`], so that those arriving after switching to another
+/// profile are dropped instead of mixing two accounts.
+#[derive(Clone, Debug)]
+pub struct ProfileSender {
+    sender: mpsc::UnboundedSender<Event>,
+    generation: u64,
+}
+
+impl ProfileSender {
+    pub fn new(sender: mpsc::UnboundedSender<Event>, generation: u64) -> Self {
+        Self { sender, generation }
+    }
+
+    pub fn send(&self, event: AppEvent) {
+        let event = AppEvent::Profile(self.generation, Box::new(event));
+        let _ = self.sender.send(Event::App(event));
+    }
+
+    /// Whether the app has quit.
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+}
+
+/// Sends [`AppEvent::Progress`] updates for one labelled operation.
+#[derive(Clone, Debug)]
+pub struct ProgressReporter {
+    sender: ProfileSender,
+    label: String,
+}
+
+impl ProgressReporter {
+    pub fn new(sender: ProfileSender, label: impl Into<String>) -> Self {
+        Self {
+            sender,
+            label: label.into(),
+        }
+    }
+
+    pub fn report(&self, detail: &str, done: usize, total: usize) {
+        self.sender.send(AppEvent::Progress(Progress {
+            label: format!("{}: {detail}", self.label),
+            done,
+            total,
+        }));
+    }
+
+    pub fn done(&self, summary: String) {
+        self.sender.send(AppEvent::ProgressDone(summary));
+    }
+}
+
+/// The paged lists that can be loaded in the background.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ListKind {
+    UserPlaylists,
+    UserTopTracks,
+    UserTopArtists,
+    Playlist,
+}
+
+/// A page fetched in the background for one of the paged lists.
+#[derive(Clone, Debug)]
+pub enum ListPage {
+    UserPlaylists(Page<SimplifiedPlaylist>),
+    UserTopTracks(Page<Track>),
+    UserTopArtists(Page<Artist>),
+    /// A page of the playlist with the given ID.
+    Playlist(String, Page<PlaylistItem>),
+}
+
 #[derive(Clone, Debug)]
 pub enum AppEvent {
     Quit,
     Select,
     Next,
+    /// Go back to the previous view in the navigation history.
+    Back,
+    /// Go forward again in the navigation history.
+    Forward,
     Init,
+    SyncSmartPlaylists,
+    Progress(Progress),
+    ProgressDone(String),
+    PageLoaded(ListPage),
+    /// The user's profile, fetched again after showing the cached one.
+    UserFetched(Box<PrivateUser>),
+    /// A fresh first page replacing a list shown from the cache.
+    ListFetched(ListPage),
+    /// Background loading of a list finished, successfully or not.
+    PagesDone(ListKind),
+    /// Fetch a list again from its first page.
+    ReloadList(ListKind),
+    /// Whether each track ID is in the user's liked songs.
+    LikedChecked(Vec<(String, bool)>),
+    /// A command picked in the command palette.
+    RunCommand(Invocation),
+    /// The user's playback devices, for the palette's `device` command.
+    DevicesLoaded(Vec<Device>),
+    /// Cover art fetched for a URL, `None` if it couldn't be loaded.
+    ArtworkLoaded(String, Option<Arc<DynamicImage>>),
+    /// A command received on the control socket.
+    Remote(Request),
+    /// The playback state, polled in the background while online.
+    PlaybackPolled(Box<Status>),
+    /// Signing in to the named profile after switching to it finished,
+    /// `None` if it failed.
+    ProfileSignedIn(String, Option<Arc<Spotify>>),
+    /// An event sent through a [`ProfileSender`], for the profile that was in
+    /// use as of the given number of switches.
+    Profile(u64, Box<AppEvent>),
 }
 
 /// Terminal event handler.
@@ -43,6 +167,10 @@ impl EventHandler {
             .ok_or_eyre("Failed to receive event")
     }
 
+    pub fn sender(&self) -> mpsc::UnboundedSender<Event> {
+        self.sender.clone()
+    }
+
     pub fn send(&mut self, app_event: AppEvent) {
         let _ = self.sender.send(Event::App(app_event));
     }
@@ -0,0 +1,52 @@
+use crate::widgets::table::{Column, TableRow};
+use color_eyre::eyre::WrapErr;
+use std::{collections::HashMap, path::Path};
+
+/// Writes the rows of a table for which `visible` holds to `path` as CSV,
+/// returning the number of rows written.
+pub fn write_csv<T: TableRow>(
+    path: &Path,
+    items: &[Option<T>],
+    columns: &[Column],
+    liked: &HashMap<String, bool>,
+    visible: impl Fn(usize) -> bool,
+) -> color_eyre::Result<usize> {
+    let (csv, rows) = to_csv(items, columns, liked, visible);
+    std::fs::write(path, csv).wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+    Ok(rows)
+}
+
+/// The rows of a table for which `visible` holds as CSV, and how many there are.
+pub fn to_csv<T: TableRow>(
+    items: &[Option<T>],
+    columns: &[Column],
+    liked: &HashMap<String, bool>,
+    visible: impl Fn(usize) -> bool,
+) -> (String, usize) {
+    let mut csv = record(columns.iter().map(|c| c.name().to_string()));
+    let mut rows = 0;
+    for (i, item) in items.iter().enumerate() {
+        let Some(item) = item.as_ref().filter(|_| visible(i)) else {
+            continue;
+        };
+        csv += &record(columns.iter().map(|&column| match column {
+            Column::Index => (i + 1).to_string(),
+            _ => item.cell(column, liked).unwrap_or_default(),
+        }));
+        rows += 1;
+    }
+    (csv, rows)
+}
+
+fn record(fields: impl Iterator<Item = String>) -> String {
+    let fields: Vec<String> = fields
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect();
+    fields.join(",") + "\n"
+}
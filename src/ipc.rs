@@ -0,0 +1,108 @@
+use crate::{navigation::View, status::Status};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+#[cfg(not(unix))]
+use {crate::event::Event, tokio::sync::mpsc};
+
+#[cfg(unix)]
+mod socket;
+
+#[cfg(unix)]
+pub use socket::{Server, send};
+
+/// A command for a running spotui, sent to its socket as a line of JSON such
+/// as `{"command": "queue", "uri": "spotify:track:…"}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum Request {
+    /// Resume playback, or play a track, album, playlist or artist.
+    Play {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        uri: Option<String>,
+    },
+    Pause,
+    Next,
+    Prev,
+    Queue {
+        uri: String,
+    },
+    /// Jump to a position in the playing item.
+    Seek {
+        position_ms: u32,
+    },
+    Volume {
+        percent: u8,
+    },
+    Shuffle {
+        enabled: bool,
+    },
+    Repeat {
+        mode: Repeat,
+    },
+    /// Show a view in the main content area.
+    Navigate {
+        view: View,
+    },
+    /// Reply with the shown view and the playback state.
+    State,
+    /// Keep the connection open and send every [`Notice`] from now on.
+    Subscribe,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Repeat {
+    Off,
+    Track,
+    /// The album or playlist.
+    Context,
+}
+
+/// What a running spotui shows and plays.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct State {
+    pub view: Option<View>,
+    pub selected: Option<usize>,
+    /// `None` until the player has been polled, which it isn't offline.
+    pub playback: Option<Status>,
+}
+
+/// Sent to subscribers as a line of JSON such as `{"event": "track-changed", …}`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Notice {
+    /// Another track or episode started, or playback stopped.
+    TrackChanged { playback: Status },
+    /// Playback was paused or resumed.
+    PlaybackState { playback: Status },
+}
+
+/// Stands in for the control socket on systems without Unix sockets, where
+/// the app runs without one.
+#[cfg(not(unix))]
+#[derive(Debug)]
+pub enum Server {}
+
+#[cfg(not(unix))]
+impl Server {
+    pub fn start(_sender: mpsc::UnboundedSender<Event>) -> Option<Self> {
+        log::info!("Not listening for commands, this system has no Unix sockets");
+        None
+    }
+
+    pub fn notify(&self, _notice: Notice) {
+        match *self {}
+    }
+
+    pub fn publish(&self, _state: State) {
+        match *self {}
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn send(
+    _request: &Request,
+    _on_line: impl FnMut(&str) -> color_eyre::Result<()>,
+) -> color_eyre::Result<()> {
+    color_eyre::eyre::bail!("spotui ctl needs Unix sockets, which this system doesn't have")
+}
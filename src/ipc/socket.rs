@@ -0,0 +1,219 @@
+use super::{Notice, Request, State};
+use crate::event::{AppEvent, Event};
+use color_eyre::eyre::{WrapErr, bail};
+use serde::Serialize;
+use std::{
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream, unix::OwnedWriteHalf},
+    sync::{
+        broadcast::{self, error::RecvError},
+        mpsc, watch,
+    },
+};
+
+/// Notices kept for a subscriber that reads slower than they are sent.
+const NOTICE_BUFFER: usize = 16;
+
+/// The answer to every request. Commands are carried out after the reply is
+/// sent, so `ok` only says that one was understood; failures are logged.
+#[derive(Debug, Default, Serialize)]
+struct Reply {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<State>,
+}
+
+impl Reply {
+    fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Self::default()
+        }
+    }
+
+    fn error(error: String) -> Self {
+        Self {
+            error: Some(error),
+            ..Self::default()
+        }
+    }
+}
+
+/// Where a running spotui listens: the runtime directory, or a name with the
+/// user in it in the temp directory on systems without one.
+pub fn socket_path() -> PathBuf {
+    match dirs::runtime_dir() {
+        Some(dir) => dir.join("spotui.sock"),
+        None => std::env::temp_dir().join(match std::env::var("USER") {
+            Ok(user) => format!("spotui-{user}.sock"),
+            Err(_) => "spotui.sock".to_string(),
+        }),
+    }
+}
+
+/// The control socket of a running spotui. The socket file is removed again
+/// when this is dropped.
+#[derive(Debug)]
+pub struct Server {
+    path: PathBuf,
+    notices: broadcast::Sender<Notice>,
+    state: watch::Sender<State>,
+}
+
+impl Server {
+    /// Listens on [`socket_path`], passing requests on as [`AppEvent::Remote`]
+    /// through `sender`. Without a socket, such as when another spotui is
+    /// already listening, the app runs on without one.
+    pub fn start(sender: mpsc::UnboundedSender<Event>) -> Option<Self> {
+        let path = socket_path();
+        match Self::bind(&path, sender) {
+            Ok(server) => {
+                log::info!("Listening for commands on {}", path.display());
+                Some(server)
+            }
+            Err(e) => {
+                log::warn!("Not listening for commands: {e:#}");
+                None
+            }
+        }
+    }
+
+    fn bind(path: &Path, sender: mpsc::UnboundedSender<Event>) -> color_eyre::Result<Self> {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            bail!("another spotui is listening on {}", path.display());
+        }
+        // Left behind by a spotui that didn't exit cleanly.
+        let _ = std::fs::remove_file(path);
+        let listener = UnixListener::bind(path)
+            .wrap_err_with(|| format!("Failed to listen on {}", path.display()))?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        let (notices, _) = broadcast::channel(NOTICE_BUFFER);
+        let (state, _) = watch::channel(State::default());
+        tokio::spawn(accept(listener, sender, notices.clone(), state.subscribe()));
+        Ok(Self {
+            path: path.to_path_buf(),
+            notices,
+            state,
+        })
+    }
+
+    /// Sends `notice` to every subscriber.
+    pub fn notify(&self, notice: Notice) {
+        let _ = self.notices.send(notice);
+    }
+
+    /// Updates the state `state` requests are answered with.
+    pub fn publish(&self, state: State) {
+        self.state.send_if_modified(|current| {
+            let changed = *current != state;
+            if changed {
+                *current = state;
+            }
+            changed
+        });
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+async fn accept(
+    listener: UnixListener,
+    sender: mpsc::UnboundedSender<Event>,
+    notices: broadcast::Sender<Notice>,
+    state: watch::Receiver<State>,
+) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let (sender, notices, state) = (sender.clone(), notices.clone(), state.clone());
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, sender, notices, state).await {
+                        log::debug!("Control connection closed: {e}");
+                    }
+                });
+            }
+            Err(e) => {
+                log::error!("Failed to accept a control connection: {e}");
+                return;
+            }
+        }
+    }
+}
+
+/// Answers the requests of one connection, one reply per line.
+async fn serve(
+    stream: UnixStream,
+    sender: mpsc::UnboundedSender<Event>,
+    notices: broadcast::Sender<Notice>,
+    state: watch::Receiver<State>,
+) -> color_eyre::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str(&line) {
+            Err(e) => Reply::error(format!("Invalid request: {e}")),
+            Ok(Request::State) => Reply {
+                state: Some(state.borrow().clone()),
+                ..Reply::ok()
+            },
+            Ok(Request::Subscribe) => {
+                let mut notices = notices.subscribe();
+                write_line(&mut writer, &Reply::ok()).await?;
+                loop {
+                    match notices.recv().await {
+                        Ok(notice) => write_line(&mut writer, &notice).await?,
+                        Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => return Ok(()),
+                    }
+                }
+            }
+            Ok(request) => match sender.send(Event::App(AppEvent::Remote(request))) {
+                Ok(()) => Reply::ok(),
+                Err(_) => Reply::error("spotui is quitting".to_string()),
+            },
+        };
+        write_line(&mut writer, &reply).await?;
+    }
+    Ok(())
+}
+
+async fn write_line(writer: &mut OwnedWriteHalf, value: &impl Serialize) -> color_eyre::Result<()> {
+    let line = serde_json::to_string(value)? + "\n";
+    writer.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+/// Sends `request` to the running spotui, handing each line it answers with
+/// to `on_line`: the reply, followed by every notice after a subscribe.
+pub async fn send(
+    request: &Request,
+    mut on_line: impl FnMut(&str) -> color_eyre::Result<()>,
+) -> color_eyre::Result<()> {
+    let path = socket_path();
+    let stream = UnixStream::connect(&path)
+        .await
+        .wrap_err_with(|| format!("spotui isn't running, no socket at {}", path.display()))?;
+    let (reader, mut writer) = stream.into_split();
+    write_line(&mut writer, request).await?;
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        on_line(&line)?;
+        if *request != Request::Subscribe {
+            break;
+        }
+    }
+    Ok(())
+}
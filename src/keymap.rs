@@ -0,0 +1,602 @@
+use crate::app::ActiveBlock;
+use color_eyre::eyre::eyre;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+/// Section of the `[keymap]` config that applies to every block.
+const GLOBAL_SECTION: &str = "global";
+
+/// Something a key sequence can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    /// Clears the filter, then the selection, then quits.
+    Cancel,
+    NextBlock,
+    Up,
+    Down,
+    Top,
+    Bottom,
+    Select,
+    ToggleMark,
+    MarkRange,
+    CycleSort,
+    ReverseSort,
+    Filter,
+    PlaylistOperations,
+    MainTab,
+    LogTab,
+    Help,
+    CommandPalette,
+    Back,
+    Forward,
+    ToggleSidebar,
+    ShrinkSidebar,
+    GrowSidebar,
+}
+
+impl Action {
+    pub const ALL: [Action; 23] = [
+        Action::Quit,
+        Action::Cancel,
+        Action::NextBlock,
+        Action::Up,
+        Action::Down,
+        Action::Top,
+        Action::Bottom,
+        Action::Select,
+        Action::ToggleMark,
+        Action::MarkRange,
+        Action::CycleSort,
+        Action::ReverseSort,
+        Action::Filter,
+        Action::PlaylistOperations,
+        Action::MainTab,
+        Action::LogTab,
+        Action::Help,
+        Action::CommandPalette,
+        Action::Back,
+        Action::Forward,
+        Action::ToggleSidebar,
+        Action::ShrinkSidebar,
+        Action::GrowSidebar,
+    ];
+
+    /// Name of the action in the `[keymap]` config.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Cancel => "cancel",
+            Action::NextBlock => "next-block",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::Select => "select",
+            Action::ToggleMark => "toggle-mark",
+            Action::MarkRange => "mark-range",
+            Action::CycleSort => "cycle-sort",
+            Action::ReverseSort => "reverse-sort",
+            Action::Filter => "filter",
+            Action::PlaylistOperations => "playlist-operations",
+            Action::MainTab => "main-tab",
+            Action::LogTab => "log-tab",
+            Action::Help => "help",
+            Action::CommandPalette => "command-palette",
+            Action::Back => "back",
+            Action::Forward => "forward",
+            Action::ToggleSidebar => "toggle-sidebar",
+            Action::ShrinkSidebar => "shrink-sidebar",
+            Action::GrowSidebar => "grow-sidebar",
+        }
+    }
+
+    /// What the action does, as shown in the help overlay.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::Cancel => "Clear the filter, then the selection, then quit",
+            Action::NextBlock => "Switch between the directory and the content",
+            Action::Up => "Move up",
+            Action::Down => "Move down",
+            Action::Top => "Jump to the first row",
+            Action::Bottom => "Jump to the last row",
+            Action::Select => "Open or act on the current row",
+            Action::ToggleMark => "Select or deselect the current row",
+            Action::MarkRange => "Start or end a range selection",
+            Action::CycleSort => "Sort by the next column",
+            Action::ReverseSort => "Reverse the sort order",
+            Action::Filter => "Filter the list",
+            Action::PlaylistOperations => "Merge, split or shuffle the selected playlists",
+            Action::MainTab => "Show the main tab",
+            Action::LogTab => "Show the log",
+            Action::Help => "Show or hide this help",
+            Action::CommandPalette => "Run a command by name",
+            Action::Back => "Go back to the previous view",
+            Action::Forward => "Go forward again after going back",
+            Action::ToggleSidebar => "Hide or show the directory sidebar",
+            Action::ShrinkSidebar => "Make the directory sidebar narrower",
+            Action::GrowSidebar => "Make the directory sidebar wider",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// A single key press with the modifiers that matter for bindings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// Normalises the chord so that a parsed binding and a key event compare
+    /// equal: shift is carried by the character itself, and control or alt
+    /// chords are matched case-insensitively.
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match code {
+            KeyCode::Char(c) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                if modifiers.is_empty() {
+                    KeyCode::Char(c)
+                } else {
+                    KeyCode::Char(c.to_ascii_lowercase())
+                }
+            }
+            KeyCode::BackTab => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::BackTab
+            }
+            code => code,
+        };
+        Self { code, modifiers }
+    }
+
+    /// Parses a chord such as `q`, `G`, `ctrl-c`, `shift-tab` or `pagedown`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            let (modifier, len) = if lower.starts_with("ctrl-") {
+                (KeyModifiers::CONTROL, 5)
+            } else if lower.starts_with("alt-") {
+                (KeyModifiers::ALT, 4)
+            } else if lower.starts_with("shift-") {
+                (KeyModifiers::SHIFT, 6)
+            } else {
+                break;
+            };
+            modifiers |= modifier;
+            rest = &rest[len..];
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "esc" => KeyCode::Esc,
+                "enter" => KeyCode::Enter,
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "space" => KeyCode::Char(' '),
+                name => KeyCode::F(name.strip_prefix('f')?.parse().ok()?),
+            },
+        };
+        Some(Self::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            KeyCode::BackTab => write!(f, "shift-tab"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Delete => write!(f, "delete"),
+            KeyCode::Insert => write!(f, "insert"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            KeyCode::Up => write!(f, "up"),
+            KeyCode::Down => write!(f, "down"),
+            KeyCode::Left => write!(f, "left"),
+            KeyCode::Right => write!(f, "right"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// Keys bound to one action: a single sequence or a list of alternatives.
+/// A sequence is written as space-separated chords, e.g. `"g g"`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeyBindings {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyBindings {
+    fn sequences(&self) -> &[String] {
+        match self {
+            KeyBindings::One(sequence) => std::slice::from_ref(sequence),
+            KeyBindings::Many(sequences) => sequences,
+        }
+    }
+}
+
+/// The `[keymap]` config: action names to keys, per section. The section is
+/// `global` or the name of a block, e.g. `playlist` or `user-top-tracks`.
+pub type KeymapConfig = BTreeMap<String, BTreeMap<String, KeyBindings>>;
+
+type Bindings = HashMap<Vec<KeyChord>, Action>;
+
+/// The keys bound to one action, as listed in the help overlay.
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub action: Action,
+    pub keys: Vec<String>,
+    /// The block the keys are bound in, `None` for global keys.
+    pub block: Option<ActiveBlock>,
+}
+
+/// Outcome of looking up the keys pressed so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolved {
+    Action(Action),
+    /// The keys are the start of a longer sequence.
+    Pending,
+    Unbound,
+}
+
+/// Key sequences bound to actions, globally and per block. A block's
+/// bindings take precedence over the global ones, including global sequences
+/// they share a prefix with: a block's `g` hides the global `g g` there.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    global: Bindings,
+    blocks: HashMap<ActiveBlock, Bindings>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bind = |pairs: &[(&str, Action)]| -> Bindings {
+            pairs
+                .iter()
+                .map(|&(keys, action)| (parse_sequence(keys).unwrap(), action))
+                .collect()
+        };
+        Self {
+            global: bind(&[
+                ("q", Action::Quit),
+                ("ctrl-c", Action::Quit),
+                ("esc", Action::Cancel),
+                ("tab", Action::NextBlock),
+                ("up", Action::Up),
+                ("k", Action::Up),
+                ("down", Action::Down),
+                ("j", Action::Down),
+                ("g g", Action::Top),
+                ("G", Action::Bottom),
+                ("enter", Action::Select),
+                ("space", Action::ToggleMark),
+                ("V", Action::MarkRange),
+                ("s", Action::CycleSort),
+                ("S", Action::ReverseSort),
+                ("/", Action::Filter),
+                ("1", Action::MainTab),
+                ("2", Action::LogTab),
+                ("?", Action::Help),
+                (":", Action::CommandPalette),
+                ("ctrl-p", Action::CommandPalette),
+                ("backspace", Action::Back),
+                ("h", Action::Back),
+                ("l", Action::Forward),
+                ("b", Action::ToggleSidebar),
+                ("<", Action::ShrinkSidebar),
+                (">", Action::GrowSidebar),
+            ]),
+            blocks: HashMap::from([
+                (
+                    ActiveBlock::UserPlaylists,
+                    bind(&[("o", Action::PlaylistOperations)]),
+                ),
+                (ActiveBlock::Help, bind(&[("q", Action::Help)])),
+            ]),
+        }
+    }
+}
+
+impl Keymap {
+    /// The default keymap with the bindings from `config` applied. Listing an
+    /// action in a section replaces its default keys in that section; an empty
+    /// list unbinds it. Every problem is reported at once.
+    pub fn from_config(config: &KeymapConfig) -> color_eyre::Result<Self> {
+        let mut keymap = Self::default();
+        let mut errors = Vec::new();
+        for (section, actions) in config {
+            let bindings = if section == GLOBAL_SECTION {
+                &mut keymap.global
+            } else if let Some(block) = ActiveBlock::ALL
+                .into_iter()
+                .find(|block| block.config_name() == section)
+            {
+                keymap.blocks.entry(block).or_default()
+            } else {
+                errors.push(format!("[{section}]: unknown section"));
+                continue;
+            };
+            let mut overrides = Vec::new();
+            for (name, keys) in actions {
+                let Some(action) = Action::from_name(name) else {
+                    errors.push(format!("[{section}]: unknown action `{name}`"));
+                    continue;
+                };
+                bindings.retain(|_, bound| *bound != action);
+                for keys in keys.sequences() {
+                    match parse_sequence(keys) {
+                        Some(sequence) => overrides.push((sequence, action)),
+                        None => errors.push(format!("[{section}] {name}: invalid keys `{keys}`")),
+                    }
+                }
+            }
+            for (sequence, action) in overrides {
+                match bindings.insert(sequence.clone(), action) {
+                    Some(other) if other != action => errors.push(format!(
+                        "[{section}]: `{}` is bound to both `{}` and `{}`",
+                        display_sequence(&sequence),
+                        other.name(),
+                        action.name()
+                    )),
+                    _ => {}
+                }
+            }
+            errors.extend(prefix_conflicts(section, bindings));
+        }
+        if errors.is_empty() {
+            Ok(keymap)
+        } else {
+            Err(eyre!("Invalid keymap:\n  {}", errors.join("\n  ")))
+        }
+    }
+
+    /// Looks up `keys` in `block`, falling back to the global bindings when
+    /// the block has nothing bound to them or starting with them.
+    pub fn resolve(&self, block: ActiveBlock, keys: &[KeyChord]) -> Resolved {
+        for bindings in self.blocks.get(&block).into_iter().chain([&self.global]) {
+            if let Some(&action) = bindings.get(keys) {
+                return Resolved::Action(action);
+            }
+            if bindings
+                .keys()
+                .any(|sequence| sequence.len() > keys.len() && sequence.starts_with(keys))
+            {
+                return Resolved::Pending;
+            }
+        }
+        Resolved::Unbound
+    }
+}
+
+impl Keymap {
+    /// The bindings available in `block`: its own first, then the global
+    /// ones it doesn't shadow.
+    pub fn bindings(&self, block: ActiveBlock) -> Vec<Binding> {
+        let local = self.blocks.get(&block);
+        let layers = [(Some(block), local), (None, Some(&self.global))];
+        let mut rows = Vec::new();
+        for (scope, bindings) in layers {
+            let Some(bindings) = bindings else { continue };
+            for action in Action::ALL {
+                let mut keys: Vec<String> = bindings
+                    .iter()
+                    .filter(|&(sequence, &bound)| {
+                        bound == action
+                            && (scope.is_some() || local.is_none_or(|l| !l.contains_key(sequence)))
+                    })
+                    .map(|(sequence, _)| display_sequence(sequence))
+                    .collect();
+                if keys.is_empty() {
+                    continue;
+                }
+                keys.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+                rows.push(Binding {
+                    action,
+                    keys,
+                    block: scope,
+                });
+            }
+        }
+        rows
+    }
+}
+
+/// Sequences that can never fire because a shorter one in the same section
+/// matches first.
+fn prefix_conflicts(section: &str, bindings: &Bindings) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (short, short_action) in bindings {
+        for (long, long_action) in bindings {
+            if long.len() > short.len() && long.starts_with(short) {
+                errors.push(format!(
+                    "[{section}]: `{}` ({}) hides `{}` ({})",
+                    display_sequence(short),
+                    short_action.name(),
+                    display_sequence(long),
+                    long_action.name()
+                ));
+            }
+        }
+    }
+    errors.sort();
+    errors
+}
+
+fn parse_sequence(keys: &str) -> Option<Vec<KeyChord>> {
+    let sequence = keys
+        .split_whitespace()
+        .map(KeyChord::parse)
+        .collect::<Option<Vec<_>>>()?;
+    (!sequence.is_empty()).then_some(sequence)
+}
+
+pub fn display_sequence(sequence: &[KeyChord]) -> String {
+    let chords: Vec<String> = sequence.iter().map(KeyChord::to_string).collect();
+    chords.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(text: &str) -> KeyChord {
+        KeyChord::parse(text).unwrap()
+    }
+
+    fn keymap(config: &str) -> color_eyre::Result<Keymap> {
+        Keymap::from_config(&toml::from_str(config).unwrap())
+    }
+
+    fn resolve(keymap: &Keymap, block: ActiveBlock, keys: &str) -> Resolved {
+        keymap.resolve(block, &parse_sequence(keys).unwrap())
+    }
+
+    #[test]
+    fn parse_normalises_chords() {
+        assert_eq!(chord("shift-a"), chord("A"));
+        assert_eq!(chord("ctrl-C"), chord("ctrl-c"));
+        assert_eq!(chord("shift-tab"), chord("backtab"));
+        assert_eq!(
+            chord("shift-tab"),
+            KeyChord::from(KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT))
+        );
+        assert_eq!(
+            chord("A"),
+            KeyChord::from(KeyEvent::new(KeyCode::Char('A'), KeyModifiers::SHIFT))
+        );
+        assert_eq!(KeyChord::parse("ctrl-"), None);
+        assert_eq!(
+            KeyChord::parse("f"),
+            Some(KeyChord::new(KeyCode::Char('f'), KeyModifiers::NONE))
+        );
+        assert_eq!(KeyChord::parse("fx"), None);
+    }
+
+    #[test]
+    fn duplicate_binding() {
+        let error = keymap("[global]\nquit = \"x\"\nhelp = \"x\"").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid keymap:\n  [global]: `x` is bound to both `help` and `quit`"
+        );
+    }
+
+    #[test]
+    fn prefix_conflict() {
+        let error = keymap("[global]\nbottom = \"g\"").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid keymap:\n  [global]: `g` (bottom) hides `g g` (top)"
+        );
+    }
+
+    #[test]
+    fn partial_sequence_is_pending() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            resolve(&keymap, ActiveBlock::Playlist, "g"),
+            Resolved::Pending
+        );
+        assert_eq!(
+            resolve(&keymap, ActiveBlock::Playlist, "g g"),
+            Resolved::Action(Action::Top)
+        );
+        assert_eq!(
+            resolve(&keymap, ActiveBlock::Playlist, "g q"),
+            Resolved::Unbound
+        );
+    }
+
+    #[test]
+    fn block_bindings_shadow_global_ones() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            resolve(&keymap, ActiveBlock::Help, "q"),
+            Resolved::Action(Action::Help)
+        );
+        assert_eq!(
+            resolve(&keymap, ActiveBlock::Playlist, "q"),
+            Resolved::Action(Action::Quit)
+        );
+    }
+
+    #[test]
+    fn block_bindings_shadow_global_prefixes() {
+        let keymap = keymap(
+            "[global]\ntop = \"t\"\nbottom = \"g\"\n\
+             [playlist]\nforward = \"g x\"\n\
+             [directory]\nselect = \"t\"",
+        )
+        .unwrap();
+        assert_eq!(
+            resolve(&keymap, ActiveBlock::Playlist, "g"),
+            Resolved::Pending
+        );
+        assert_eq!(
+            resolve(&keymap, ActiveBlock::Playlist, "g x"),
+            Resolved::Action(Action::Forward)
+        );
+        assert_eq!(
+            resolve(&keymap, ActiveBlock::UserPlaylists, "g"),
+            Resolved::Action(Action::Bottom)
+        );
+        assert_eq!(
+            resolve(&keymap, ActiveBlock::Directory, "t"),
+            Resolved::Action(Action::Select)
+        );
+    }
+}
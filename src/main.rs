@@ -1,10 +1,36 @@
-use crate::app::App;
+use crate::{app::App, auth::SignIn, cli::Cli, config::Config};
+use clap::Parser;
 use log::LevelFilter;
-use tui_logger;
+use ratatui::crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+};
 
+mod api;
 mod app;
+mod artwork;
+mod auth;
+mod cache;
+mod cli;
+mod command;
+mod config;
 mod event;
+mod export;
+mod ipc;
+mod keymap;
+mod mouse;
+mod mpris;
+mod navigation;
+mod notifications;
+mod panes;
+mod playlist_ops;
+mod session;
+mod smart_playlist;
+mod status;
+mod theme;
+mod track_actions;
 mod ui;
+mod web_remote;
 mod widgets;
 
 #[tokio::main]
@@ -12,10 +38,40 @@ async fn main() -> color_eyre::Result<()> {
     tui_logger::init_logger(LevelFilter::Debug).unwrap();
     tui_logger::set_default_level(LevelFilter::Debug);
     color_eyre::install()?;
-    let terminal = ratatui::init();
+    let cli = Cli::parse();
+    if let Some(profile) = &cli.profile {
+        Config::set_profile(profile)?;
+    }
+    let sign_in = SignIn::new(cli.headless);
+    if let Some(command) = cli.command {
+        return cli::run(command, cli.json, sign_in).await;
+    }
     log::info!("Starting application");
-    let result = App::new().await.run(terminal).await;
+    // Signing in may print to and read from the terminal, so it happens
+    // before the interface takes the terminal over.
+    let app = App::new(cli.offline, sign_in).await;
+    let terminal = ratatui::init();
+    // ratatui's panic hook restores the terminal but leaves the mouse captured.
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        hook(info);
+    }));
+    if let Err(e) = execute!(std::io::stdout(), EnableMouseCapture) {
+        restore_terminal();
+        return Err(e.into());
+    }
+    let result = app.run(terminal).await;
     log::info!("Exiting application");
-    ratatui::restore();
+    restore_terminal();
     result
 }
+
+/// Releases the mouse and hands the terminal back to the shell. Failing to
+/// release the mouse doesn't stop the rest from being restored.
+fn restore_terminal() {
+    if let Err(e) = execute!(std::io::stdout(), DisableMouseCapture) {
+        log::error!("Failed to disable mouse capture: {e}");
+    }
+    ratatui::restore();
+}
@@ -0,0 +1,203 @@
+use crate::{
+    app::{ActiveBlock, App, SelectedTab},
+    event::AppEvent,
+    ipc::Request,
+    ui::{self, TAB_TITLES},
+    widgets::list::scrolled_offset,
+};
+use ratatui::{
+    crossterm::event::{MouseButton, MouseEvent, MouseEventKind},
+    layout::{Margin, Position, Rect},
+    widgets::ListState,
+};
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthStr;
+
+/// Two clicks on the same cell within this interval make a double-click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+impl App {
+    pub fn handle_mouse_event(&mut self, event: MouseEvent) {
+        let position = Position::new(event.column, event.row);
+        match event.kind {
+            MouseEventKind::ScrollUp => self.scroll(position, false),
+            MouseEventKind::ScrollDown => self.scroll(position, true),
+            MouseEventKind::Down(MouseButton::Left) => {
+                let double = self
+                    .last_click
+                    .is_some_and(|(at, last)| last == position && at.elapsed() <= DOUBLE_CLICK);
+                // A double-click consumes both clicks, so a third starts over.
+                self.last_click = (!double).then(|| (Instant::now(), position));
+                self.click(position, double);
+            }
+            _ => {}
+        }
+    }
+
+    fn scroll(&mut self, position: Position, down: bool) {
+        let areas = self.areas(self.area);
+        if matches!(self.selected_tab, SelectedTab::Main) && self.open_popup().is_none() {
+            if areas.directory.contains(position) {
+                self.route.active_block = ActiveBlock::Directory;
+            } else if areas.main.contains(position)
+                && self.route.active_block == ActiveBlock::Directory
+            {
+                self.route.active_block = self.route.hovered_block;
+            }
+        }
+        if down { self.down() } else { self.up() }
+    }
+
+    fn click(&mut self, position: Position, double: bool) {
+        let bar = self.areas(self.area).now_playing;
+        if bar.contains(position) {
+            let duration_ms = self
+                .playback
+                .as_ref()
+                .map_or(0, |status| status.duration_ms);
+            if let Some(position_ms) = seek_position(bar, position.x, duration_ms) {
+                self.events
+                    .send(AppEvent::Remote(Request::Seek { position_ms }));
+            }
+            return;
+        }
+        // Popups are short menus, so a single click picks an option.
+        let popup = self.open_popup().is_some();
+        if self.select_clicked_row(position) && (double || popup) {
+            self.events.send(AppEvent::Select);
+        }
+    }
+
+    /// Focuses whatever is at `position` and moves its cursor to the clicked
+    /// row, returning whether a row was clicked.
+    fn select_clicked_row(&mut self, position: Position) -> bool {
+        let areas = self.areas(self.area);
+        if matches!(
+            self.route.active_block,
+            ActiveBlock::Help | ActiveBlock::Palette
+        ) {
+            return false;
+        }
+        if areas.tabs.contains(position) {
+            match tab_at(areas.tabs, position.x) {
+                Some(0) => {
+                    self.selected_tab = SelectedTab::Main;
+                    self.route.active_block = self.route.hovered_block;
+                }
+                Some(1) => {
+                    self.selected_tab = SelectedTab::Logger;
+                    self.route.active_block = ActiveBlock::Logger;
+                }
+                _ => {}
+            }
+            return false;
+        }
+        if !matches!(self.selected_tab, SelectedTab::Main) {
+            return false;
+        }
+        if let Some(popup) = self.open_popup() {
+            let area = ui::popup_area(popup, areas.main);
+            if !area.contains(position) {
+                self.route.active_block = self.route.hovered_block;
+                return false;
+            }
+            let len = popup.list.len();
+            let state = &popup.list_state;
+            let row = row_at(area, 0, position.y, len, state.selected(), state.offset());
+            let state = match self.route.active_block {
+                ActiveBlock::Popup => &mut self.track_popup.list_state,
+                ActiveBlock::PlaylistPopup => &mut self.playlist_popup.list_state,
+                _ => &mut self.playlist_picker.list_state,
+            };
+            select_row(row, state)
+        } else if areas.directory.contains(position) {
+            self.route.active_block = ActiveBlock::Directory;
+            let directory = &mut self.directory;
+            let row = row_at(
+                areas.directory,
+                0,
+                position.y,
+                directory.list.len(),
+                directory.list_state.selected(),
+                directory.list_state.offset(),
+            );
+            select_row(row, &mut directory.list_state)
+        } else if areas.content.contains(position) {
+            self.route.active_block = self.route.hovered_block;
+            let Some(list) = self.active_list() else {
+                return false;
+            };
+            let visible: Vec<usize> = (0..list.len)
+                .filter(|&i| list.filter.is_visible(i))
+                .collect();
+            let cursor = list
+                .list_state
+                .selected()
+                .and_then(|c| visible.iter().position(|&r| r == c));
+            // Tables have a header row above the rows.
+            let row = row_at(
+                areas.content,
+                1,
+                position.y,
+                visible.len(),
+                cursor,
+                list.list_state.offset(),
+            )
+            .map(|row| visible[row]);
+            select_row(row, list.list_state)
+        } else {
+            false
+        }
+    }
+}
+
+fn select_row(row: Option<usize>, state: &mut ListState) -> bool {
+    state.select(row.or(state.selected()));
+    row.is_some()
+}
+
+/// The tab whose title is at column `x` of the tab bar in `area`.
+fn tab_at(area: Rect, x: u16) -> Option<usize> {
+    // Titles are padded by a space on each side and separated by a one column divider.
+    let mut start = area.x + 1;
+    for (i, title) in TAB_TITLES.iter().enumerate() {
+        let end = start + title.width() as u16 + 2;
+        if (start..end).contains(&x) {
+            return Some(i);
+        }
+        start = end + 1;
+    }
+    None
+}
+
+/// Where to seek to in an item `duration_ms` long for a click at column `x`
+/// of the now-playing bar in `area`, `None` if nothing with a length plays.
+fn seek_position(area: Rect, x: u16, duration_ms: u32) -> Option<u32> {
+    let gauge = area.inner(Margin::new(1, 1));
+    if duration_ms == 0 || gauge.width == 0 {
+        return None;
+    }
+    let column = x.clamp(gauge.left(), gauge.right() - 1) - gauge.x;
+    let position = u64::from(duration_ms) * u64::from(column) / u64::from(gauge.width);
+    Some(position as u32)
+}
+
+/// Index of the row at `y` in a bordered list drawn in `area` with `header`
+/// rows above its `rows` rows, scrolled the way ratatui scrolls it to keep
+/// `cursor` in view.
+fn row_at(
+    area: Rect,
+    header: u16,
+    y: u16,
+    rows: usize,
+    cursor: Option<usize>,
+    offset: usize,
+) -> Option<usize> {
+    let top = area.y + 1 + header;
+    let height = usize::from(area.height.saturating_sub(2 + header));
+    if y < top || usize::from(y - top) >= height {
+        return None;
+    }
+    let row = scrolled_offset(offset, rows, cursor, height) + usize::from(y - top);
+    (row < rows).then_some(row)
+}
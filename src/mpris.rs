@@ -0,0 +1,562 @@
+use crate::{
+    event::{AppEvent, Event},
+    ipc::{Repeat, Request},
+    status::Status,
+};
+use std::{collections::HashMap, time::Instant};
+use tokio::sync::mpsc;
+use zbus::{
+    connection, fdo, interface,
+    object_server::SignalEmitter,
+    zvariant::{ObjectPath, OwnedValue, Value},
+};
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.spotui";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+/// The track ID MPRIS reserves for when nothing is loaded.
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+/// Positions further than this from where playback should be by the clock
+/// are announced as a seek.
+const SEEK_TOLERANCE_MS: i64 = 2000;
+
+/// The MPRIS service on the session bus, through which desktop media keys
+/// and widgets show and control what is playing. Commands are passed on as
+/// [`AppEvent::Remote`], like those sent to the control socket.
+#[derive(Debug, Clone)]
+pub struct Mpris {
+    connection: zbus::Connection,
+}
+
+impl Mpris {
+    /// Claims [`BUS_NAME`]. Without a session bus, or when another spotui
+    /// already has the name, the app runs on without one.
+    pub async fn start(sender: mpsc::UnboundedSender<Event>) -> Option<Self> {
+        let connected = match connection::Builder::session() {
+            Ok(bus) => Self::connect(bus, sender).await,
+            Err(e) => Err(e),
+        };
+        match connected {
+            Ok(mpris) => {
+                log::info!("Registered {BUS_NAME} on the session bus");
+                Some(mpris)
+            }
+            Err(e) => {
+                log::warn!("Not registering with MPRIS: {e}");
+                None
+            }
+        }
+    }
+
+    async fn connect(
+        bus: connection::Builder<'_>,
+        sender: mpsc::UnboundedSender<Event>,
+    ) -> zbus::Result<Self> {
+        let player = Player {
+            sender: sender.clone(),
+            status: Status::new(None),
+            polled_at: Instant::now(),
+        };
+        let connection = bus
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, Root { sender })?
+            .serve_at(OBJECT_PATH, player)?
+            .build()
+            .await?;
+        Ok(Self { connection })
+    }
+
+    /// Shows the polled `status`, announcing the properties that changed.
+    pub fn update(&self, status: Status) {
+        let connection = self.connection.clone();
+        tokio::spawn(async move {
+            if let Err(e) = update(&connection, status).await {
+                log::error!("Failed to update MPRIS: {e}");
+            }
+        });
+    }
+}
+
+async fn update(connection: &zbus::Connection, status: Status) -> zbus::Result<()> {
+    let interface = connection
+        .object_server()
+        .interface::<_, Player>(OBJECT_PATH)
+        .await?;
+    let emitter = interface.signal_emitter();
+    let mut player = interface.get_mut().await;
+    let expected_ms = player.position_ms();
+    let previous = std::mem::replace(&mut player.status, status);
+    player.polled_at = Instant::now();
+    let status = &player.status;
+    if previous.state() != status.state() {
+        player.playback_status_changed(emitter).await?;
+    }
+    if previous.uri != status.uri
+        || previous.title != status.title
+        || previous.duration_ms != status.duration_ms
+        || previous.art_url != status.art_url
+    {
+        player.metadata_changed(emitter).await?;
+    } else if (expected_ms - i64::from(status.progress_ms)).abs() > SEEK_TOLERANCE_MS {
+        Player::seeked(emitter, ms_to_us(status.progress_ms)).await?;
+    }
+    if previous.volume != status.volume {
+        player.volume_changed(emitter).await?;
+    }
+    if previous.shuffle != status.shuffle {
+        player.shuffle_changed(emitter).await?;
+    }
+    if previous.repeat != status.repeat {
+        player.loop_status_changed(emitter).await?;
+    }
+    Ok(())
+}
+
+fn ms_to_us(ms: u32) -> i64 {
+    i64::from(ms) * 1000
+}
+
+/// Passes `request` on to the app as if it came from the control socket.
+fn send(sender: &mpsc::UnboundedSender<Event>, request: Request) -> fdo::Result<()> {
+    sender
+        .send(Event::App(AppEvent::Remote(request)))
+        .map_err(|_| fdo::Error::Failed("spotui is quitting".to_string()))
+}
+
+/// `org.mpris.MediaPlayer2`, which describes the player itself.
+struct Root {
+    sender: mpsc::UnboundedSender<Event>,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    /// spotui runs in a terminal it can't bring to the front.
+    fn raise(&self) {}
+
+    fn quit(&self) {
+        let _ = self.sender.send(Event::App(AppEvent::Quit));
+    }
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "spotui"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<&str> {
+        vec!["spotify"]
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<&str> {
+        Vec::new()
+    }
+}
+
+/// `org.mpris.MediaPlayer2.Player`, showing the playback state as last polled.
+/// Changes made through it show once the player is polled again.
+struct Player {
+    sender: mpsc::UnboundedSender<Event>,
+    status: Status,
+    polled_at: Instant,
+}
+
+impl Player {
+    /// Where playback is now, going by the clock since the last poll.
+    fn position_ms(&self) -> i64 {
+        i64::from(self.status.position_ms(self.polled_at.elapsed()))
+    }
+
+    /// The object path MPRIS identifies the playing item by.
+    fn track_id(&self) -> ObjectPath<'static> {
+        let id = self
+            .status
+            .uri
+            .as_deref()
+            .and_then(|uri| uri.rsplit(':').next())
+            .filter(|id| id.chars().all(|c| c.is_ascii_alphanumeric()));
+        match id {
+            Some(id) => ObjectPath::try_from(format!("/org/mpris/MediaPlayer2/Track/{id}")),
+            None => ObjectPath::try_from(NO_TRACK.to_string()),
+        }
+        .expect("track paths are valid object paths")
+    }
+
+    fn send(&self, request: Request) -> fdo::Result<()> {
+        send(&self.sender, request)
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) -> fdo::Result<()> {
+        self.send(Request::Next)
+    }
+
+    fn previous(&self) -> fdo::Result<()> {
+        self.send(Request::Prev)
+    }
+
+    fn pause(&self) -> fdo::Result<()> {
+        self.send(Request::Pause)
+    }
+
+    fn play_pause(&self) -> fdo::Result<()> {
+        if self.status.playing {
+            self.send(Request::Pause)
+        } else {
+            self.send(Request::Play { uri: None })
+        }
+    }
+
+    /// Spotify can't stop without unloading the track, so this pauses.
+    fn stop(&self) -> fdo::Result<()> {
+        self.send(Request::Pause)
+    }
+
+    fn play(&self) -> fdo::Result<()> {
+        self.send(Request::Play { uri: None })
+    }
+
+    /// Moves `offset` microseconds forward, or back when negative.
+    fn seek(&self, offset: i64) -> fdo::Result<()> {
+        let position = self.position_ms() + offset / 1000;
+        if position >= i64::from(self.status.duration_ms) {
+            return self.send(Request::Next);
+        }
+        self.send(Request::Seek {
+            position_ms: position.max(0) as u32,
+        })
+    }
+
+    /// Moves to `position` microseconds, if `track_id` is still playing.
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) -> fdo::Result<()> {
+        if track_id != self.track_id() || position < 0 {
+            return Ok(());
+        }
+        let position_ms = position / 1000;
+        if position_ms > i64::from(self.status.duration_ms) {
+            return Ok(());
+        }
+        self.send(Request::Seek {
+            position_ms: position_ms as u32,
+        })
+    }
+
+    /// Plays a `spotify:` URI, such as a track, album or playlist.
+    fn open_uri(&self, uri: &str) -> fdo::Result<()> {
+        if !uri.starts_with("spotify:") {
+            return Err(fdo::Error::InvalidArgs(format!("Not a Spotify URI: {uri}")));
+        }
+        self.send(Request::Play {
+            uri: Some(uri.to_string()),
+        })
+    }
+
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> &str {
+        match self.status.state() {
+            "playing" => "Playing",
+            "paused" => "Paused",
+            _ => "Stopped",
+        }
+    }
+
+    #[zbus(property)]
+    fn loop_status(&self) -> &str {
+        match self.status.repeat {
+            "track" => "Track",
+            "context" => "Playlist",
+            _ => "None",
+        }
+    }
+
+    #[zbus(property)]
+    fn set_loop_status(&mut self, loop_status: String) -> zbus::Result<()> {
+        let mode = match loop_status.as_str() {
+            "None" => Repeat::Off,
+            "Track" => Repeat::Track,
+            "Playlist" => Repeat::Context,
+            _ => {
+                return Err(
+                    fdo::Error::InvalidArgs(format!("Unknown loop status {loop_status}")).into(),
+                );
+            }
+        };
+        Ok(self.send(Request::Repeat { mode })?)
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    /// Spotify only plays at normal speed.
+    #[zbus(property)]
+    fn set_rate(&mut self, _rate: f64) {}
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn shuffle(&self) -> bool {
+        self.status.shuffle
+    }
+
+    #[zbus(property)]
+    fn set_shuffle(&mut self, shuffle: bool) -> zbus::Result<()> {
+        Ok(self.send(Request::Shuffle { enabled: shuffle })?)
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let mut metadata = HashMap::new();
+        let mut insert = |key: &str, value: Value<'_>| {
+            if let Ok(value) = value.try_into_owned() {
+                metadata.insert(key.to_string(), value);
+            }
+        };
+        insert("mpris:trackid", self.track_id().into());
+        let Some(title) = &self.status.title else {
+            return metadata;
+        };
+        insert("xesam:title", title.as_str().into());
+        insert("xesam:artist", self.status.artists.clone().into());
+        insert("mpris:length", ms_to_us(self.status.duration_ms).into());
+        if let Some(album) = &self.status.album {
+            insert("xesam:album", album.as_str().into());
+        }
+        if let Some(art_url) = &self.status.art_url {
+            insert("mpris:artUrl", art_url.as_str().into());
+        }
+        if let Some(uri) = &self.status.uri {
+            let path = uri.trim_start_matches("spotify:").replace(':', "/");
+            insert(
+                "xesam:url",
+                format!("https://open.spotify.com/{path}").into(),
+            );
+        }
+        metadata
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.status
+            .volume
+            .map_or(0.0, |volume| f64::from(volume) / 100.0)
+    }
+
+    #[zbus(property)]
+    fn set_volume(&mut self, volume: f64) -> zbus::Result<()> {
+        let percent = (volume.clamp(0.0, 1.0) * 100.0).round() as u8;
+        Ok(self.send(Request::Volume { percent })?)
+    }
+
+    /// In microseconds. Changes all the time, so it isn't announced.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        self.position_ms() * 1000
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        true
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+    };
+    use zbus::proxy::CacheProperties;
+
+    /// A private session bus, stopped again when dropped.
+    struct Bus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl Bus {
+        /// `None` if `dbus-daemon` isn't installed.
+        fn start() -> Option<Self> {
+            let mut daemon = match Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+            {
+                Ok(daemon) => daemon,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+                Err(e) => panic!("failed to start dbus-daemon: {e}"),
+            };
+            let mut address = String::new();
+            let stdout = daemon.stdout.take().expect("stdout is piped");
+            BufReader::new(stdout).read_line(&mut address).unwrap();
+            Some(Self {
+                daemon,
+                address: address.trim().to_string(),
+            })
+        }
+
+        fn connect(&self) -> connection::Builder<'static> {
+            connection::Builder::address(self.address.as_str()).unwrap()
+        }
+    }
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    async fn player(client: &zbus::Connection) -> zbus::Proxy<'static> {
+        zbus::proxy::Builder::new(client)
+            .destination(BUS_NAME)
+            .unwrap()
+            .path(OBJECT_PATH)
+            .unwrap()
+            .interface("org.mpris.MediaPlayer2.Player")
+            .unwrap()
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+            .unwrap()
+    }
+
+    fn playing() -> Status {
+        Status {
+            playing: true,
+            title: Some("So What".to_string()),
+            artists: vec!["Miles Davis".to_string()],
+            album: Some("Kind of Blue".to_string()),
+            uri: Some("spotify:track:4vLYewWIvqHfKtJDk8c8tq".to_string()),
+            art_url: Some("https://i.scdn.co/image/cover".to_string()),
+            progress_ms: 83_000,
+            duration_ms: 562_000,
+            device: Some("Kitchen".to_string()),
+            volume: Some(40),
+            shuffle: false,
+            repeat: "off",
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_the_player_on_the_session_bus() {
+        let Some(bus) = Bus::start() else {
+            eprintln!("dbus-daemon isn't installed, skipping");
+            return;
+        };
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let mpris = Mpris::connect(bus.connect(), sender).await.unwrap();
+        let client = bus.connect().build().await.unwrap();
+        let player = player(&client).await;
+
+        let status: String = player.get_property("PlaybackStatus").await.unwrap();
+        assert_eq!(status, "Stopped");
+        let metadata: HashMap<String, OwnedValue> = player.get_property("Metadata").await.unwrap();
+        assert_eq!(
+            ObjectPath::try_from(metadata["mpris:trackid"].clone()).unwrap(),
+            ObjectPath::try_from(NO_TRACK).unwrap()
+        );
+        assert!(!metadata.contains_key("xesam:title"));
+
+        update(&mpris.connection, playing()).await.unwrap();
+        let status: String = player.get_property("PlaybackStatus").await.unwrap();
+        assert_eq!(status, "Playing");
+        let metadata: HashMap<String, OwnedValue> = player.get_property("Metadata").await.unwrap();
+        let text = |key: &str| String::try_from(metadata[key].clone()).unwrap();
+        assert_eq!(text("xesam:title"), "So What");
+        assert_eq!(text("xesam:album"), "Kind of Blue");
+        assert_eq!(text("mpris:artUrl"), "https://i.scdn.co/image/cover");
+        assert_eq!(
+            text("xesam:url"),
+            "https://open.spotify.com/track/4vLYewWIvqHfKtJDk8c8tq"
+        );
+        assert_eq!(
+            Vec::<String>::try_from(metadata["xesam:artist"].clone()).unwrap(),
+            ["Miles Davis"]
+        );
+        assert_eq!(
+            i64::try_from(metadata["mpris:length"].clone()).unwrap(),
+            562_000_000
+        );
+
+        let rejected: zbus::Result<()> = player
+            .call(
+                "OpenUri",
+                &("https://open.spotify.com/track/4vLYewWIvqHfKtJDk8c8tq",),
+            )
+            .await;
+        assert!(matches!(
+            rejected.map_err(fdo::Error::from),
+            Err(fdo::Error::InvalidArgs(_))
+        ));
+        assert!(receiver.try_recv().is_err());
+
+        let uri = "spotify:album:1weenld61qoidwYuZ1GESA";
+        let () = player.call("OpenUri", &(uri,)).await.unwrap();
+        match receiver.try_recv() {
+            Ok(Event::App(AppEvent::Remote(request))) => assert_eq!(
+                request,
+                Request::Play {
+                    uri: Some(uri.to_string())
+                }
+            ),
+            other => panic!("expected a play request, got {other:?}"),
+        }
+    }
+}
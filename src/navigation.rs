@@ -0,0 +1,134 @@
+use crate::app::ActiveBlock;
+use serde::{Deserialize, Serialize};
+
+/// Most views kept in either direction of the history.
+const MAX_HISTORY: usize = 50;
+
+/// A view of the main content area that can be navigated to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum View {
+    UserPlaylists,
+    UserTopTracks,
+    UserTopArtists,
+    Playlist {
+        id: String,
+        /// Only shown in messages, so it may be left out when navigating by ID.
+        #[serde(default)]
+        name: String,
+    },
+}
+
+impl View {
+    pub fn block(&self) -> ActiveBlock {
+        match self {
+            View::UserPlaylists => ActiveBlock::UserPlaylists,
+            View::UserTopTracks => ActiveBlock::UserTopTracks,
+            View::UserTopArtists => ActiveBlock::UserTopArtists,
+            View::Playlist { .. } => ActiveBlock::Playlist,
+        }
+    }
+
+    /// Name of the view in the breadcrumb trail.
+    pub fn title(&self) -> &str {
+        match self {
+            View::UserPlaylists => "Playlists",
+            View::UserTopTracks => "Top Tracks",
+            View::UserTopArtists => "Top Artists",
+            View::Playlist { name, .. } => name,
+        }
+    }
+}
+
+/// A visited view with the cursor and scroll position it was left at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Visit {
+    pub view: View,
+    pub selected: Option<usize>,
+    pub offset: usize,
+}
+
+/// Views visited before and, after going back, after the current one.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    pub back: Vec<Visit>,
+    pub forward: Vec<Visit>,
+}
+
+impl History {
+    /// Records leaving `current` for a new view, which drops the forward history.
+    pub fn push(&mut self, current: Visit) {
+        self.back.push(current);
+        if self.back.len() > MAX_HISTORY {
+            self.back.remove(0);
+        }
+        self.forward.clear();
+    }
+
+    /// The view to go back to, recording `current` to come forward to again.
+    pub fn back(&mut self, current: Option<Visit>) -> Option<Visit> {
+        let previous = self.back.pop()?;
+        self.forward.extend(current);
+        Some(previous)
+    }
+
+    /// The view to go forward to, recording `current` to come back to again.
+    pub fn forward(&mut self, current: Option<Visit>) -> Option<Visit> {
+        let next = self.forward.pop()?;
+        self.back.extend(current);
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visit(view: View) -> Visit {
+        Visit {
+            view,
+            selected: None,
+            offset: 0,
+        }
+    }
+
+    #[test]
+    fn back_and_forward_retrace_the_visits() {
+        let mut history = History::default();
+        history.push(visit(View::UserPlaylists));
+        history.push(visit(View::UserTopTracks));
+        let current = visit(View::UserTopArtists);
+        let previous = history.back(Some(current.clone()));
+        assert_eq!(previous, Some(visit(View::UserTopTracks)));
+        // Going forward again returns to the view gone back from.
+        assert_eq!(history.forward(previous.clone()), Some(current));
+        assert_eq!(history.forward(None), None);
+        assert_eq!(history.back(None), previous);
+        assert_eq!(history.back(None), Some(visit(View::UserPlaylists)));
+        assert_eq!(history.back(None), None);
+    }
+
+    #[test]
+    fn visiting_a_new_view_drops_the_forward_history() {
+        let mut history = History::default();
+        history.push(visit(View::UserPlaylists));
+        let previous = history.back(Some(visit(View::UserTopTracks)));
+        assert_eq!(history.forward.len(), 1);
+        history.push(previous.unwrap());
+        assert!(history.forward.is_empty());
+        assert_eq!(history.forward(None), None);
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let mut history = History::default();
+        for i in 0..=MAX_HISTORY {
+            history.push(Visit {
+                selected: Some(i),
+                ..visit(View::UserPlaylists)
+            });
+        }
+        assert_eq!(history.back.len(), MAX_HISTORY);
+        assert_eq!(history.back[0].selected, Some(1));
+    }
+}
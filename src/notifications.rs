@@ -0,0 +1,160 @@
+use crate::{
+    artwork,
+    status::{Status, escape_markup},
+};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::OnceCell;
+use zbus::{proxy, zvariant::Value};
+
+/// The `[notifications]` config.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    pub enabled: bool,
+    /// Least number of seconds between two notifications. Tracks skipped
+    /// through faster than this only get one, for the last of them.
+    pub min_interval_secs: u64,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_interval_secs: 5,
+        }
+    }
+}
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications",
+    gen_blocking = false
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
+
+/// Desktop notifications for track changes, sent through the notification
+/// daemon on the session bus.
+#[derive(Debug)]
+pub struct Notifier {
+    enabled: bool,
+    min_interval: Duration,
+    /// The newest track not notified about yet.
+    pending: Option<Status>,
+    last_sent: Option<Instant>,
+    /// Connected on the first notification.
+    connection: Arc<OnceCell<zbus::Connection>>,
+    /// ID of the last notification, which the next one replaces.
+    last_id: Arc<AtomicU32>,
+}
+
+impl Notifier {
+    pub fn from_config(config: &NotificationsConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            min_interval: Duration::from_secs(config.min_interval_secs),
+            pending: None,
+            last_sent: None,
+            connection: Arc::new(OnceCell::new()),
+            last_id: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    /// Queues a notification for the track `status` started playing.
+    pub fn track_changed(&mut self, status: &Status) {
+        if self.enabled && status.title.is_some() {
+            self.pending = Some(status.clone());
+        }
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.pending.is_some()
+            && self
+                .last_sent
+                .is_none_or(|last| last.elapsed() >= self.min_interval)
+    }
+
+    /// Shows the pending notification, if any.
+    pub fn send_pending(&mut self) {
+        let Some(status) = self.pending.take() else {
+            return;
+        };
+        self.last_sent = Some(Instant::now());
+        let connection = self.connection.clone();
+        let last_id = self.last_id.clone();
+        tokio::spawn(async move {
+            if let Err(e) = notify(&connection, &last_id, &status).await {
+                log::warn!("Failed to show a notification: {e}");
+            }
+        });
+    }
+}
+
+async fn notify(
+    connection: &OnceCell<zbus::Connection>,
+    last_id: &AtomicU32,
+    status: &Status,
+) -> zbus::Result<()> {
+    let connection = connection
+        .get_or_try_init(zbus::Connection::session)
+        .await?;
+    let proxy = NotificationsProxy::new(connection).await?;
+    let summary = status.title.clone().unwrap_or_default();
+    let body: Vec<String> = [Some(status.artists.join(", ")), status.album.clone()]
+        .into_iter()
+        .flatten()
+        .filter(|line| !line.is_empty())
+        .map(|line| escape_markup(&line))
+        .collect();
+    // The cover from the artwork cache, fetched now if it isn't there yet.
+    let image = match &status.art_url {
+        Some(url) => match artwork::cached(url).await {
+            Ok(path) => Some(format!("file://{}", path.display())),
+            Err(e) => {
+                log::debug!("No cover for the notification: {e}");
+                None
+            }
+        },
+        None => None,
+    };
+    let mut hints = HashMap::new();
+    hints.insert("category", Value::from("x-gnome.music"));
+    if let Some(image) = &image {
+        hints.insert("image-path", Value::from(image.as_str()));
+    }
+    let id = proxy
+        .notify(
+            "spotui",
+            last_id.load(Ordering::Relaxed),
+            image.as_deref().unwrap_or_default(),
+            &summary,
+            &body.join("\n"),
+            &[],
+            hints,
+            -1,
+        )
+        .await?;
+    last_id.store(id, Ordering::Relaxed);
+    Ok(())
+}
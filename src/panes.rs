@@ -0,0 +1,128 @@
+use crate::config::Config;
+use color_eyre::eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+
+/// File in the config directory the pane sizes are saved to.
+const PANES_FILE: &str = "layout.toml";
+
+/// Smallest terminal the layout is drawn in, in columns and rows.
+pub const MIN_SIZE: (u16, u16) = (40, 10);
+/// Shortest terminal the now-playing bar is shown in, in rows. Below this
+/// the rows go to the lists instead.
+pub const NOW_PLAYING_MIN_HEIGHT: u16 = 16;
+/// Narrowest the sidebar gets, in columns.
+const MIN_SIDEBAR: u16 = 14;
+/// Narrowest the content pane gets before the sidebar collapses.
+const MIN_CONTENT: u16 = 30;
+/// Bounds and step of the sidebar width, in percent of the screen.
+const SIDEBAR_PERCENT: (u16, u16) = (10, 50);
+const SIDEBAR_STEP: u16 = 5;
+
+/// The `[layout]` config.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct LayoutConfig {
+    /// Width of the Directory sidebar, in percent. Used until it is resized.
+    pub sidebar_width: u16,
+    /// Terminals narrower than this many columns use the compact layout.
+    pub compact_width: u16,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            sidebar_width: 20,
+            compact_width: 80,
+        }
+    }
+}
+
+/// Pane sizes as adjusted from the keyboard, kept between runs.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct PaneSizes {
+    sidebar_width: u16,
+    sidebar_hidden: bool,
+}
+
+#[derive(Debug)]
+pub struct PaneLayout {
+    sizes: PaneSizes,
+    compact_width: u16,
+}
+
+impl PaneLayout {
+    /// The sizes saved by the last run, or those from `config` if there are none.
+    pub fn load(config: &LayoutConfig) -> Self {
+        let saved = read_sizes().unwrap_or_else(|e| {
+            log::error!("Failed to load pane sizes, using the config: {e:#}");
+            None
+        });
+        let mut sizes = saved.unwrap_or(PaneSizes {
+            sidebar_width: config.sidebar_width,
+            sidebar_hidden: false,
+        });
+        sizes.sidebar_width = sizes
+            .sidebar_width
+            .clamp(SIDEBAR_PERCENT.0, SIDEBAR_PERCENT.1);
+        Self {
+            sizes,
+            compact_width: config.compact_width,
+        }
+    }
+
+    pub fn save(&self) -> color_eyre::Result<()> {
+        let dir = Config::dir()?;
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(PANES_FILE);
+        let contents = toml::to_string(&self.sizes)?;
+        std::fs::write(&path, contents)
+            .wrap_err_with(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Whether a terminal `width` columns wide is drawn without margins and
+    /// with the sidebar only shown while focused.
+    pub fn is_compact(&self, width: u16) -> bool {
+        width < self.compact_width
+    }
+
+    pub fn sidebar_hidden(&self) -> bool {
+        self.sizes.sidebar_hidden
+    }
+
+    pub fn toggle_sidebar(&mut self) {
+        self.sizes.sidebar_hidden = !self.sizes.sidebar_hidden;
+    }
+
+    /// Widens or narrows the sidebar by a step, returning whether it changed.
+    pub fn resize_sidebar(&mut self, grow: bool) -> bool {
+        let width = if grow {
+            self.sizes.sidebar_width + SIDEBAR_STEP
+        } else {
+            self.sizes.sidebar_width.saturating_sub(SIDEBAR_STEP)
+        }
+        .clamp(SIDEBAR_PERCENT.0, SIDEBAR_PERCENT.1);
+        let changed = width != self.sizes.sidebar_width;
+        self.sizes.sidebar_width = width;
+        changed
+    }
+
+    /// Columns of `width` given to the sidebar. A hidden sidebar, or one that
+    /// would leave too little room for the content, takes none unless `focused`.
+    pub fn sidebar_width(&self, width: u16, focused: bool) -> u16 {
+        let wanted = (u32::from(width) * u32::from(self.sizes.sidebar_width) / 100) as u16;
+        let wanted = wanted.max(MIN_SIDEBAR).min(width);
+        let collapsed =
+            self.sizes.sidebar_hidden || self.is_compact(width) || wanted + MIN_CONTENT > width;
+        if collapsed && !focused { 0 } else { wanted }
+    }
+}
+
+fn read_sizes() -> color_eyre::Result<Option<PaneSizes>> {
+    let path = Config::dir()?.join(PANES_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = std::fs::read_to_string(&path)
+        .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&contents).wrap_err_with(|| format!("Invalid pane sizes in {}", path.display()))
+}
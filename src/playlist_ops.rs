@@ -0,0 +1,210 @@
+use crate::{
+    api,
+    auth::Spotify,
+    event::{ProfileSender, ProgressReporter},
+};
+use color_eyre::eyre::bail;
+use rand::seq::SliceRandom;
+use spotify_rs::model::{playlist::SimplifiedPlaylist, track::Track};
+use std::collections::{BTreeMap, HashSet};
+
+/// Split groups with fewer tracks than this are collected into a single "Other" playlist.
+const MIN_SPLIT_GROUP: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaylistOperation {
+    Merge,
+    SplitByArtist,
+    SplitByDecade,
+    SplitByGenre,
+    ShuffledCopy,
+}
+
+impl PlaylistOperation {
+    pub const ALL: [PlaylistOperation; 5] = [
+        PlaylistOperation::Merge,
+        PlaylistOperation::SplitByArtist,
+        PlaylistOperation::SplitByDecade,
+        PlaylistOperation::SplitByGenre,
+        PlaylistOperation::ShuffledCopy,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PlaylistOperation::Merge => "Merge Selected",
+            PlaylistOperation::SplitByArtist => "Split by Artist",
+            PlaylistOperation::SplitByDecade => "Split by Decade",
+            PlaylistOperation::SplitByGenre => "Split by Genre",
+            PlaylistOperation::ShuffledCopy => "Shuffled Copy",
+        }
+    }
+}
+
+/// Runs `op` over `sources`, reporting progress and finally an
+/// [`crate::event::AppEvent::ProgressDone`] with a summary through `sender`.
+pub async fn run(
+    op: PlaylistOperation,
+    spotify: &Spotify,
+    user_id: &str,
+    sources: Vec<SimplifiedPlaylist>,
+    sender: ProfileSender,
+) {
+    let reporter = ProgressReporter::new(sender, op.label());
+    let result = match op {
+        PlaylistOperation::Merge => merge(spotify, user_id, &sources, &reporter).await,
+        PlaylistOperation::ShuffledCopy => {
+            shuffled_copy(spotify, user_id, &sources, &reporter).await
+        }
+        _ => split(op, spotify, user_id, &sources, &reporter).await,
+    };
+    let summary = match result {
+        Ok(summary) => {
+            log::info!("{}: {summary}", op.label());
+            summary
+        }
+        Err(e) => {
+            log::error!("{} failed: {e}", op.label());
+            format!("{} failed: {e}", op.label())
+        }
+    };
+    reporter.done(summary);
+}
+
+async fn merge(
+    spotify: &Spotify,
+    user_id: &str,
+    sources: &[SimplifiedPlaylist],
+    reporter: &ProgressReporter,
+) -> color_eyre::Result<String> {
+    if sources.len() < 2 {
+        bail!("Select at least two playlists to merge");
+    }
+    let mut seen = HashSet::new();
+    let mut uris = Vec::new();
+    for (i, source) in sources.iter().enumerate() {
+        reporter.report(&format!("reading {}", source.name), i, sources.len());
+        for (track, _) in api::playlist_tracks(spotify, &source.id).await? {
+            if seen.insert(track.uri.clone()) {
+                uris.push(track.uri);
+            }
+        }
+    }
+    let name = sources
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect::<Vec<_>>()
+        .join(" + ");
+    create_with_tracks(spotify, user_id, &name, &uris, reporter).await?;
+    Ok(format!("Created \"{name}\" with {} tracks", uris.len()))
+}
+
+async fn shuffled_copy(
+    spotify: &Spotify,
+    user_id: &str,
+    sources: &[SimplifiedPlaylist],
+    reporter: &ProgressReporter,
+) -> color_eyre::Result<String> {
+    let mut created = Vec::new();
+    for source in sources {
+        let mut uris: Vec<String> = api::playlist_tracks(spotify, &source.id)
+            .await?
+            .into_iter()
+            .map(|(track, _)| track.uri)
+            .collect();
+        uris.shuffle(&mut rand::thread_rng());
+        let name = format!("{} (shuffled)", source.name);
+        create_with_tracks(spotify, user_id, &name, &uris, reporter).await?;
+        created.push(name);
+    }
+    Ok(format!("Created {}", created.join(", ")))
+}
+
+async fn split(
+    op: PlaylistOperation,
+    spotify: &Spotify,
+    user_id: &str,
+    sources: &[SimplifiedPlaylist],
+    reporter: &ProgressReporter,
+) -> color_eyre::Result<String> {
+    let mut created = 0;
+    for source in sources {
+        let tracks: Vec<Track> = api::playlist_tracks(spotify, &source.id)
+            .await?
+            .into_iter()
+            .map(|(track, _)| track)
+            .collect();
+        let genres = if op == PlaylistOperation::SplitByGenre {
+            let artist_ids: Vec<String> = tracks
+                .iter()
+                .filter_map(|t| t.artists.first().map(|a| a.id.clone()))
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect();
+            api::artist_genres(spotify, &artist_ids).await?
+        } else {
+            Default::default()
+        };
+
+        let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for track in &tracks {
+            let key = match op {
+                PlaylistOperation::SplitByArtist => track.artists.first().map(|a| a.name.clone()),
+                PlaylistOperation::SplitByDecade => track
+                    .album
+                    .release_date
+                    .get(..4)
+                    .and_then(|y| y.parse::<u32>().ok())
+                    .map(|y| format!("{}s", y / 10 * 10)),
+                _ => track
+                    .artists
+                    .first()
+                    .and_then(|a| genres.get(&a.id))
+                    .and_then(|g| g.first().cloned()),
+            };
+            groups
+                .entry(key.unwrap_or_else(|| "Unknown".to_string()))
+                .or_default()
+                .push(track.uri.clone());
+        }
+        let mut other = Vec::new();
+        groups.retain(|_, uris| {
+            if uris.len() < MIN_SPLIT_GROUP {
+                other.append(uris);
+                return false;
+            }
+            true
+        });
+        if !other.is_empty() {
+            groups
+                .entry("Other".to_string())
+                .or_default()
+                .append(&mut other);
+        }
+
+        for (group, uris) in groups {
+            let name = format!("{} - {group}", source.name);
+            create_with_tracks(spotify, user_id, &name, &uris, reporter).await?;
+            created += 1;
+        }
+    }
+    Ok(format!("Created {created} playlists"))
+}
+
+async fn create_with_tracks(
+    spotify: &Spotify,
+    user_id: &str,
+    name: &str,
+    uris: &[String],
+    reporter: &ProgressReporter,
+) -> color_eyre::Result<()> {
+    let playlist = spotify_rs::create_playlist(user_id, name)
+        .public(false)
+        .description("Created by spotui")
+        .send(spotify)
+        .await?;
+    reporter.report(name, 0, uris.len());
+    api::add_items_in_batches(spotify, &playlist.id, uris, |done| {
+        reporter.report(name, done, uris.len())
+    })
+    .await
+}
@@ -0,0 +1,41 @@
+use crate::{config::Config, navigation::Visit};
+use color_eyre::eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+
+/// File in the config directory the session is saved to.
+const SESSION_FILE: &str = "session.toml";
+
+/// Where the user was when spotui last quit, restored on the next launch.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// Whether the log tab was shown.
+    pub logger: bool,
+    /// Whether the Directory sidebar had focus rather than the view.
+    pub directory: bool,
+    /// The shown view.
+    pub view: Option<Visit>,
+    /// Cursors of the library lists, including the shown one.
+    pub lists: Vec<Visit>,
+}
+
+impl Session {
+    /// The saved session, `None` on the first run.
+    pub fn load() -> color_eyre::Result<Option<Self>> {
+        let path = Config::dir()?.join(SESSION_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&contents).wrap_err_with(|| format!("Invalid session in {}", path.display()))
+    }
+
+    pub fn save(&self) -> color_eyre::Result<()> {
+        let dir = Config::dir()?;
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(SESSION_FILE);
+        std::fs::write(&path, toml::to_string(self)?)
+            .wrap_err_with(|| format!("Failed to write {}", path.display()))
+    }
+}
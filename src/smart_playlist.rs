@@ -0,0 +1,78 @@
+pub mod eval;
+pub mod rule;
+pub mod sync;
+
+use crate::config::{SmartPlaylistConfig, SmartPlaylistsConfig};
+use color_eyre::eyre::WrapErr;
+use rule::Rule;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone)]
+pub struct SmartPlaylist {
+    pub name: String,
+    pub description: Option<String>,
+    pub rule_text: String,
+    pub rule: Rule,
+}
+
+impl SmartPlaylist {
+    pub fn from_config(config: &SmartPlaylistConfig) -> color_eyre::Result<Self> {
+        let rule = Rule::parse(&config.rule)
+            .wrap_err_with(|| format!("Invalid rule for smart playlist \"{}\"", config.name))?;
+        Ok(Self {
+            name: config.name.clone(),
+            description: config.description.clone(),
+            rule_text: config.rule.clone(),
+            rule,
+        })
+    }
+}
+
+/// Smart playlist definitions plus the bookkeeping for periodic background syncs.
+#[derive(Debug)]
+pub struct SmartPlaylists {
+    pub playlists: Arc<Vec<SmartPlaylist>>,
+    pub interval: Option<Duration>,
+    pub last_sync: Option<Instant>,
+    pub syncing: Arc<AtomicBool>,
+}
+
+impl SmartPlaylists {
+    pub fn from_config(config: &SmartPlaylistsConfig) -> Self {
+        let playlists = config
+            .playlist
+            .iter()
+            .filter_map(|p| match SmartPlaylist::from_config(p) {
+                Ok(smart) => Some(smart),
+                Err(e) => {
+                    log::error!("{e:#}");
+                    None
+                }
+            })
+            .collect();
+        Self {
+            playlists: Arc::new(playlists),
+            interval: (config.sync_interval_secs > 0)
+                .then(|| Duration::from_secs(config.sync_interval_secs)),
+            last_sync: None,
+            syncing: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn is_due(&self) -> bool {
+        if self.playlists.is_empty() || self.syncing.load(Ordering::Relaxed) {
+            return false;
+        }
+        match (self.last_sync, self.interval) {
+            (None, _) => true,
+            (Some(last), Some(interval)) => last.elapsed() >= interval,
+            (Some(_), None) => false,
+        }
+    }
+}
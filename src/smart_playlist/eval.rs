@@ -0,0 +1,266 @@
+use crate::smart_playlist::rule::{Condition, Expr, Rule, SortKey};
+use chrono::{DateTime, Utc};
+use spotify_rs::model::track::Track;
+use std::collections::{HashMap, HashSet};
+
+/// A track the rule's source produced, with the date it was added to that source.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub track: Track,
+    pub added_at: Option<DateTime<Utc>>,
+}
+
+/// Library data the conditions are checked against.
+#[derive(Debug, Default)]
+pub struct LibraryData {
+    /// Genres keyed by artist ID.
+    pub genres: HashMap<String, Vec<String>>,
+    /// Track URIs keyed by lowercase playlist name.
+    pub playlists: HashMap<String, HashSet<String>>,
+}
+
+pub fn evaluate(
+    rule: &Rule,
+    candidates: Vec<Candidate>,
+    data: &LibraryData,
+    now: DateTime<Utc>,
+) -> Vec<Candidate> {
+    let mut seen = HashSet::new();
+    let mut matches: Vec<Candidate> = candidates
+        .into_iter()
+        .filter(|c| seen.insert(c.track.uri.clone()))
+        .filter(|c| {
+            rule.filter
+                .as_ref()
+                .is_none_or(|f| matches_expr(f, c, data, now))
+        })
+        .collect();
+
+    if let Some(sort) = rule.sort {
+        matches.sort_by(|a, b| {
+            let ordering = match sort.key {
+                SortKey::Popularity => a.track.popularity.cmp(&b.track.popularity),
+                SortKey::Added => a.added_at.cmp(&b.added_at),
+                SortKey::Title => a
+                    .track
+                    .name
+                    .to_lowercase()
+                    .cmp(&b.track.name.to_lowercase()),
+                SortKey::Artist => first_artist(&a.track).cmp(&first_artist(&b.track)),
+                SortKey::Duration => a.track.duration_ms.cmp(&b.track.duration_ms),
+                SortKey::Year => release_year(&a.track).cmp(&release_year(&b.track)),
+            };
+            if sort.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+    if let Some(limit) = rule.limit {
+        matches.truncate(limit);
+    }
+    matches
+}
+
+fn matches_expr(expr: &Expr, c: &Candidate, data: &LibraryData, now: DateTime<Utc>) -> bool {
+    match expr {
+        Expr::And(a, b) => matches_expr(a, c, data, now) && matches_expr(b, c, data, now),
+        Expr::Or(a, b) => matches_expr(a, c, data, now) || matches_expr(b, c, data, now),
+        Expr::Not(e) => !matches_expr(e, c, data, now),
+        Expr::Cond(cond) => matches_condition(cond, c, data, now),
+    }
+}
+
+fn matches_condition(
+    cond: &Condition,
+    c: &Candidate,
+    data: &LibraryData,
+    now: DateTime<Utc>,
+) -> bool {
+    match cond {
+        Condition::AddedWithin(d) => c.added_at.is_some_and(|at| now - at <= *d),
+        Condition::AddedBefore(d) => c.added_at.is_some_and(|at| now - at > *d),
+        Condition::Genre(genre) => {
+            let genre = genre.to_lowercase();
+            c.track.artists.iter().any(|a| {
+                data.genres
+                    .get(&a.id)
+                    .is_some_and(|gs| gs.iter().any(|g| g.to_lowercase().contains(&genre)))
+            })
+        }
+        Condition::Artist(name) => c
+            .track
+            .artists
+            .iter()
+            .any(|a| a.name.eq_ignore_ascii_case(name)),
+        Condition::InPlaylist(name) => data
+            .playlists
+            .get(&name.to_lowercase())
+            .is_some_and(|uris| uris.contains(&c.track.uri)),
+        Condition::Popularity(op, n) => op.test(c.track.popularity, *n),
+        Condition::Year(op, n) => release_year(&c.track).is_some_and(|y| op.test(y, *n)),
+        Condition::Explicit => c.track.explicit,
+    }
+}
+
+fn first_artist(track: &Track) -> String {
+    track
+        .artists
+        .first()
+        .map(|a| a.name.to_lowercase())
+        .unwrap_or_default()
+}
+
+fn release_year(track: &Track) -> Option<u32> {
+    track.album.release_date.get(..4)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn track(id: &str, artist: &str, popularity: u32, release_date: &str) -> Track {
+        let artist = serde_json::json!({
+            "external_urls": { "spotify": "" },
+            "href": "",
+            "id": artist.to_lowercase(),
+            "name": artist,
+            "type": "artist",
+            "uri": format!("spotify:artist:{}", artist.to_lowercase()),
+        });
+        serde_json::from_value(serde_json::json!({
+            "album": {
+                "album_type": "album",
+                "total_tracks": 1,
+                "external_urls": { "spotify": "" },
+                "href": "",
+                "id": "album",
+                "images": [],
+                "name": "Album",
+                "release_date": release_date,
+                "release_date_precision": "day",
+                "type": "album",
+                "uri": "spotify:album:album",
+                "artists": [artist],
+            },
+            "artists": [artist],
+            "disc_number": 1,
+            "duration_ms": 180_000,
+            "explicit": id.starts_with('x'),
+            "external_ids": {},
+            "external_urls": { "spotify": "" },
+            "href": "",
+            "id": id,
+            "name": id,
+            "popularity": popularity,
+            "track_number": 1,
+            "type": "track",
+            "uri": format!("spotify:track:{id}"),
+            "is_local": false,
+        }))
+        .unwrap()
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap()
+    }
+
+    fn candidate(track: Track, days_ago: i64) -> Candidate {
+        Candidate {
+            track,
+            added_at: Some(now() - chrono::Duration::days(days_ago)),
+        }
+    }
+
+    fn ids(rule: &str, candidates: Vec<Candidate>, data: &LibraryData) -> Vec<String> {
+        evaluate(&Rule::parse(rule).unwrap(), candidates, data, now())
+            .into_iter()
+            .map(|c| c.track.id)
+            .collect()
+    }
+
+    fn library() -> Vec<Candidate> {
+        vec![
+            candidate(track("a", "Coltrane", 40, "1965-01-01"), 3),
+            candidate(track("xb", "Slayer", 80, "1986-10-07"), 20),
+            candidate(track("c", "Coltrane", 60, "1959-08-17"), 60),
+            candidate(track("d", "Björk", 70, "1997-09-22"), 400),
+        ]
+    }
+
+    #[test]
+    fn filters_by_conditions() {
+        let data = LibraryData::default();
+        assert_eq!(
+            ids("liked where added within 30d", library(), &data),
+            ["a", "xb"]
+        );
+        assert_eq!(ids("liked where added before 1y", library(), &data), ["d"]);
+        assert_eq!(ids("liked where explicit", library(), &data), ["xb"]);
+        assert_eq!(
+            ids(
+                r#"liked where artist "coltrane" and year < 1960"#,
+                library(),
+                &data
+            ),
+            ["c"]
+        );
+        assert_eq!(
+            ids(
+                "liked where not (popularity >= 60 or explicit)",
+                library(),
+                &data
+            ),
+            ["a"]
+        );
+    }
+
+    #[test]
+    fn checks_genres_and_playlists() {
+        let data = LibraryData {
+            genres: HashMap::from([("björk".to_string(), vec!["Art Pop".to_string()])]),
+            playlists: HashMap::from([(
+                "old stuff".to_string(),
+                HashSet::from(["spotify:track:c".to_string()]),
+            )]),
+        };
+        assert_eq!(ids(r#"liked where genre "pop""#, library(), &data), ["d"]);
+        assert_eq!(
+            ids(
+                r#"liked where not in playlist "Old Stuff""#,
+                library(),
+                &data
+            ),
+            ["a", "xb", "d"]
+        );
+    }
+
+    #[test]
+    fn sorts_then_limits() {
+        let data = LibraryData::default();
+        assert_eq!(
+            ids("liked sort popularity desc limit 2", library(), &data),
+            ["xb", "d"]
+        );
+        assert_eq!(
+            ids("liked sort year", library(), &data),
+            ["c", "a", "xb", "d"]
+        );
+        assert_eq!(
+            ids("liked sort added", library(), &data),
+            ["d", "c", "xb", "a"]
+        );
+    }
+
+    #[test]
+    fn drops_duplicates() {
+        let mut candidates = library();
+        candidates.push(candidate(track("a", "Coltrane", 40, "1965-01-01"), 1));
+        assert_eq!(
+            ids("liked", candidates, &LibraryData::default()),
+            ["a", "xb", "c", "d"]
+        );
+    }
+}
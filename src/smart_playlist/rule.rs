@@ -0,0 +1,542 @@
+use color_eyre::eyre::{bail, eyre};
+use std::{iter::Peekable, str::Chars};
+
+/// A parsed smart playlist rule, e.g.
+/// `liked where added within 30d and genre "jazz" and not in playlist "Old" sort popularity desc limit 100`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub source: Source,
+    pub filter: Option<Expr>,
+    pub sort: Option<Sort>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Liked,
+    TopTracks,
+    Playlist(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Cond(Condition),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    AddedWithin(chrono::Duration),
+    AddedBefore(chrono::Duration),
+    Genre(String),
+    Artist(String),
+    InPlaylist(String),
+    Popularity(Cmp, u32),
+    Year(Cmp, u32),
+    Explicit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cmp {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+impl Cmp {
+    pub fn test(self, lhs: u32, rhs: u32) -> bool {
+        match self {
+            Cmp::Lt => lhs < rhs,
+            Cmp::Le => lhs <= rhs,
+            Cmp::Eq => lhs == rhs,
+            Cmp::Ge => lhs >= rhs,
+            Cmp::Gt => lhs > rhs,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Popularity,
+    Added,
+    Title,
+    Artist,
+    Duration,
+    Year,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl Rule {
+    pub fn parse(input: &str) -> color_eyre::Result<Self> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let rule = parser.rule()?;
+        if let Some(token) = parser.peek() {
+            bail!("Unexpected {token} at token {}", parser.pos + 1);
+        }
+        Ok(rule)
+    }
+
+    /// Whether evaluating this rule needs artist genres to be fetched.
+    pub fn needs_genres(&self) -> bool {
+        self.filter
+            .as_ref()
+            .is_some_and(|f| f.any(&|c| matches!(c, Condition::Genre(_))))
+    }
+
+    /// Names of playlists referenced by `in playlist` conditions.
+    pub fn referenced_playlists(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        if let Some(filter) = &self.filter {
+            filter.collect_playlists(&mut names);
+        }
+        names
+    }
+}
+
+impl Expr {
+    fn any(&self, pred: &dyn Fn(&Condition) -> bool) -> bool {
+        match self {
+            Expr::And(a, b) | Expr::Or(a, b) => a.any(pred) || b.any(pred),
+            Expr::Not(e) => e.any(pred),
+            Expr::Cond(c) => pred(c),
+        }
+    }
+
+    fn collect_playlists(&self, names: &mut Vec<String>) {
+        match self {
+            Expr::And(a, b) | Expr::Or(a, b) => {
+                a.collect_playlists(names);
+                b.collect_playlists(names);
+            }
+            Expr::Not(e) => e.collect_playlists(names),
+            Expr::Cond(Condition::InPlaylist(name)) => {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+            Expr::Cond(_) => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Number(u32),
+    Duration(chrono::Duration),
+    Op(Cmp),
+    LParen,
+    RParen,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(w) => write!(f, "'{w}'"),
+            Token::Str(s) => write!(f, "\"{s}\""),
+            Token::Number(n) => write!(f, "{n}"),
+            Token::Duration(d) => write!(f, "{}d", d.num_days()),
+            Token::Op(op) => write!(f, "{op:?}"),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> color_eyre::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => s.push(c),
+                        None => bail!("Unterminated string \"{s}"),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            '<' | '>' | '=' => tokens.push(Token::Op(operator(&mut chars))),
+            c if c.is_ascii_digit() => tokens.push(number(&mut chars)?),
+            c if c.is_alphabetic() => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    word.push(c.to_ascii_lowercase());
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+            c => bail!("Unexpected character '{c}'"),
+        }
+    }
+    Ok(tokens)
+}
+
+fn operator(chars: &mut Peekable<Chars>) -> Cmp {
+    let first = chars.next();
+    let eq = chars.next_if_eq(&'=').is_some();
+    match (first, eq) {
+        (Some('<'), true) => Cmp::Le,
+        (Some('<'), false) => Cmp::Lt,
+        (Some('>'), true) => Cmp::Ge,
+        (Some('>'), false) => Cmp::Gt,
+        _ => Cmp::Eq,
+    }
+}
+
+fn number(chars: &mut Peekable<Chars>) -> color_eyre::Result<Token> {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        digits.push(c);
+    }
+    let n: u32 = digits
+        .parse()
+        .map_err(|_| eyre!("Number too large: {digits}"))?;
+    let (unit, days_per_unit) = match chars.next_if(|c| c.is_alphabetic()) {
+        None => return Ok(Token::Number(n)),
+        Some(unit @ 'd') => (unit, 1),
+        Some(unit @ 'w') => (unit, 7),
+        Some(unit @ 'm') => (unit, 30),
+        Some(unit @ 'y') => (unit, 365),
+        Some(unit) => {
+            bail!("Unknown duration unit '{unit}' in {digits}{unit}, expected d, w, m or y")
+        }
+    };
+    let Some(days) = n
+        .checked_mul(days_per_unit)
+        .and_then(|days| chrono::Duration::try_days(i64::from(days)))
+    else {
+        bail!("Duration too large: {digits}{unit}");
+    };
+    Ok(Token::Duration(days))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> color_eyre::Result<Token> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| eyre!("Unexpected end of rule"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Word(w)) if w == word) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect_word(&mut self, word: &str) -> color_eyre::Result<()> {
+        match self.next()? {
+            Token::Word(w) if w == word => Ok(()),
+            other => bail!("Expected '{word}', found {other}"),
+        }
+    }
+
+    fn string(&mut self) -> color_eyre::Result<String> {
+        match self.next()? {
+            Token::Str(s) => Ok(s),
+            other => bail!("Expected a quoted string, found {other}"),
+        }
+    }
+
+    fn number(&mut self) -> color_eyre::Result<u32> {
+        match self.next()? {
+            Token::Number(n) => Ok(n),
+            other => bail!("Expected a number, found {other}"),
+        }
+    }
+
+    fn duration(&mut self) -> color_eyre::Result<chrono::Duration> {
+        match self.next()? {
+            Token::Duration(d) => Ok(d),
+            other => bail!("Expected a duration like 30d, found {other}"),
+        }
+    }
+
+    fn comparison(&mut self) -> color_eyre::Result<(Cmp, u32)> {
+        let op = match self.next()? {
+            Token::Op(op) => op,
+            other => bail!("Expected a comparison operator, found {other}"),
+        };
+        Ok((op, self.number()?))
+    }
+
+    fn rule(&mut self) -> color_eyre::Result<Rule> {
+        let source = match self.next()? {
+            Token::Word(w) if w == "liked" => Source::Liked,
+            Token::Word(w) if w == "top" => Source::TopTracks,
+            Token::Word(w) if w == "playlist" => Source::Playlist(self.string()?),
+            other => bail!("Expected a source (liked, top or playlist \"name\"), found {other}"),
+        };
+        let filter = if self.eat_word("where") {
+            Some(self.or()?)
+        } else {
+            None
+        };
+        let sort = if self.eat_word("sort") {
+            let key = match self.next()? {
+                Token::Word(w) => match w.as_str() {
+                    "popularity" => SortKey::Popularity,
+                    "added" => SortKey::Added,
+                    "title" => SortKey::Title,
+                    "artist" => SortKey::Artist,
+                    "duration" => SortKey::Duration,
+                    "year" => SortKey::Year,
+                    _ => bail!("Unknown sort key '{w}'"),
+                },
+                other => bail!("Expected a sort key, found {other}"),
+            };
+            let descending = if self.eat_word("desc") {
+                true
+            } else {
+                self.eat_word("asc");
+                false
+            };
+            Some(Sort { key, descending })
+        } else {
+            None
+        };
+        let limit = if self.eat_word("limit") {
+            Some(self.number()? as usize)
+        } else {
+            None
+        };
+        Ok(Rule {
+            source,
+            filter,
+            sort,
+            limit,
+        })
+    }
+
+    fn or(&mut self) -> color_eyre::Result<Expr> {
+        let mut expr = self.and()?;
+        while self.eat_word("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> color_eyre::Result<Expr> {
+        let mut expr = self.unary()?;
+        while self.eat_word("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> color_eyre::Result<Expr> {
+        if self.eat_word("not") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let expr = self.or()?;
+            match self.next()? {
+                Token::RParen => return Ok(expr),
+                other => bail!("Expected ')', found {other}"),
+            }
+        }
+        Ok(Expr::Cond(self.condition()?))
+    }
+
+    fn condition(&mut self) -> color_eyre::Result<Condition> {
+        let word = match self.next()? {
+            Token::Word(w) => w,
+            other => bail!("Expected a condition, found {other}"),
+        };
+        let condition = match word.as_str() {
+            "added" => {
+                if self.eat_word("within") {
+                    Condition::AddedWithin(self.duration()?)
+                } else {
+                    self.expect_word("before")?;
+                    Condition::AddedBefore(self.duration()?)
+                }
+            }
+            "genre" => Condition::Genre(self.string()?),
+            "artist" => Condition::Artist(self.string()?),
+            "in" => {
+                self.expect_word("playlist")?;
+                Condition::InPlaylist(self.string()?)
+            }
+            "popularity" => {
+                let (op, n) = self.comparison()?;
+                Condition::Popularity(op, n)
+            }
+            "year" => {
+                let (op, n) = self.comparison()?;
+                Condition::Year(op, n)
+            }
+            "explicit" => Condition::Explicit,
+            _ => bail!("Unknown condition '{word}'"),
+        };
+        Ok(condition)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cond(condition: Condition) -> Box<Expr> {
+        Box::new(Expr::Cond(condition))
+    }
+
+    #[test]
+    fn parses_a_full_rule() {
+        let rule = Rule::parse(
+            r#"playlist "Road Trip" where added within 2w and not in playlist "Old" sort popularity desc limit 50"#,
+        )
+        .unwrap();
+        assert_eq!(
+            rule,
+            Rule {
+                source: Source::Playlist("Road Trip".to_string()),
+                filter: Some(Expr::And(
+                    cond(Condition::AddedWithin(chrono::Duration::days(14))),
+                    Box::new(Expr::Not(cond(Condition::InPlaylist("Old".to_string())))),
+                )),
+                sort: Some(Sort {
+                    key: SortKey::Popularity,
+                    descending: true,
+                }),
+                limit: Some(50),
+            }
+        );
+        assert_eq!(rule.referenced_playlists(), ["Old"]);
+        assert!(!rule.needs_genres());
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let rule = Rule::parse("top where explicit or year < 1990 and popularity >= 70").unwrap();
+        assert_eq!(
+            rule.filter,
+            Some(Expr::Or(
+                cond(Condition::Explicit),
+                Box::new(Expr::And(
+                    cond(Condition::Year(Cmp::Lt, 1990)),
+                    cond(Condition::Popularity(Cmp::Ge, 70)),
+                )),
+            ))
+        );
+    }
+
+    #[test]
+    fn parentheses_group() {
+        let rule =
+            Rule::parse(r#"liked where (genre "jazz" or explicit) and year = 2020"#).unwrap();
+        assert_eq!(
+            rule.filter,
+            Some(Expr::And(
+                Box::new(Expr::Or(
+                    cond(Condition::Genre("jazz".to_string())),
+                    cond(Condition::Explicit),
+                )),
+                cond(Condition::Year(Cmp::Eq, 2020)),
+            ))
+        );
+        assert!(rule.needs_genres());
+    }
+
+    #[test]
+    fn keywords_ignore_case_but_strings_keep_it() {
+        let rule = Rule::parse(r#"LIKED Where Artist "Nina Simone" SORT Title"#).unwrap();
+        assert_eq!(rule.source, Source::Liked);
+        assert_eq!(
+            rule.filter,
+            Some(Expr::Cond(Condition::Artist("Nina Simone".to_string())))
+        );
+        assert_eq!(
+            rule.sort,
+            Some(Sort {
+                key: SortKey::Title,
+                descending: false,
+            })
+        );
+    }
+
+    #[test]
+    fn duration_units() {
+        for (text, days) in [("3d", 3), ("2w", 14), ("6m", 180), ("1y", 365)] {
+            let rule = Rule::parse(&format!("liked where added before {text}")).unwrap();
+            assert_eq!(
+                rule.filter,
+                Some(Expr::Cond(Condition::AddedBefore(chrono::Duration::days(
+                    days
+                )))),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let errors: Vec<String> = [
+            "",
+            "everything",
+            "liked where",
+            "liked where genre jazz",
+            "liked where genre \"jazz",
+            "liked where added within 30",
+            "liked where added within 30x",
+            "liked where added within 99999999y",
+            "liked where added within 99999999999d",
+            "liked where popularity 50",
+            "liked where tempo > 120",
+            "liked where (explicit",
+            "liked where explicit)",
+            "liked sort mood",
+            "liked limit many",
+            "liked where explicit & year > 2000",
+        ]
+        .into_iter()
+        .map(|rule| format!("{rule:?}: {}", Rule::parse(rule).unwrap_err()))
+        .collect();
+        insta::assert_snapshot!(errors.join("\n"));
+    }
+}
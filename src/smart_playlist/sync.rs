@@ -0,0 +1,226 @@
+use crate::{
+    api,
+    auth::Spotify,
+    smart_playlist::{
+        SmartPlaylist,
+        eval::{self, Candidate, LibraryData},
+        rule::Source,
+    },
+};
+use color_eyre::eyre::eyre;
+use spotify_rs::model::playlist::SimplifiedPlaylist;
+use std::collections::HashSet;
+
+#[derive(Debug)]
+pub struct SyncReport {
+    pub name: String,
+    pub created: bool,
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// Materializes every smart playlist, creating missing target playlists on the fly.
+pub async fn sync_all(
+    spotify: &Spotify,
+    user_id: &str,
+    smart_playlists: &[SmartPlaylist],
+) -> color_eyre::Result<Vec<SyncReport>> {
+    let playlists = api::all_user_playlists(spotify).await?;
+    let mut reports = Vec::new();
+    for smart in smart_playlists {
+        match sync(spotify, user_id, smart, &playlists).await {
+            Ok(report) => reports.push(report),
+            Err(e) => log::error!("Failed to sync smart playlist {}: {e}", smart.name),
+        }
+    }
+    Ok(reports)
+}
+
+pub async fn sync(
+    spotify: &Spotify,
+    user_id: &str,
+    smart: &SmartPlaylist,
+    playlists: &[SimplifiedPlaylist],
+) -> color_eyre::Result<SyncReport> {
+    let rule = &smart.rule;
+    let candidates = match &rule.source {
+        Source::Liked => spotify_rs::saved_tracks()
+            .limit(50)
+            .get(spotify)
+            .await?
+            .get_remaining(spotify)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|saved| Candidate {
+                track: saved.track,
+                added_at: Some(saved.added_at),
+            })
+            .collect(),
+        Source::TopTracks => spotify_rs::current_user_top_tracks()
+            .limit(50)
+            .get(spotify)
+            .await?
+            .get_remaining(spotify)
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|track| Candidate {
+                track,
+                added_at: None,
+            })
+            .collect(),
+        Source::Playlist(name) => {
+            let playlist = find_playlist(playlists, name)
+                .ok_or_else(|| eyre!("Source playlist \"{name}\" not found"))?;
+            playlist_candidates(spotify, &playlist.id).await?
+        }
+    };
+
+    let mut data = LibraryData::default();
+    if rule.needs_genres() {
+        let artist_ids: Vec<String> = candidates
+            .iter()
+            .flat_map(|c: &Candidate| c.track.artists.iter().map(|a| a.id.clone()))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        data.genres = api::artist_genres(spotify, &artist_ids).await?;
+    }
+    for name in rule.referenced_playlists() {
+        let playlist = find_playlist(playlists, &name)
+            .ok_or_else(|| eyre!("Referenced playlist \"{name}\" not found"))?;
+        let uris = playlist_candidates(spotify, &playlist.id)
+            .await?
+            .into_iter()
+            .map(|c| c.track.uri)
+            .collect();
+        data.playlists.insert(name.to_lowercase(), uris);
+    }
+
+    let desired: Vec<String> = eval::evaluate(rule, candidates, &data, chrono::Utc::now())
+        .into_iter()
+        .map(|c| c.track.uri)
+        .collect();
+
+    let target = playlists
+        .iter()
+        .find(|p| p.owner.id == user_id && p.name == smart.name);
+    let (playlist_id, current, created) = match target {
+        Some(playlist) => {
+            let current: Vec<String> = playlist_candidates(spotify, &playlist.id)
+                .await?
+                .into_iter()
+                .map(|c| c.track.uri)
+                .collect();
+            (playlist.id.clone(), current, false)
+        }
+        None => {
+            let playlist = spotify_rs::create_playlist(user_id, &smart.name)
+                .public(false)
+                .description(smart.description.clone().unwrap_or_else(|| {
+                    format!("Smart playlist managed by spotui: {}", smart.rule_text)
+                }))
+                .send(spotify)
+                .await?;
+            (playlist.id, Vec::new(), true)
+        }
+    };
+
+    let (to_add, to_remove) = diff(&current, &desired);
+    if rule.sort.is_some() && !keeps_order(&current, &desired) {
+        api::replace_items(spotify, &playlist_id, &desired).await?;
+    } else {
+        api::remove_items_in_batches(spotify, &playlist_id, &to_remove, |_| {}).await?;
+        api::add_items_in_batches(spotify, &playlist_id, &to_add, |_| {}).await?;
+    }
+
+    Ok(SyncReport {
+        name: smart.name.clone(),
+        created,
+        added: to_add.len(),
+        removed: to_remove.len(),
+    })
+}
+
+/// Returns the URIs to add and to remove so `current` ends up holding exactly `desired`.
+/// Items already in the playlist are left where they are, so an unchanged rule costs no calls.
+/// A sorted rule whose order this wouldn't keep has the items replaced instead.
+pub fn diff(current: &[String], desired: &[String]) -> (Vec<String>, Vec<String>) {
+    let current_set: HashSet<&String> = current.iter().collect();
+    let desired_set: HashSet<&String> = desired.iter().collect();
+    let to_add = desired
+        .iter()
+        .filter(|uri| !current_set.contains(uri))
+        .cloned()
+        .collect();
+    let mut removed = HashSet::new();
+    let to_remove = current
+        .iter()
+        .filter(|uri| !desired_set.contains(uri) && removed.insert(*uri))
+        .cloned()
+        .collect();
+    (to_add, to_remove)
+}
+
+/// Whether applying [`diff`] leaves the playlist in the order of `desired`.
+/// New items are appended, so they only land in place when they sort last.
+pub fn keeps_order(current: &[String], desired: &[String]) -> bool {
+    let (to_add, _) = diff(current, desired);
+    let desired_set: HashSet<&String> = desired.iter().collect();
+    current
+        .iter()
+        .filter(|uri| desired_set.contains(uri))
+        .chain(&to_add)
+        .eq(desired)
+}
+
+fn find_playlist<'a>(
+    playlists: &'a [SimplifiedPlaylist],
+    name: &str,
+) -> Option<&'a SimplifiedPlaylist> {
+    playlists.iter().find(|p| p.name.eq_ignore_ascii_case(name))
+}
+
+async fn playlist_candidates(
+    spotify: &Spotify,
+    playlist_id: &str,
+) -> color_eyre::Result<Vec<Candidate>> {
+    Ok(api::playlist_tracks(spotify, playlist_id)
+        .await?
+        .into_iter()
+        .map(|(track, added_at)| Candidate { track, added_at })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uris(uris: &[&str]) -> Vec<String> {
+        uris.iter().map(|uri| uri.to_string()).collect()
+    }
+
+    #[test]
+    fn diff_adds_and_removes() {
+        let (to_add, to_remove) = diff(&uris(&["a", "b", "c", "b"]), &uris(&["c", "d", "a"]));
+        assert_eq!(to_add, ["d"]);
+        assert_eq!(to_remove, ["b"]);
+    }
+
+    #[test]
+    fn diff_of_an_unchanged_playlist_is_empty() {
+        let (to_add, to_remove) = diff(&uris(&["a", "b"]), &uris(&["a", "b"]));
+        assert!(to_add.is_empty() && to_remove.is_empty());
+    }
+
+    #[test]
+    fn keeps_order_when_new_items_come_last() {
+        assert!(keeps_order(
+            &uris(&["a", "x", "b"]),
+            &uris(&["a", "b", "c"])
+        ));
+        assert!(!keeps_order(&uris(&["a", "b"]), &uris(&["c", "a", "b"])));
+        assert!(!keeps_order(&uris(&["b", "a"]), &uris(&["a", "b"])));
+    }
+}
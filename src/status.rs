@@ -0,0 +1,382 @@
+use crate::widgets::table::format_duration;
+use serde::Serialize;
+use serde_json::json;
+use spotify_rs::model::{
+    PlayableItem,
+    player::{PlaybackState, RepeatState},
+};
+use std::{str::FromStr, time::Duration};
+
+/// Template `status` prints with when no `--format` is given.
+pub const DEFAULT_FORMAT: &str = "{state} {artist} - {title} [{progress}/{duration}]";
+
+/// The playback state as printed by `status`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Status {
+    pub playing: bool,
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    pub uri: Option<String>,
+    /// The largest cover of the album or episode.
+    pub art_url: Option<String>,
+    pub progress_ms: u32,
+    pub duration_ms: u32,
+    pub device: Option<String>,
+    pub volume: Option<u32>,
+    pub shuffle: bool,
+    pub repeat: &'static str,
+}
+
+impl Status {
+    pub fn new(state: Option<&PlaybackState>) -> Self {
+        let (title, artists, album, uri, images, duration_ms) =
+            match state.and_then(|state| state.item.as_ref()) {
+                Some(PlayableItem::Track(track)) => (
+                    Some(track.name.clone()),
+                    track.artists.iter().map(|a| a.name.clone()).collect(),
+                    Some(track.album.name.clone()),
+                    Some(track.uri.clone()),
+                    track.album.images.as_slice(),
+                    track.duration_ms,
+                ),
+                Some(PlayableItem::Episode(episode)) => (
+                    Some(episode.name.clone()),
+                    vec![episode.show.name.clone()],
+                    None,
+                    Some(episode.uri.clone()),
+                    episode.images.as_slice(),
+                    episode.duration_ms,
+                ),
+                None => (None, Vec::new(), None, None, [].as_slice(), 0),
+            };
+        let device = state.and_then(|state| state.device.as_ref());
+        Self {
+            playing: state.is_some_and(|state| state.is_playing),
+            title,
+            artists,
+            album,
+            uri,
+            // Spotify lists images largest first.
+            art_url: images.first().map(|image| image.url.clone()),
+            progress_ms: state.and_then(|state| state.progress_ms).unwrap_or(0),
+            duration_ms,
+            device: device.map(|device| device.name.clone()),
+            volume: device.and_then(|device| device.volume_percent),
+            shuffle: state.and_then(|state| state.shuffle_state) == Some(true),
+            repeat: match state.and_then(|state| state.repeat_state.as_ref()) {
+                Some(RepeatState::Track) => "track",
+                Some(RepeatState::Context) => "context",
+                Some(RepeatState::Off) | None => "off",
+            },
+        }
+    }
+
+    /// `playing`, `paused`, or `stopped` when nothing is loaded.
+    pub fn state(&self) -> &'static str {
+        match (&self.title, self.playing) {
+            (None, _) => "stopped",
+            (Some(_), true) => "playing",
+            (Some(_), false) => "paused",
+        }
+    }
+
+    /// Where playback is `elapsed` after this status was polled, assuming it
+    /// carried on uninterrupted.
+    pub fn position_ms(&self, elapsed: Duration) -> u32 {
+        let mut position = self.progress_ms;
+        if self.playing {
+            let elapsed = u32::try_from(elapsed.as_millis()).unwrap_or(u32::MAX);
+            position = position.saturating_add(elapsed);
+        }
+        position.min(self.duration_ms)
+    }
+
+    /// How far into the item playback is, in percent.
+    pub fn percent(&self) -> u32 {
+        (u64::from(self.progress_ms) * 100)
+            .checked_div(u64::from(self.duration_ms))
+            .map_or(0, |percent| percent.min(100) as u32)
+    }
+
+    /// The status as a waybar custom module expects it, with `text` rendered
+    /// from `template`.
+    pub fn waybar(&self, template: &Template) -> serde_json::Value {
+        let (text, tooltip) = if self.title.is_some() {
+            let tooltip = [
+                self.title.clone(),
+                Some(self.artists.join(", ")),
+                self.album.clone(),
+            ];
+            let tooltip: Vec<String> = tooltip.into_iter().flatten().collect();
+            (template.render(self), tooltip.join("\n"))
+        } else {
+            (String::new(), String::new())
+        };
+        json!({
+            "text": escape_markup(&text),
+            "tooltip": escape_markup(&tooltip),
+            "alt": self.state(),
+            "class": self.state(),
+            "percentage": self.percent(),
+        })
+    }
+}
+
+/// Waybar and notification daemons render text as Pango markup.
+pub fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// A `--format` template: text with fields such as `{title}` filled in.
+/// `{{` and `}}` stand for literal braces.
+#[derive(Debug, Clone)]
+pub struct Template(Vec<Piece>);
+
+#[derive(Debug, Clone)]
+enum Piece {
+    Text(String),
+    Field(Field),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Artist,
+    Title,
+    Album,
+    Progress,
+    Duration,
+    Percent,
+    State,
+    Status,
+    Device,
+    Volume,
+    Shuffle,
+    Repeat,
+    Uri,
+}
+
+impl Field {
+    const ALL: [Field; 13] = [
+        Field::Artist,
+        Field::Title,
+        Field::Album,
+        Field::Progress,
+        Field::Duration,
+        Field::Percent,
+        Field::State,
+        Field::Status,
+        Field::Device,
+        Field::Volume,
+        Field::Shuffle,
+        Field::Repeat,
+        Field::Uri,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Field::Artist => "artist",
+            Field::Title => "title",
+            Field::Album => "album",
+            Field::Progress => "progress",
+            Field::Duration => "duration",
+            Field::Percent => "percent",
+            Field::State => "state",
+            Field::Status => "status",
+            Field::Device => "device",
+            Field::Volume => "volume",
+            Field::Shuffle => "shuffle",
+            Field::Repeat => "repeat",
+            Field::Uri => "uri",
+        }
+    }
+
+    fn value(self, status: &Status) -> String {
+        match self {
+            Field::Artist => status.artists.join(", "),
+            Field::Title => status.title.clone().unwrap_or_default(),
+            Field::Album => status.album.clone().unwrap_or_default(),
+            Field::Progress => format_duration(status.progress_ms),
+            Field::Duration => format_duration(status.duration_ms),
+            Field::Percent => status.percent().to_string(),
+            Field::State => match status.state() {
+                "playing" => "▶",
+                "paused" => "⏸",
+                _ => "⏹",
+            }
+            .to_string(),
+            Field::Status => status.state().to_string(),
+            Field::Device => status.device.clone().unwrap_or_default(),
+            Field::Volume => status.volume.map(|v| v.to_string()).unwrap_or_default(),
+            Field::Shuffle => if status.shuffle { "on" } else { "off" }.to_string(),
+            Field::Repeat => status.repeat.to_string(),
+            Field::Uri => status.uri.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl Template {
+    pub fn render(&self, status: &Status) -> String {
+        self.0
+            .iter()
+            .map(|piece| match piece {
+                Piece::Text(text) => text.clone(),
+                Piece::Field(field) => field.value(status),
+            })
+            .collect()
+    }
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        DEFAULT_FORMAT.parse().expect("the default format is valid")
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or("unclosed `{`, write `{{` for a brace")?;
+                    let name = &rest[..end];
+                    let field = Field::ALL
+                        .into_iter()
+                        .find(|field| field.name() == name)
+                        .ok_or_else(|| {
+                            let names: Vec<&str> = Field::ALL.iter().map(|f| f.name()).collect();
+                            format!(
+                                "unknown field {{{name}}}, expected one of {}",
+                                names.join(", ")
+                            )
+                        })?;
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(Piece::Field(field));
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => return Err("unmatched `}`, write `}}` for a brace".to_string()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(Self(pieces))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status() -> Status {
+        Status {
+            playing: true,
+            title: Some("So What".to_string()),
+            artists: vec!["Miles Davis".to_string(), "John Coltrane".to_string()],
+            album: Some("Kind of Blue".to_string()),
+            uri: Some("spotify:track:4vLYewWIvqHfKtJDk8c8tq".to_string()),
+            art_url: None,
+            progress_ms: 83_000,
+            duration_ms: 562_000,
+            device: Some("Kitchen".to_string()),
+            volume: Some(40),
+            shuffle: false,
+            repeat: "context",
+        }
+    }
+
+    fn render(template: &str) -> String {
+        template.parse::<Template>().unwrap().render(&status())
+    }
+
+    #[test]
+    fn renders_fields() {
+        assert_eq!(
+            Template::default().render(&status()),
+            "▶ Miles Davis, John Coltrane - So What [1:23/9:22]"
+        );
+        assert_eq!(
+            render("{status} on {device} at {volume}%, {percent}% in"),
+            "playing on Kitchen at 40%, 14% in"
+        );
+        assert_eq!(
+            render("{album}|{shuffle}|{repeat}|{uri}"),
+            "Kind of Blue|off|context|spotify:track:4vLYewWIvqHfKtJDk8c8tq"
+        );
+        assert_eq!(render(""), "");
+        assert_eq!(render("no fields"), "no fields");
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(render("{{title}}"), "{title}");
+        assert_eq!(render("{{{title}}}"), "{So What}");
+        assert_eq!(render("}}{{"), "}{");
+    }
+
+    #[test]
+    fn missing_values_render_empty() {
+        let stopped = Status::new(None);
+        let template: Template = "[{title}|{artist}|{device}|{volume}] {state} {status}"
+            .parse()
+            .unwrap();
+        assert_eq!(template.render(&stopped), "[|||] ⏹ stopped");
+    }
+
+    #[test]
+    fn position_moves_on_while_playing() {
+        let playing = status();
+        assert_eq!(playing.position_ms(Duration::from_secs(2)), 85_000);
+        assert_eq!(playing.position_ms(Duration::from_secs(3600)), 562_000);
+        let paused = Status {
+            playing: false,
+            ..status()
+        };
+        assert_eq!(paused.position_ms(Duration::from_secs(2)), 83_000);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let errors: Vec<String> = [
+            "{",
+            "{title",
+            "{title} {",
+            "}",
+            "{title}}",
+            "{}",
+            "{Title}",
+            "{ title }",
+            "{name}",
+        ]
+        .into_iter()
+        .map(|template| {
+            format!(
+                "{template:?}: {}",
+                template.parse::<Template>().unwrap_err()
+            )
+        })
+        .collect();
+        insta::assert_snapshot!(errors.join("\n"));
+    }
+}
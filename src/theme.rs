@@ -0,0 +1,294 @@
+use color_eyre::eyre::eyre;
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+use std::{collections::BTreeMap, str::FromStr};
+
+/// Names of the bundled themes, the first being the default.
+pub const THEME_NAMES: [&str; 4] = ["default", "nord", "high-contrast", "mono"];
+
+/// Names of the styles a theme defines, as used in the `[theme.styles]` config.
+const STYLE_NAMES: [&str; 14] = [
+    "border-active",
+    "border-inactive",
+    "title",
+    "highlight",
+    "marked",
+    "filter-match",
+    "header",
+    "tab",
+    "gauge",
+    "log",
+    "log-error",
+    "log-warn",
+    "log-info",
+    "log-debug",
+];
+
+/// The `[theme]` config: a bundled theme and styles replacing some of its own.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub name: Option<String>,
+    pub styles: BTreeMap<String, StyleConfig>,
+}
+
+/// A style written as `{ fg = "yellow", bg = "#2e3440", modifiers = ["bold"] }`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct StyleConfig {
+    pub fg: Option<String>,
+    pub bg: Option<String>,
+    pub modifiers: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub border_active: Style,
+    pub border_inactive: Style,
+    pub title: Style,
+    /// The row under the cursor.
+    pub highlight: Style,
+    /// Rows marked for bulk actions.
+    pub marked: Style,
+    /// Characters matched by the `/` filter.
+    pub filter_match: Style,
+    /// Table column headers.
+    pub header: Style,
+    /// The selected tab.
+    pub tab: Style,
+    pub gauge: Style,
+    pub log: Style,
+    pub log_error: Style,
+    pub log_warn: Style,
+    pub log_info: Style,
+    pub log_debug: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            border_active: Style::default(),
+            border_inactive: Style::default(),
+            title: Style::default(),
+            highlight: Style::default()
+                .fg(Color::Yellow)
+                .bg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+            marked: Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD | Modifier::REVERSED),
+            filter_match: Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            header: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            tab: Style::default().fg(Color::Yellow),
+            gauge: Style::default().fg(Color::Green),
+            log: Style::default().fg(Color::White),
+            log_error: Style::default().fg(Color::Red),
+            log_warn: Style::default().fg(Color::Yellow),
+            log_info: Style::default().fg(Color::White),
+            log_debug: Style::default().fg(Color::Green),
+        }
+    }
+}
+
+impl Theme {
+    /// A bundled theme by name.
+    pub fn named(name: &str) -> Option<Self> {
+        let rgb = |hex: u32| Color::from_u32(hex);
+        let theme = match name {
+            "default" => Self::default(),
+            "nord" => Self {
+                border_active: Style::default().fg(rgb(0x88c0d0)),
+                border_inactive: Style::default().fg(rgb(0x4c566a)),
+                title: Style::default().fg(rgb(0xd8dee9)),
+                highlight: Style::default()
+                    .fg(rgb(0x2e3440))
+                    .bg(rgb(0x88c0d0))
+                    .add_modifier(Modifier::BOLD),
+                marked: Style::default()
+                    .fg(rgb(0xebcb8b))
+                    .add_modifier(Modifier::BOLD | Modifier::REVERSED),
+                filter_match: Style::default()
+                    .fg(rgb(0xb48ead))
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                header: Style::default()
+                    .fg(rgb(0x81a1c1))
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                tab: Style::default().fg(rgb(0x88c0d0)),
+                gauge: Style::default().fg(rgb(0xa3be8c)),
+                log: Style::default().fg(rgb(0xe5e9f0)),
+                log_error: Style::default().fg(rgb(0xbf616a)),
+                log_warn: Style::default().fg(rgb(0xebcb8b)),
+                log_info: Style::default().fg(rgb(0xe5e9f0)),
+                log_debug: Style::default().fg(rgb(0xa3be8c)),
+            },
+            "high-contrast" => Self {
+                border_active: Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+                border_inactive: Style::default().fg(Color::Gray),
+                title: Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+                highlight: Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+                marked: Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+                filter_match: Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                header: Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                tab: Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+                gauge: Style::default().fg(Color::White).bg(Color::Black),
+                log: Style::default().fg(Color::White),
+                log_error: Style::default()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+                log_warn: Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+                log_info: Style::default().fg(Color::White),
+                log_debug: Style::default().fg(Color::LightGreen),
+            },
+            "mono" => Self {
+                border_active: Style::default().add_modifier(Modifier::BOLD),
+                border_inactive: Style::default(),
+                title: Style::default(),
+                highlight: Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+                marked: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                filter_match: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                header: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                tab: Style::default().add_modifier(Modifier::REVERSED),
+                gauge: Style::default(),
+                log: Style::default(),
+                log_error: Style::default().add_modifier(Modifier::BOLD),
+                log_warn: Style::default().add_modifier(Modifier::BOLD),
+                log_info: Style::default(),
+                log_debug: Style::default().add_modifier(Modifier::DIM),
+            },
+            _ => return None,
+        };
+        Some(theme)
+    }
+
+    /// The theme named in `config` with its style overrides applied. When
+    /// `NO_COLOR` is set the `mono` theme is used and every color is dropped.
+    pub fn from_config(config: &ThemeConfig) -> color_eyre::Result<Self> {
+        let no_color = no_color();
+        let name = match (&config.name, no_color) {
+            (_, true) => "mono",
+            (Some(name), false) => name.as_str(),
+            (None, false) => THEME_NAMES[0],
+        };
+        let mut theme = Self::named(name).ok_or_else(|| {
+            eyre!(
+                "Unknown theme `{name}`, expected one of: {}",
+                THEME_NAMES.join(", ")
+            )
+        })?;
+        let mut errors = Vec::new();
+        for (name, style) in &config.styles {
+            match (theme.style_mut(name), style.parse()) {
+                (Some(slot), Ok(style)) => *slot = style,
+                (None, _) => errors.push(format!(
+                    "unknown style `{name}`, expected one of: {}",
+                    STYLE_NAMES.join(", ")
+                )),
+                (_, Err(e)) => errors.push(format!("{name}: {e}")),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(eyre!("Invalid theme:\n  {}", errors.join("\n  ")));
+        }
+        if no_color {
+            theme = theme.without_colors();
+        }
+        Ok(theme)
+    }
+
+    /// The theme with only its modifiers left.
+    pub fn without_colors(mut self) -> Self {
+        for name in STYLE_NAMES {
+            if let Some(style) = self.style_mut(name) {
+                *style = Style::default()
+                    .add_modifier(style.add_modifier)
+                    .remove_modifier(style.sub_modifier);
+            }
+        }
+        self
+    }
+
+    /// Style of a block's border and title.
+    pub fn border(&self, is_active: bool) -> Style {
+        if is_active {
+            self.border_active
+        } else {
+            self.border_inactive
+        }
+    }
+
+    fn style_mut(&mut self, name: &str) -> Option<&mut Style> {
+        Some(match name {
+            "border-active" => &mut self.border_active,
+            "border-inactive" => &mut self.border_inactive,
+            "title" => &mut self.title,
+            "highlight" => &mut self.highlight,
+            "marked" => &mut self.marked,
+            "filter-match" => &mut self.filter_match,
+            "header" => &mut self.header,
+            "tab" => &mut self.tab,
+            "gauge" => &mut self.gauge,
+            "log" => &mut self.log,
+            "log-error" => &mut self.log_error,
+            "log-warn" => &mut self.log_warn,
+            "log-info" => &mut self.log_info,
+            "log-debug" => &mut self.log_debug,
+            _ => return None,
+        })
+    }
+}
+
+/// Whether colors are turned off through the `NO_COLOR` environment variable.
+pub fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+}
+
+impl StyleConfig {
+    fn parse(&self) -> Result<Style, String> {
+        let color =
+            |text: &String| Color::from_str(text).map_err(|_| format!("invalid color `{text}`"));
+        let mut style = Style::default();
+        if let Some(fg) = &self.fg {
+            style = style.fg(color(fg)?);
+        }
+        if let Some(bg) = &self.bg {
+            style = style.bg(color(bg)?);
+        }
+        for name in &self.modifiers {
+            let modifier = match name.as_str() {
+                "bold" => Modifier::BOLD,
+                "dim" => Modifier::DIM,
+                "italic" => Modifier::ITALIC,
+                "underlined" => Modifier::UNDERLINED,
+                "reversed" => Modifier::REVERSED,
+                "crossed-out" => Modifier::CROSSED_OUT,
+                "slow-blink" => Modifier::SLOW_BLINK,
+                "rapid-blink" => Modifier::RAPID_BLINK,
+                "hidden" => Modifier::HIDDEN,
+                _ => return Err(format!("invalid modifier `{name}`")),
+            };
+            style = style.add_modifier(modifier);
+        }
+        Ok(style)
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{
+    api::{self, LIBRARY_BATCH_SIZE},
+    auth::Spotify,
+    event::{ProfileSender, ProgressReporter},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackOption {
+    Play,
+    AddToQueue,
+    AddToPlaylist,
+    Like,
+    RemoveFromPlaylist,
+    GoToArtist,
+    GoToAlbum,
+}
+
+impl TrackOption {
+    pub const ALL: [TrackOption; 7] = [
+        TrackOption::Play,
+        TrackOption::AddToQueue,
+        TrackOption::AddToPlaylist,
+        TrackOption::Like,
+        TrackOption::RemoveFromPlaylist,
+        TrackOption::GoToArtist,
+        TrackOption::GoToAlbum,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TrackOption::Play => "Play",
+            TrackOption::AddToQueue => "Add to Queue",
+            TrackOption::AddToPlaylist => "Add to Playlist",
+            TrackOption::Like => "Like",
+            TrackOption::RemoveFromPlaylist => "Remove from Playlist",
+            TrackOption::GoToArtist => "Go to Artist",
+            TrackOption::GoToAlbum => "Go to Album",
+        }
+    }
+}
+
+/// A bulk action applied to every selected track.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrackAction {
+    Play,
+    Queue,
+    AddToPlaylist { id: String, name: String },
+    Like,
+    RemoveFromPlaylist { id: String, name: String },
+}
+
+impl TrackAction {
+    pub fn label(&self) -> String {
+        match self {
+            TrackAction::Play => "Play".to_string(),
+            TrackAction::Queue => "Add to Queue".to_string(),
+            TrackAction::AddToPlaylist { name, .. } => format!("Add to {name}"),
+            TrackAction::Like => "Like".to_string(),
+            TrackAction::RemoveFromPlaylist { name, .. } => format!("Remove from {name}"),
+        }
+    }
+}
+
+/// Applies `action` to `uris`, reporting progress and finally an
+/// [`crate::event::AppEvent::ProgressDone`] with a summary through `sender`.
+pub async fn run(action: TrackAction, spotify: &Spotify, uris: Vec<String>, sender: ProfileSender) {
+    let label = action.label();
+    let reporter = ProgressReporter::new(sender, label.as_str());
+    let total = uris.len();
+    let result = match &action {
+        TrackAction::Play => {
+            let uris: Vec<&str> = uris.iter().map(String::as_str).collect();
+            spotify_rs::start_playback()
+                .uris(&uris)
+                .send(spotify)
+                .await
+                .map(|_| format!("Playing {total} tracks"))
+                .map_err(Into::into)
+        }
+        TrackAction::Queue => queue(spotify, &uris, &reporter).await,
+        TrackAction::AddToPlaylist { id, name } => {
+            api::add_items_in_batches(spotify, id, &uris, |done| {
+                reporter.report("adding", done, total)
+            })
+            .await
+            .map(|_| format!("Added {total} tracks to {name}"))
+        }
+        TrackAction::Like => like(spotify, &uris, &reporter).await,
+        TrackAction::RemoveFromPlaylist { id, name } => {
+            api::remove_items_in_batches(spotify, id, &uris, |done| {
+                reporter.report("removing", done, total)
+            })
+            .await
+            .map(|_| format!("Removed {total} tracks from {name}"))
+        }
+    };
+    let summary = match result {
+        Ok(summary) => summary,
+        Err(e) => {
+            log::error!("{label} failed: {e}");
+            format!("{label} failed: {e}")
+        }
+    };
+    reporter.done(summary);
+}
+
+async fn queue(
+    spotify: &Spotify,
+    uris: &[String],
+    reporter: &ProgressReporter,
+) -> color_eyre::Result<String> {
+    for (i, uri) in uris.iter().enumerate() {
+        spotify_rs::add_item_to_queue(uri).send(spotify).await?;
+        reporter.report("queueing", i + 1, uris.len());
+    }
+    Ok(format!("Queued {} tracks", uris.len()))
+}
+
+async fn like(
+    spotify: &Spotify,
+    uris: &[String],
+    reporter: &ProgressReporter,
+) -> color_eyre::Result<String> {
+    // Episodes can't be saved to the liked songs library.
+    let ids: Vec<&str> = uris
+        .iter()
+        .filter_map(|uri| uri.strip_prefix("spotify:track:"))
+        .collect();
+    let mut done = 0;
+    for chunk in ids.chunks(LIBRARY_BATCH_SIZE) {
+        spotify_rs::save_tracks(chunk, spotify).await?;
+        done += chunk.len();
+        reporter.report("saving", done, ids.len());
+    }
+    Ok(format!("Liked {} tracks", ids.len()))
+}
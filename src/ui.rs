@@ -1,144 +1,538 @@
-use crate::app::{ActiveBlock, App, SelectedTab};
 use crate::widgets::{
-    nav_list::NavList, playlist::PlaylistWidget, top_artists::TopArtistsWidget,
-    top_tracks::TopTracksWidget, user_playlists::UserPlaylistsWidget,
+    help::HelpWidget,
+    nav_list::NavList,
+    palette::PaletteWidget,
+    table::{ARTIST_COLUMNS, PLAYLIST_COLUMNS, TableWidget},
+};
+use crate::{
+    app::{self, ActiveBlock, App, SelectedTab},
+    artwork::Protocol,
+    event::Progress,
+    panes,
+    status::{Status, Template},
 };
 use ratatui::{
     buffer::Buffer,
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
-    widgets::{Block, Clear, Tabs, Widget},
+    layout::{Constraint, Layout, Margin, Rect},
+    text::{Line, Span},
+    widgets::{Block, Clear, Gauge, Tabs, Widget},
 };
 use tui_logger::{TuiLoggerLevelOutput, TuiLoggerWidget};
+use unicode_width::UnicodeWidthStr;
+
+pub const TAB_TITLES: [&str; 2] = ["Main", "Logger"];
+
+/// Where each part of the screen is drawn. Shared by rendering and mouse handling.
+#[derive(Debug, Clone, Copy)]
+pub struct Areas {
+    pub tabs: Rect,
+    /// Everything between the tabs and the now-playing bar.
+    pub body: Rect,
+    pub directory: Rect,
+    /// The main content pane, breadcrumbs included. Popups are placed relative to it.
+    pub main: Rect,
+    pub breadcrumbs: Rect,
+    /// The list shown in the main content pane.
+    pub content: Rect,
+    /// The cover art pane beside the list, empty when there's no room for it.
+    pub artwork: Rect,
+    /// The now-playing bar along the bottom, empty on short terminals.
+    pub now_playing: Rect,
+}
+
+impl App {
+    /// Lays out the screen. Compact terminals lose the margins and only show
+    /// the Directory sidebar while it is focused.
+    pub fn areas(&self, area: Rect) -> Areas {
+        let bar = if area.height >= panes::NOW_PLAYING_MIN_HEIGHT {
+            3
+        } else {
+            0
+        };
+        let [tabs, body, now_playing] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Fill(1),
+            Constraint::Length(bar),
+        ])
+        .areas(area);
+        let margin = if self.panes.is_compact(area.width) {
+            0
+        } else {
+            1
+        };
+        let inner = body.inner(Margin::new(margin, margin));
+        let sidebar = self.panes.sidebar_width(
+            inner.width,
+            self.route.active_block == ActiveBlock::Directory,
+        );
+        let [directory, main] =
+            Layout::horizontal([Constraint::Length(sidebar), Constraint::Fill(1)]).areas(inner);
+        let [breadcrumbs, content] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(main);
+        let [content, artwork] = Layout::horizontal([
+            Constraint::Fill(1),
+            Constraint::Length(self.artwork_width(content)),
+        ])
+        .areas(content);
+        Areas {
+            tabs,
+            body,
+            directory,
+            main,
+            breadcrumbs,
+            content,
+            artwork,
+            now_playing,
+        }
+    }
+
+    /// Width of the artwork pane beside the list in `content`. Cells are about
+    /// twice as tall as they are wide, so this keeps the image area square.
+    fn artwork_width(&self, content: Rect) -> u16 {
+        const MIN_WIDTH: u16 = 12;
+        if self.artwork.protocol == Protocol::None {
+            return 0;
+        }
+        let width = (content.height.saturating_sub(2) * 2 + 2).min(content.width / 3);
+        if width < MIN_WIDTH { 0 } else { width }
+    }
+
+    /// The cover shown in the artwork pane and where it's drawn, unless
+    /// something is drawn over it.
+    pub fn artwork_target(&self, area: Rect) -> Option<(String, Rect)> {
+        let covered = !matches!(self.selected_tab, SelectedTab::Main)
+            || matches!(
+                self.route.active_block,
+                ActiveBlock::Help | ActiveBlock::Palette
+            )
+            || self.progress.is_some()
+            || self.loading_progress().is_some();
+        let pane = self.areas(area).artwork;
+        if covered || pane.is_empty() {
+            return None;
+        }
+        let (title, url) = self.shown_artwork()?;
+        Some((url.to_string(), artwork_block(title).inner(pane)))
+    }
+
+    /// Title of the artwork pane and the cover it shows: that of the row
+    /// under the cursor, or else that of what is playing.
+    fn shown_artwork(&self) -> Option<(&'static str, &str)> {
+        if let Some(url) = self.selected_image_url() {
+            return Some(("Artwork", url));
+        }
+        let url = self.playback.as_ref()?.art_url.as_deref()?;
+        Some(("Now Playing", url))
+    }
+
+    /// The popup shown over the main content pane, if the focused block is one.
+    pub fn open_popup(&self) -> Option<&app::NavList> {
+        match self.route.active_block {
+            ActiveBlock::Popup => Some(&self.track_popup),
+            ActiveBlock::PlaylistPopup => Some(&self.playlist_popup),
+            ActiveBlock::PlaylistPicker => Some(&self.playlist_picker),
+            _ => None,
+        }
+    }
+}
+
+fn artwork_block(title: &str) -> Block<'_> {
+    Block::bordered().title(title)
+}
+
+/// Where `popup` is drawn over the main content pane `area`. A popup taller
+/// than `area` is cut to fit and its list scrolls.
+pub fn popup_area(popup: &app::NavList, area: Rect) -> Rect {
+    let height = (popup.list.len() as u16).saturating_add(2).min(area.height);
+    Rect {
+        x: area.x + area.width / 4,
+        y: area.y + (area.height / 4).min(area.height - height),
+        width: area.width / 4,
+        height,
+    }
+}
 
 impl Widget for &App {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let main_layout = Layout::new(
-            Direction::Vertical,
-            [Constraint::Percentage(10), Constraint::Percentage(90)],
-        )
-        .margin(0)
-        .split(area);
-
-        let content_layout = Layout::new(
-            Direction::Horizontal,
-            [Constraint::Percentage(20), Constraint::Percentage(80)],
-        )
-        .margin(1)
-        .split(main_layout[1]);
-        self.render_tabs(main_layout[0], buf);
+        let (min_width, min_height) = panes::MIN_SIZE;
+        if area.width < min_width || area.height < min_height {
+            Line::from(format!(
+                "Terminal too small, needs {min_width}x{min_height}"
+            ))
+            .centered()
+            .render(area, buf);
+            return;
+        }
+        let areas = self.areas(area);
+        self.render_tabs(areas.tabs, buf);
         match self.selected_tab {
             SelectedTab::Main => {
-                self.render_directory(content_layout[0], buf);
-                self.render_main_content(content_layout[1], buf);
-                if matches!(self.route.active_block, ActiveBlock::Popup) {
-                    self.render_track_popup(content_layout[1], buf);
+                if !areas.directory.is_empty() {
+                    self.render_directory(areas.directory, buf);
+                }
+                self.render_breadcrumbs(areas.breadcrumbs, buf);
+                self.render_main_content(areas.content, buf);
+                if !areas.artwork.is_empty() {
+                    self.render_artwork(areas.artwork, buf);
+                }
+                if let Some(popup) = self.open_popup() {
+                    self.render_popup(popup, areas.main, buf);
+                }
+                if let Some(progress) = self.progress.clone().or_else(|| self.loading_progress()) {
+                    self.render_progress(&progress, areas.main, buf);
                 }
             }
             SelectedTab::Logger => {
-                self.render_logger(main_layout[1], buf);
-                return;
+                self.render_logger(areas.body, buf);
+            }
+        }
+        if !areas.now_playing.is_empty() {
+            self.render_now_playing(areas.now_playing, buf);
+        }
+        if self.route.active_block == ActiveBlock::Help {
+            HelpWidget {
+                help: &self.help,
+                theme: &self.theme,
+            }
+            .render(areas.body, buf);
+        }
+        if self.route.active_block == ActiveBlock::Palette {
+            PaletteWidget {
+                palette: &self.palette,
+                theme: &self.theme,
             }
+            .render(areas.body, buf);
         }
     }
 }
 
 impl App {
     fn render_tabs(&self, area: Rect, buf: &mut Buffer) {
-        let titles = ["Main", "Logger"];
         let selected = match self.selected_tab {
             SelectedTab::Main => 0,
             SelectedTab::Logger => 1,
         };
-        let highlight_style = Style::default().fg(Color::Yellow);
-        Tabs::new(titles)
+        let mut block = Block::default()
+            .borders(ratatui::widgets::Borders::ALL)
+            .border_style(self.theme.border_inactive)
+            .title("Tabs")
+            .title_style(self.theme.title);
+        if self.spotify_client.is_none() {
+            block = block.title(
+                Line::styled(
+                    " Offline: cached library, playback disabled ",
+                    self.theme.log_warn,
+                )
+                .right_aligned(),
+            );
+        }
+        Tabs::new(TAB_TITLES)
             .select(selected)
-            .highlight_style(highlight_style)
-            .block(
-                Block::default()
-                    .borders(ratatui::widgets::Borders::ALL)
-                    .title("Tabs"),
-            )
+            .highlight_style(self.theme.tab)
+            .block(block)
             .render(area, buf);
     }
     fn render_logger(&self, area: Rect, buf: &mut Buffer) {
         let state = &self.logger_state;
 
         TuiLoggerWidget::default()
-            .block(Block::bordered().title("Logs"))
-            .style(Style::default().fg(Color::White))
-            .style_debug(Style::default().fg(Color::Green))
-            .style_error(Style::default().fg(Color::Red))
-            .style_warn(Style::default().fg(Color::Yellow))
+            .block(
+                Block::bordered()
+                    .border_style(self.theme.border_active)
+                    .title("Logs")
+                    .title_style(self.theme.title),
+            )
+            .style(self.theme.log)
+            .style_info(self.theme.log_info)
+            .style_debug(self.theme.log_debug)
+            .style_error(self.theme.log_error)
+            .style_warn(self.theme.log_warn)
             .output_separator('|')
             .output_timestamp(Some("%Y-%m-%d %H:%M:%S".to_string()))
             .output_level(Some(TuiLoggerLevelOutput::Abbreviated))
             .output_target(true)
             .output_file(false)
             .output_line(false)
-            .state(&state)
+            .state(state)
             .render(area, buf);
     }
 
-    fn render_track_popup(&self, area: Rect, buf: &mut Buffer) {
+    fn render_artwork(&self, area: Rect, buf: &mut Buffer) {
+        let title = self.shown_artwork().map_or("Artwork", |(title, _)| title);
+        let block = artwork_block(title)
+            .border_style(self.theme.border_inactive)
+            .title_style(self.theme.title);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        self.artwork.render(inner, buf);
+    }
+
+    fn render_popup(&self, popup: &app::NavList, area: Rect, buf: &mut Buffer) {
         let popup_block = NavList {
-            title: self.track_popup.title.clone(),
-            list: self.track_popup.list.clone(),
-            list_state: self.track_popup.list_state.clone(),
-            is_active: matches!(self.route.active_block, ActiveBlock::Popup),
+            title: popup.title.clone(),
+            list: popup.list.clone(),
+            list_state: popup.list_state.clone(),
+            is_active: true,
+            theme: &self.theme,
         };
 
-        let popup_area = Rect {
-            x: area.x + area.width / 4,
-            y: area.y + area.height / 4,
-            width: area.width / 4,
-            height: popup_block.list.len() as u16 + 2,
-        };
+        let popup_area = popup_area(popup, area);
         Clear.render(popup_area, buf); // Clear the area first
         popup_block.render(popup_area, buf);
     }
 
+    fn render_progress(&self, progress: &Progress, area: Rect, buf: &mut Buffer) {
+        let ratio = if progress.total == 0 {
+            0.0
+        } else {
+            (progress.done as f64 / progress.total as f64).min(1.0)
+        };
+        let gauge_area = Rect {
+            x: area.x + 1,
+            y: (area.y + area.height).saturating_sub(4),
+            width: area.width.saturating_sub(2),
+            height: 3.min(area.height),
+        }
+        .intersection(area);
+        Clear.render(gauge_area, buf);
+        Gauge::default()
+            .block(
+                Block::bordered()
+                    .border_style(self.theme.border_inactive)
+                    .title(progress.label.as_str())
+                    .title_style(self.theme.title),
+            )
+            .gauge_style(self.theme.gauge)
+            .label(format!("{}/{}", progress.done, progress.total))
+            .ratio(ratio)
+            .render(gauge_area, buf);
+    }
+
+    /// What is playing and how far in, from the polled playback state moved
+    /// on by the time since, as MPRIS reports it.
+    fn render_now_playing(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered()
+            .border_style(self.theme.border_inactive)
+            .title("Now Playing")
+            .title_style(self.theme.title);
+        let playing = self
+            .playback
+            .as_ref()
+            .filter(|status| status.title.is_some());
+        let Some(status) = playing else {
+            let message = if self.spotify_client.is_none() {
+                "Playback disabled offline"
+            } else {
+                "Nothing is playing"
+            };
+            Line::from(message)
+                .centered()
+                .render(block.inner(area), buf);
+            block.render(area, buf);
+            return;
+        };
+        let status = Status {
+            progress_ms: status.position_ms(self.playback_polled_at.elapsed()),
+            ..status.clone()
+        };
+        let ratio = if status.duration_ms == 0 {
+            0.0
+        } else {
+            f64::from(status.progress_ms) / f64::from(status.duration_ms)
+        };
+        Gauge::default()
+            .block(block)
+            .gauge_style(self.theme.gauge)
+            .label(Template::default().render(&status))
+            .ratio(ratio.min(1.0))
+            .render(area, buf);
+    }
+
     fn render_directory(&self, area: Rect, buf: &mut Buffer) {
         let directory = NavList {
             title: self.directory.title.clone(),
             list: self.directory.list.clone(),
             list_state: self.directory.list_state.clone(),
             is_active: matches!(self.route.active_block, ActiveBlock::Directory),
+            theme: &self.theme,
         };
 
         directory.render(area, buf);
     }
+    /// The views visited on the way to the shown one, most recent last.
+    fn render_breadcrumbs(&self, area: Rect, buf: &mut Buffer) {
+        const SEPARATOR: &str = " › ";
+        let Some(current) = self.current_visit() else {
+            return;
+        };
+        let mut spans = vec![Span::styled(
+            current.view.title().to_string(),
+            self.theme.title,
+        )];
+        let mut width = current.view.title().width();
+        for visit in self.history.back.iter().rev() {
+            let title = visit.view.title();
+            if width + SEPARATOR.width() + title.width() > usize::from(area.width) {
+                spans.push(Span::raw("… "));
+                break;
+            }
+            width += SEPARATOR.width() + title.width();
+            spans.push(Span::raw(SEPARATOR));
+            spans.push(Span::raw(title.to_string()));
+        }
+        spans.reverse();
+        Line::from(spans).render(area, buf);
+    }
+
     fn render_main_content(&self, area: Rect, buf: &mut Buffer) {
+        let is_active = self.route.active_block == self.route.hovered_block;
         match self.route.hovered_block {
             ActiveBlock::UserPlaylists => {
-                let user_playlists = UserPlaylistsWidget::new(
-                    self.user_library.user_playlists.clone(),
-                    matches!(self.route.active_block, ActiveBlock::UserPlaylists),
-                );
-                user_playlists.render(area, buf);
+                let list = &self.user_library.user_playlists;
+                TableWidget {
+                    title: "User Playlists".to_string(),
+                    empty_title: "No Playlists Found",
+                    items: &list.list,
+                    columns: &PLAYLIST_COLUMNS,
+                    list_state: &list.list_state,
+                    selection: &list.selection,
+                    filter: &list.filter,
+                    liked: &self.liked,
+                    sort: list.sort,
+                    is_active,
+                    theme: &self.theme,
+                }
+                .render(area, buf);
             }
             ActiveBlock::UserTopTracks => {
-                let top_tracks = TopTracksWidget::new(
-                    self.user_library.user_top_tracks.clone(),
-                    matches!(self.route.active_block, ActiveBlock::UserTopTracks),
-                );
-                top_tracks.render(area, buf);
+                let list = &self.user_library.user_top_tracks;
+                TableWidget {
+                    title: "User Top Tracks".to_string(),
+                    empty_title: "No Tracks Found",
+                    items: &list.list,
+                    columns: &self.track_columns,
+                    list_state: &list.list_state,
+                    selection: &list.selection,
+                    filter: &list.filter,
+                    liked: &self.liked,
+                    sort: list.sort,
+                    is_active,
+                    theme: &self.theme,
+                }
+                .render(area, buf);
             }
             ActiveBlock::UserTopArtists => {
-                let top_artists = TopArtistsWidget::new(
-                    self.user_library.user_top_artists.clone(),
-                    matches!(self.route.active_block, ActiveBlock::UserTopArtists),
-                );
-                top_artists.render(area, buf);
+                let list = &self.user_library.user_top_artists;
+                TableWidget {
+                    title: "User Top Artists".to_string(),
+                    empty_title: "No Artists Found",
+                    items: &list.list,
+                    columns: &ARTIST_COLUMNS,
+                    list_state: &list.list_state,
+                    selection: &list.selection,
+                    filter: &list.filter,
+                    liked: &self.liked,
+                    sort: list.sort,
+                    is_active,
+                    theme: &self.theme,
+                }
+                .render(area, buf);
             }
             ActiveBlock::Playlist => {
-                let playlist = PlaylistWidget::new(
-                    self.playlist.clone(),
-                    matches!(self.route.active_block, ActiveBlock::Playlist),
-                );
-                playlist.render(area, buf);
+                let playlist = &self.playlist;
+                let name = playlist
+                    .result
+                    .as_ref()
+                    .map_or("Playlist", |p| p.name.as_str());
+                TableWidget {
+                    title: name.to_string(),
+                    empty_title: "No Tracks Found",
+                    items: &playlist.pages.list,
+                    columns: &self.track_columns,
+                    list_state: &playlist.list_state,
+                    selection: &playlist.selection,
+                    filter: &playlist.filter,
+                    liked: &self.liked,
+                    sort: playlist.pages.sort,
+                    is_active,
+                    theme: &self.theme,
+                }
+                .render(area, buf);
             }
             _ => { /* Do nothing */ }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::SignIn;
+    use ratatui::widgets::ListItem;
+
+    /// Draws each popup and overlay over the progress gauge, at the smallest
+    /// size the layout accepts and with more playlists than fit on screen.
+    #[tokio::test]
+    async fn overlays_stay_on_screen() {
+        let mut app = App::new(true, SignIn::Headless).await;
+        app.playlist_picker.list = (0..40)
+            .map(|i| ListItem::new(format!("Playlist {i}")))
+            .collect();
+        app.playlist_picker.list_state.select(Some(39));
+        app.progress = Some(Progress {
+            label: "Merging".to_string(),
+            done: 1,
+            total: 2,
+        });
+        let (width, height) = panes::MIN_SIZE;
+        for area in [Rect::new(0, 0, width, height), Rect::new(0, 0, 80, 24)] {
+            for block in [
+                ActiveBlock::Popup,
+                ActiveBlock::PlaylistPopup,
+                ActiveBlock::PlaylistPicker,
+                ActiveBlock::Help,
+                ActiveBlock::Palette,
+            ] {
+                app.route.active_block = block;
+                let mut buf = Buffer::empty(area);
+                (&app).render(area, &mut buf);
+                if let Some(popup) = app.open_popup() {
+                    let main = app.areas(area).main;
+                    let popup = popup_area(popup, main);
+                    assert_eq!(main.union(popup), main, "{block:?} at {area:?}");
+                }
+            }
+        }
+    }
+
+    /// The bar shows the polled playback, and gives its rows back to the
+    /// lists on short terminals.
+    #[tokio::test]
+    async fn now_playing_bar() {
+        let mut app = App::new(true, SignIn::Headless).await;
+        app.playback = Some(Status {
+            playing: false,
+            title: Some("So What".to_string()),
+            artists: vec!["Miles Davis".to_string()],
+            progress_ms: 83_000,
+            duration_ms: 562_000,
+            ..Status::new(None)
+        });
+        let area = Rect::new(0, 0, 80, 24);
+        let bar = app.areas(area).now_playing;
+        assert_eq!(bar, Rect::new(0, 21, 80, 3));
+        let mut buf = Buffer::empty(area);
+        (&app).render(area, &mut buf);
+        let row: String = (bar.left()..bar.right())
+            .map(|x| buf[(x, bar.y + 1)].symbol())
+            .collect();
+        assert!(row.contains("Miles Davis - So What [1:23/9:22]"), "{row}");
+
+        let (width, height) = panes::MIN_SIZE;
+        assert!(
+            app.areas(Rect::new(0, 0, width, height))
+                .now_playing
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use crate::{
+    auth::Spotify,
+    config::Config,
+    event::{AppEvent, Event},
+    ipc::Request,
+    status::Status,
+};
+use color_eyre::eyre::{WrapErr, eyre};
+use rand::{Rng, distributions::Alphanumeric};
+use rouille::Response;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use spotify_rs::model::search::Item;
+use std::{
+    io::{Read, Write},
+    net::{IpAddr, SocketAddr, UdpSocket},
+    sync::Arc,
+};
+use tokio::{runtime::Handle, sync::mpsc, sync::watch};
+
+/// The remote page, with its styles and script inlined.
+const PAGE: &str = include_str!("web_remote.html");
+
+/// File in the config directory the pairing token is saved to.
+const TOKEN_FILE: &str = "web-remote-token";
+
+const TOKEN_LENGTH: usize = 32;
+
+/// Results returned per search.
+const SEARCH_LIMIT: u32 = 20;
+
+/// The `[web_remote]` config.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct WebRemoteConfig {
+    pub enabled: bool,
+    /// Address and port to listen on. The default listens on every network
+    /// interface, so other devices on the LAN can reach it.
+    pub address: String,
+}
+
+impl Default for WebRemoteConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "0.0.0.0:8484".to_string(),
+        }
+    }
+}
+
+/// A small web page and JSON API for controlling playback from a phone or
+/// another computer. Every API request must carry the pairing token, which
+/// is part of the URL logged at startup. The server stops when this is
+/// dropped.
+#[derive(Debug)]
+pub struct WebRemote {
+    playback: watch::Sender<Option<Status>>,
+    stop: std::sync::mpsc::Sender<()>,
+}
+
+/// What the request handler needs, shared by the server's threads.
+struct Shared {
+    token: String,
+    sender: mpsc::UnboundedSender<Event>,
+    playback: watch::Receiver<Option<Status>>,
+    spotify: Arc<Spotify>,
+    runtime: Handle,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Body {
+    uri: Option<String>,
+    percent: Option<u8>,
+}
+
+impl WebRemote {
+    /// Starts the server if the config enables it. Commands are passed on as
+    /// [`AppEvent::Remote`], like those sent to the control socket.
+    pub fn start(
+        config: &WebRemoteConfig,
+        spotify: Arc<Spotify>,
+        sender: mpsc::UnboundedSender<Event>,
+    ) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        match Self::serve(&config.address, spotify, sender) {
+            Ok(remote) => Some(remote),
+            Err(e) => {
+                log::error!("Failed to start the web remote: {e:#}");
+                None
+            }
+        }
+    }
+
+    fn serve(
+        address: &str,
+        spotify: Arc<Spotify>,
+        sender: mpsc::UnboundedSender<Event>,
+    ) -> color_eyre::Result<Self> {
+        let token = pairing_token()?;
+        let (playback, receiver) = watch::channel(None);
+        let shared = Shared {
+            token: token.clone(),
+            sender,
+            playback: receiver,
+            spotify,
+            runtime: Handle::current(),
+        };
+        let server = rouille::Server::new(address, move |request| handle(request, &shared))
+            .map_err(|e| eyre!("Failed to listen on {address}: {e}"))?;
+        log::info!(
+            "Web remote at http://{}/#token={token}",
+            reachable_address(server.server_addr())
+        );
+        let (_, stop) = server.stoppable();
+        Ok(Self { playback, stop })
+    }
+
+    /// Updates the playback state the page shows.
+    pub fn update(&self, status: &Status) {
+        self.playback.send_if_modified(|current| {
+            let changed = current.as_ref() != Some(status);
+            if changed {
+                *current = Some(status.clone());
+            }
+            changed
+        });
+    }
+}
+
+impl Drop for WebRemote {
+    fn drop(&mut self) {
+        let _ = self.stop.send(());
+    }
+}
+
+fn handle(request: &rouille::Request, shared: &Shared) -> Response {
+    if request.method() == "GET" && request.url() == "/" {
+        return Response::html(PAGE);
+    }
+    if !request.url().starts_with("/api/") {
+        return Response::empty_404();
+    }
+    if !shared.authorized(request) {
+        return error(401, "Missing or wrong pairing token");
+    }
+    let body = match read_body(request) {
+        Ok(body) => body,
+        Err(e) => return error(400, &e),
+    };
+    let command = match (request.method(), request.url().as_str()) {
+        ("GET", "/api/state") => {
+            return Response::json(&json!({ "playback": *shared.playback.borrow() }));
+        }
+        ("GET", "/api/search") => return shared.search(request),
+        ("POST", "/api/play") => Request::Play { uri: body.uri },
+        ("POST", "/api/pause") => Request::Pause,
+        ("POST", "/api/next") => Request::Next,
+        ("POST", "/api/prev") => Request::Prev,
+        ("POST", "/api/volume") => match body.percent {
+            Some(percent) => Request::Volume {
+                percent: percent.min(100),
+            },
+            None => return error(400, "Missing percent"),
+        },
+        ("POST", "/api/queue") => match body.uri {
+            Some(uri) => Request::Queue { uri },
+            None => return error(400, "Missing uri"),
+        },
+        _ => return Response::empty_404(),
+    };
+    if let Request::Play { uri: Some(uri) } | Request::Queue { uri } = &command
+        && !uri.starts_with("spotify:")
+    {
+        return error(400, &format!("Not a Spotify URI: {uri}"));
+    }
+    match shared.sender.send(Event::App(AppEvent::Remote(command))) {
+        Ok(()) => Response::json(&json!({ "ok": true })),
+        Err(_) => error(503, "spotui is quitting"),
+    }
+}
+
+impl Shared {
+    /// Whether `request` carries the pairing token, as a bearer token or in
+    /// the `token` query parameter.
+    fn authorized(&self, request: &rouille::Request) -> bool {
+        let token = request
+            .header("Authorization")
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .map(str::to_string)
+            .or_else(|| request.get_param("token"));
+        token.is_some_and(|token| same(token.as_bytes(), self.token.as_bytes()))
+    }
+
+    /// Answers `GET /api/search?q=…&type=track|artist|playlist`.
+    fn search(&self, request: &rouille::Request) -> Response {
+        let Some(query) = request.get_param("q").filter(|q| !q.trim().is_empty()) else {
+            return error(400, "Missing q");
+        };
+        let kind = request.get_param("type");
+        let item = match kind.as_deref() {
+            None | Some("track") => Item::Track,
+            Some("artist") => Item::Artist,
+            Some("playlist") => Item::Playlist,
+            Some(kind) => return error(400, &format!("Unknown type {kind}")),
+        };
+        let results = self.runtime.block_on(
+            spotify_rs::search(query, &[item])
+                .limit(SEARCH_LIMIT)
+                .get(&self.spotify),
+        );
+        let results = match results {
+            Ok(results) => results,
+            Err(e) => {
+                log::error!("Web remote search failed: {e}");
+                return error(502, &format!("Search failed: {e}"));
+            }
+        };
+        let tracks = results.tracks.map(|page| page.items).unwrap_or_default();
+        let artists = results.artists.map(|page| page.items).unwrap_or_default();
+        let playlists = results.playlists.map(|page| page.items).unwrap_or_default();
+        let results: Vec<Value> = tracks
+            .into_iter()
+            .flatten()
+            .map(|track| {
+                let artists: Vec<&str> = track.artists.iter().map(|a| a.name.as_str()).collect();
+                result(&track.name, &artists.join(", "), &track.uri, true)
+            })
+            .chain(
+                artists
+                    .into_iter()
+                    .flatten()
+                    .map(|artist| result(&artist.name, "Artist", &artist.uri, false)),
+            )
+            .chain(playlists.into_iter().flatten().map(|playlist| {
+                let owner = playlist.owner.display_name.as_deref().unwrap_or_default();
+                result(&playlist.name, owner, &playlist.uri, false)
+            }))
+            .collect();
+        Response::json(&json!({ "results": results }))
+    }
+}
+
+fn result(name: &str, detail: &str, uri: &str, queueable: bool) -> Value {
+    json!({ "name": name, "detail": detail, "uri": uri, "queueable": queueable })
+}
+
+fn error(status: u16, message: &str) -> Response {
+    Response::json(&json!({ "ok": false, "error": message })).with_status_code(status)
+}
+
+/// The JSON body of a `POST`, empty for commands without arguments.
+fn read_body(request: &rouille::Request) -> Result<Body, String> {
+    let mut data = String::new();
+    if let Some(mut reader) = request.data() {
+        reader
+            .read_to_string(&mut data)
+            .map_err(|e| format!("Failed to read the request: {e}"))?;
+    }
+    if data.trim().is_empty() {
+        return Ok(Body::default());
+    }
+    serde_json::from_str(&data).map_err(|e| format!("Invalid request: {e}"))
+}
+
+/// Compares in constant time, so the token can't be guessed byte by byte.
+fn same(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// The token saved by an earlier run, or a new one. Deleting the file
+/// unpairs every device.
+fn pairing_token() -> color_eyre::Result<String> {
+    let dir = Config::dir()?;
+    let path = dir.join(TOKEN_FILE);
+    if let Ok(mut file) = std::fs::File::open(&path) {
+        let mut token = String::new();
+        file.read_to_string(&mut token)
+            .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+        let token = token.trim();
+        if !token.is_empty() {
+            return Ok(token.to_string());
+        }
+    }
+    let token: String = rand::thread_rng()
+        .sample_iter(Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect();
+    std::fs::create_dir_all(&dir)?;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // Anyone with the token can control playback.
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(&path)
+        .and_then(|mut file| file.write_all(token.as_bytes()))
+        .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+    Ok(token)
+}
+
+/// `address` with the machine's LAN address in place of an unspecified one,
+/// for a URL that works from other devices.
+fn reachable_address(address: SocketAddr) -> SocketAddr {
+    if !address.ip().is_unspecified() {
+        return address;
+    }
+    // Connecting a UDP socket sends nothing, but picks the interface that
+    // routes to the internet.
+    let ip = UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| {
+            socket.connect("8.8.8.8:80")?;
+            socket.local_addr()
+        })
+        .map_or(IpAddr::from([127, 0, 0, 1]), |local| local.ip());
+    SocketAddr::new(ip, address.port())
+}
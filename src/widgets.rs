@@ -1,6 +1,6 @@
+pub mod filter;
+pub mod help;
 pub mod list;
 pub mod nav_list;
-pub mod playlist;
-pub mod top_artists;
-pub mod top_tracks;
-pub mod user_playlists;
+pub mod palette;
+pub mod table;
@@ -0,0 +1,288 @@
+use crate::{command::PaletteEntry, keymap::Binding};
+use ratatui::{
+    style::Style,
+    text::{Line, Span},
+};
+use spotify_rs::model::{
+    PlayableItem,
+    artist::Artist,
+    playlist::{PlaylistItem, SimplifiedPlaylist},
+    track::Track,
+};
+use std::collections::BTreeMap;
+
+/// Text a list row can be filtered on. The first field is the one shown in the list.
+pub trait Filterable {
+    fn filter_fields(&self) -> Vec<String>;
+}
+
+impl Filterable for Track {
+    fn filter_fields(&self) -> Vec<String> {
+        let artists: Vec<&str> = self.artists.iter().map(|a| a.name.as_str()).collect();
+        vec![
+            self.name.clone(),
+            artists.join(", "),
+            self.album.name.clone(),
+        ]
+    }
+}
+
+impl Filterable for PlaylistItem {
+    fn filter_fields(&self) -> Vec<String> {
+        match &self.track {
+            PlayableItem::Track(track) => track.filter_fields(),
+            PlayableItem::Episode(episode) => vec![episode.name.clone(), episode.show.name.clone()],
+        }
+    }
+}
+
+impl Filterable for Artist {
+    fn filter_fields(&self) -> Vec<String> {
+        vec![self.name.clone(), self.genres.join(", ")]
+    }
+}
+
+impl Filterable for SimplifiedPlaylist {
+    fn filter_fields(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.owner.display_name.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+impl Filterable for Binding {
+    fn filter_fields(&self) -> Vec<String> {
+        vec![
+            self.action.description().to_string(),
+            self.keys.join(", "),
+            self.action.name().to_string(),
+        ]
+    }
+}
+
+impl Filterable for PaletteEntry {
+    fn filter_fields(&self) -> Vec<String> {
+        vec![self.text.clone(), self.detail.clone()]
+    }
+}
+
+/// Incremental `/` filter over a list. Rows keep their index in the unfiltered
+/// list, so the cursor and selection always refer to the underlying item.
+#[derive(Debug, Clone, Default)]
+pub struct ListFilter {
+    pub query: String,
+    pub editing: bool,
+    /// Matched character positions per field, keyed by row index.
+    /// `None` while no filter is applied.
+    pub matches: Option<BTreeMap<usize, Vec<Vec<usize>>>>,
+}
+
+impl ListFilter {
+    pub fn is_active(&self) -> bool {
+        self.matches.is_some()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn is_visible(&self, index: usize) -> bool {
+        self.matches.as_ref().is_none_or(|m| m.contains_key(&index))
+    }
+
+    /// Matched character positions of `field` in row `index`.
+    pub fn highlights(&self, index: usize, field: usize) -> &[usize] {
+        self.matches
+            .as_ref()
+            .and_then(|m| m.get(&index))
+            .and_then(|fields| fields.get(field))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Recomputes the matching rows. Every whitespace-separated term of the
+    /// query has to fuzzy match at least one field of a row.
+    pub fn apply<T: Filterable>(&mut self, items: &[Option<T>]) {
+        let terms: Vec<&str> = self.query.split_whitespace().collect();
+        if terms.is_empty() {
+            self.matches = None;
+            return;
+        }
+        let mut matches = BTreeMap::new();
+        'rows: for (i, item) in items.iter().enumerate() {
+            let Some(item) = item else { continue };
+            let fields = item.filter_fields();
+            let mut positions = vec![Vec::new(); fields.len()];
+            for term in &terms {
+                let Some((field, found)) = fields
+                    .iter()
+                    .enumerate()
+                    .find_map(|(f, text)| fuzzy_match(term, text).map(|p| (f, p)))
+                else {
+                    continue 'rows;
+                };
+                positions[field].extend(found);
+            }
+            matches.insert(i, positions);
+        }
+        self.matches = Some(matches);
+    }
+
+    /// The visible row after (or before) `cursor` in a list of `len` rows,
+    /// staying put at either end.
+    pub fn step(&self, cursor: Option<usize>, len: usize, forward: bool) -> Option<usize> {
+        let visible = |i: &usize| self.is_visible(*i);
+        let next = match (cursor, forward) {
+            (None, _) => (0..len).find(visible),
+            (Some(c), true) => (c + 1..len).find(visible),
+            (Some(c), false) => (0..c).rev().find(visible),
+        };
+        next.or(cursor.filter(|&c| c < len))
+    }
+
+    /// `cursor` if it is visible, otherwise the first visible row.
+    pub fn nearest(&self, cursor: Option<usize>, len: usize) -> Option<usize> {
+        cursor
+            .filter(|&c| c < len && self.is_visible(c))
+            .or_else(|| (0..len).find(|&i| self.is_visible(i)))
+    }
+
+    /// Title suffix describing the filter, empty when there is none.
+    pub fn describe(&self) -> String {
+        match (&self.matches, self.editing) {
+            (_, true) => format!(" /{}_", self.query),
+            (Some(matches), false) => format!(" /{} ({})", self.query, matches.len()),
+            (None, false) => String::new(),
+        }
+    }
+}
+
+/// Case-insensitive fuzzy match of `pattern` against `text`, returning the
+/// matched character positions. A contiguous match is preferred over a
+/// scattered subsequence.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<Vec<usize>> {
+    let lower = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let pattern: Vec<char> = pattern.chars().map(lower).collect();
+    let text: Vec<char> = text.chars().map(lower).collect();
+    if pattern.is_empty() {
+        return Some(Vec::new());
+    }
+    if let Some(start) = text
+        .windows(pattern.len())
+        .position(|window| window == pattern.as_slice())
+    {
+        return Some((start..start + pattern.len()).collect());
+    }
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut chars = pattern.iter().peekable();
+    for (i, c) in text.iter().enumerate() {
+        if chars.peek() == Some(&c) {
+            positions.push(i);
+            chars.next();
+        }
+    }
+    chars.peek().is_none().then_some(positions)
+}
+
+/// `text` with the characters at `positions` drawn in `style`.
+pub fn highlight(text: &str, positions: &[usize], style: Style) -> Line<'static> {
+    if positions.is_empty() {
+        return Line::from(text.to_string());
+    }
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in text.chars().enumerate() {
+        let matched = positions.contains(&i);
+        if matched != run_matched && !run.is_empty() {
+            let span = std::mem::take(&mut run);
+            spans.push(if run_matched {
+                Span::styled(span, style)
+            } else {
+                Span::raw(span)
+            });
+        }
+        run_matched = matched;
+        run.push(c);
+    }
+    spans.push(if run_matched {
+        Span::styled(run, style)
+    } else {
+        Span::raw(run)
+    });
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fields(&'static [&'static str]);
+
+    impl Filterable for Fields {
+        fn filter_fields(&self) -> Vec<String> {
+            self.0.iter().map(|field| field.to_string()).collect()
+        }
+    }
+
+    fn filtered(query: &str) -> ListFilter {
+        let items = [
+            Some(Fields(&["So What", "Miles Davis"])),
+            None,
+            Some(Fields(&["Blue in Green", "Miles Davis"])),
+            Some(Fields(&["Naima", "John Coltrane"])),
+        ];
+        let mut filter = ListFilter {
+            query: query.to_string(),
+            ..ListFilter::default()
+        };
+        filter.apply(&items);
+        filter
+    }
+
+    #[test]
+    fn fuzzy_match_prefers_contiguous_matches() {
+        assert_eq!(fuzzy_match("een", "Blue in Green"), Some(vec![10, 11, 12]));
+        assert_eq!(fuzzy_match("BIG", "Blue in Green"), Some(vec![0, 5, 8]));
+        assert_eq!(fuzzy_match("gb", "Blue in Green"), None);
+        assert_eq!(fuzzy_match("", "anything"), Some(vec![]));
+    }
+
+    #[test]
+    fn matches_keep_the_unfiltered_indices() {
+        let filter = filtered("miles");
+        let rows: Vec<usize> = filter.matches.as_ref().unwrap().keys().copied().collect();
+        assert_eq!(rows, [0, 2]);
+        assert!(!filter.is_visible(1));
+        assert!(!filter.is_visible(3));
+        assert!(filter.highlights(2, 0).is_empty());
+        assert_eq!(filter.highlights(2, 1), [0, 1, 2, 3, 4]);
+        assert_eq!(filter.step(Some(0), 4, true), Some(2));
+        assert_eq!(filter.step(Some(2), 4, true), Some(2));
+        assert_eq!(filter.nearest(Some(3), 4), Some(0));
+    }
+
+    #[test]
+    fn every_term_has_to_match_a_field() {
+        let filter = filtered("green davis");
+        assert_eq!(filter.matches.as_ref().unwrap().len(), 1);
+        assert_eq!(filter.highlights(2, 0), [8, 9, 10, 11, 12]);
+        assert_eq!(filter.highlights(2, 1), [6, 7, 8, 9, 10]);
+        assert!(filtered("green coltrane").matches.unwrap().is_empty());
+        assert!(!filtered("  ").is_active());
+    }
+
+    #[test]
+    fn highlight_styles_matched_runs() {
+        let style = Style::new().add_modifier(ratatui::style::Modifier::BOLD);
+        let line = highlight("Naima", &[0, 1, 4], style);
+        assert_eq!(
+            line.spans,
+            [
+                Span::styled("Na", style),
+                Span::raw("im"),
+                Span::styled("a", style)
+            ]
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{app::HelpView, theme::Theme, widgets::filter::highlight};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    text::Line,
+    widgets::{
+        Block, BorderType, Borders, Clear, HighlightSpacing, Row, StatefulWidget, Table,
+        TableState, Widget,
+    },
+};
+use unicode_width::UnicodeWidthStr;
+
+/// The `?` overlay, drawn centered over `area`.
+pub struct HelpWidget<'a> {
+    pub help: &'a HelpView,
+    pub theme: &'a Theme,
+}
+
+impl Widget for HelpWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let help = self.help;
+        let popup = Rect {
+            x: area.x + area.width / 8,
+            y: area.y + area.height / 8,
+            width: area.width - area.width / 4,
+            height: area.height - area.height / 4,
+        };
+        Clear.render(popup, buf);
+
+        let visible: Vec<usize> = (0..help.rows.len())
+            .filter(|&i| help.filter.is_visible(i))
+            .collect();
+        let bindings = || help.rows.iter().flatten();
+        let keys_width = bindings()
+            .map(|b| b.keys.join(", ").width())
+            .chain(["Keys".len()])
+            .max()
+            .unwrap_or_default();
+        let scope_width = bindings()
+            .map(|b| b.block.map_or("global", |b| b.config_name()).len())
+            .chain(["Where".len()])
+            .max()
+            .unwrap_or_default();
+        let rows = visible.iter().filter_map(|&i| {
+            let binding = help.rows[i].as_ref()?;
+            Some(Row::new([
+                highlight(
+                    &binding.keys.join(", "),
+                    help.filter.highlights(i, 1),
+                    self.theme.filter_match,
+                ),
+                highlight(
+                    binding.action.description(),
+                    help.filter.highlights(i, 0),
+                    self.theme.filter_match,
+                ),
+                Line::from(binding.block.map_or("global", |b| b.config_name())),
+            ]))
+        });
+
+        let title = format!(
+            "Help: {}{}",
+            help.context.config_name(),
+            help.filter.describe()
+        );
+        let mut state = TableState::default().with_selected(
+            help.list_state
+                .selected()
+                .and_then(|c| visible.iter().position(|&r| r == c)),
+        );
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(keys_width as u16),
+                Constraint::Fill(1),
+                Constraint::Length(scope_width as u16),
+            ],
+        )
+        .header(Row::new(["Keys", "Action", "Where"]).style(self.theme.header))
+        .block(
+            Block::new()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Double)
+                .border_style(self.theme.border_active)
+                .title(title)
+                .title_style(self.theme.title),
+        )
+        .row_highlight_style(self.theme.highlight)
+        .highlight_spacing(HighlightSpacing::Always)
+        .highlight_symbol(">> ");
+        StatefulWidget::render(table, popup, buf, &mut state);
+    }
+}
@@ -1,15 +1,103 @@
+use crate::{theme::Theme, widgets::filter::ListFilter};
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
-    style::{Color, Modifier},
     widgets::{Block, BorderType, Borders, List, ListItem, ListState, StatefulWidget, Widget},
 };
+use std::collections::BTreeSet;
+
+/// The first row ratatui draws of a list of `rows` rows with room for
+/// `height`, last drawn from `offset`: the nearest that keeps `cursor` in view.
+pub fn scrolled_offset(offset: usize, rows: usize, cursor: Option<usize>, height: usize) -> usize {
+    let mut start = offset.min(rows.saturating_sub(1));
+    if let Some(cursor) = cursor {
+        if cursor >= start + height {
+            start = cursor + 1 - height;
+        }
+        start = start.min(cursor);
+    }
+    start
+}
+
+/// Rows marked for bulk actions. `anchor` is set while a visual range is open,
+/// in which case every row between the anchor and the cursor counts as marked.
+#[derive(Debug, Clone, Default)]
+pub struct MultiSelect {
+    pub marked: BTreeSet<usize>,
+    pub anchor: Option<usize>,
+}
+
+impl MultiSelect {
+    pub fn toggle(&mut self, index: usize) {
+        if !self.marked.remove(&index) {
+            self.marked.insert(index);
+        }
+    }
+
+    /// Opens a visual range at `cursor`, or commits the visible rows of the open range.
+    pub fn toggle_range(&mut self, cursor: usize, visible: impl Fn(usize) -> bool) {
+        match self.anchor.take() {
+            Some(anchor) => self
+                .marked
+                .extend((anchor.min(cursor)..=anchor.max(cursor)).filter(|&i| visible(i))),
+            None => self.anchor = Some(cursor),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.marked.clear();
+        self.anchor = None;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.marked.is_empty() && self.anchor.is_none()
+    }
+
+    pub fn contains(&self, index: usize, cursor: Option<usize>) -> bool {
+        if self.marked.contains(&index) {
+            return true;
+        }
+        match (self.anchor, cursor) {
+            (Some(anchor), Some(cursor)) => {
+                (anchor.min(cursor)..=anchor.max(cursor)).contains(&index)
+            }
+            _ => false,
+        }
+    }
+
+    /// Indices an action should apply to: the marked rows plus the visible rows
+    /// of any open range, or just the cursor row when nothing is marked.
+    pub fn indices(&self, cursor: Option<usize>, visible: impl Fn(usize) -> bool) -> Vec<usize> {
+        let mut indices = self.marked.clone();
+        if let (Some(anchor), Some(cursor)) = (self.anchor, cursor) {
+            indices.extend((anchor.min(cursor)..=anchor.max(cursor)).filter(|&i| visible(i)));
+        }
+        if indices.is_empty() {
+            indices.extend(cursor);
+        }
+        indices.into_iter().collect()
+    }
+}
+
+/// `title` followed by the selection and filter state of the list.
+pub fn decorated_title(title: &str, selection: &MultiSelect, filter: &ListFilter) -> String {
+    let marked = selection.indices(None, |_| true).len();
+    let title = match (selection.anchor.is_some(), marked) {
+        (true, _) => format!("{title} [VISUAL]"),
+        (false, 0) => title.to_string(),
+        (false, n) => format!("{title} [{n} selected]"),
+    };
+    title + &filter.describe()
+}
 
 pub struct ListWidget<'a> {
     pub title: &'a str,
     pub list_items: Vec<ListItem<'a>>,
     pub list_state: ListState,
+    pub selection: MultiSelect,
+    pub filter: ListFilter,
     pub is_active: bool,
+    pub theme: &'a Theme,
 }
 
 impl Widget for &mut ListWidget<'_> {
@@ -19,22 +107,54 @@ impl Widget for &mut ListWidget<'_> {
         } else {
             BorderType::Plain
         };
-        let items: Vec<ListItem> = self.list_items.iter().cloned().collect();
+        let cursor = self.list_state.selected();
+        let rows: Vec<usize> = (0..self.list_items.len())
+            .filter(|&i| self.filter.is_visible(i))
+            .collect();
+        let items: Vec<ListItem> = rows
+            .iter()
+            .map(|&i| {
+                let item = self.list_items[i].clone();
+                if self.selection.contains(i, cursor) {
+                    item.style(self.theme.marked)
+                } else {
+                    item
+                }
+            })
+            .collect();
+        let title = decorated_title(self.title, &self.selection, &self.filter);
+        // The cursor refers to the unfiltered list; render it at its visible row.
+        let mut list_state = ListState::default()
+            .with_offset(self.list_state.offset())
+            .with_selected(cursor.and_then(|c| rows.iter().position(|&r| r == c)));
         let list = List::new(items)
             .block(
                 Block::new()
                     .borders(Borders::ALL)
                     .border_type(border_type)
-                    .title(self.title),
-            )
-            .highlight_style(
-                ratatui::style::Style::default()
-                    .fg(Color::Yellow)
-                    .bg(Color::Green)
-                    .add_modifier(Modifier::BOLD),
+                    .border_style(self.theme.border(self.is_active))
+                    .title(title)
+                    .title_style(self.theme.title),
             )
+            .highlight_style(self.theme.highlight)
             .highlight_symbol(">> ");
 
-        StatefulWidget::render(list, area, buf, &mut self.list_state);
+        StatefulWidget::render(list, area, buf, &mut list_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrolls_only_as_far_as_the_cursor_needs() {
+        // Moving down past the bottom scrolls the cursor onto the last row.
+        assert_eq!(scrolled_offset(0, 100, Some(25), 10), 16);
+        // Moving back up keeps the offset until the cursor passes the top.
+        assert_eq!(scrolled_offset(16, 100, Some(20), 10), 16);
+        assert_eq!(scrolled_offset(16, 100, Some(12), 10), 12);
+        // An offset beyond the list, e.g. after filtering, is pulled back.
+        assert_eq!(scrolled_offset(50, 5, None, 10), 4);
     }
 }
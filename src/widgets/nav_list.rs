@@ -1,23 +1,33 @@
-use crate::widgets::list::ListWidget;
+use crate::{
+    theme::Theme,
+    widgets::{
+        filter::ListFilter,
+        list::{ListWidget, MultiSelect},
+    },
+};
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
     widgets::{ListItem, Widget},
 };
 
-pub struct NavList {
+pub struct NavList<'a> {
     pub title: String,
     pub list: Vec<ListItem<'static>>,
     pub list_state: ratatui::widgets::ListState,
     pub is_active: bool,
+    pub theme: &'a Theme,
 }
-impl Widget for NavList {
+impl Widget for NavList<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let mut nav_list = ListWidget {
             title: &self.title,
             list_items: self.list,
             list_state: self.list_state,
+            selection: MultiSelect::default(),
+            filter: ListFilter::default(),
             is_active: self.is_active,
+            theme: self.theme,
         };
         nav_list.render(area, buf);
     }
@@ -0,0 +1,94 @@
+use crate::{app::PaletteView, theme::Theme, widgets::filter::highlight};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    text::Line,
+    widgets::{
+        Block, BorderType, Borders, Clear, HighlightSpacing, Paragraph, Row, StatefulWidget, Table,
+        TableState, Widget,
+    },
+};
+use unicode_width::UnicodeWidthStr;
+
+/// The `:` command palette, drawn near the top of `area`.
+pub struct PaletteWidget<'a> {
+    pub palette: &'a PaletteView,
+    pub theme: &'a Theme,
+}
+
+impl Widget for PaletteWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let palette = self.palette;
+        let visible: Vec<usize> = (0..palette.entries.len())
+            .filter(|&i| palette.filter.is_visible(i))
+            .collect();
+        // Input line, the rows and the borders.
+        let height = (visible.len() as u16 + 3)
+            .max(3)
+            .min(area.height.saturating_sub(2));
+        let popup = Rect {
+            x: area.x + area.width / 5,
+            y: area.y + 1,
+            width: area.width - area.width * 2 / 5,
+            height,
+        };
+        Clear.render(popup, buf);
+
+        let title = match palette.pending.and_then(|c| c.argument()) {
+            Some(argument) => argument.prompt(),
+            None => "Command Palette",
+        };
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double)
+            .border_style(self.theme.border_active)
+            .title(title)
+            .title_style(self.theme.title);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+        let [input, list] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(inner);
+        Paragraph::new(format!("> {}_", palette.filter.query)).render(input, buf);
+
+        let text_width = visible
+            .iter()
+            .filter_map(|&i| palette.entries[i].as_ref())
+            .map(|entry| entry.text.width())
+            .max()
+            .unwrap_or_default();
+        let rows = visible.iter().filter_map(|&i| {
+            let entry = palette.entries[i].as_ref()?;
+            Some(Row::new([
+                highlight(
+                    &entry.text,
+                    palette.filter.highlights(i, 0),
+                    self.theme.filter_match,
+                ),
+                highlight(
+                    &entry.detail,
+                    palette.filter.highlights(i, 1),
+                    self.theme.filter_match,
+                ),
+            ]))
+        });
+        let mut state = TableState::default().with_selected(
+            palette
+                .list_state
+                .selected()
+                .and_then(|c| visible.iter().position(|&r| r == c)),
+        );
+        let table = Table::new(
+            rows,
+            [Constraint::Length(text_width as u16), Constraint::Fill(1)],
+        )
+        .column_spacing(2)
+        .row_highlight_style(self.theme.highlight)
+        .highlight_spacing(HighlightSpacing::Always)
+        .highlight_symbol(">> ");
+        if visible.is_empty() && palette.pending.is_none() {
+            Line::from("No matching commands").render(list, buf);
+        } else {
+            StatefulWidget::render(table, list, buf, &mut state);
+        }
+    }
+}
@@ -0,0 +1,672 @@
+use crate::{
+    theme::Theme,
+    widgets::{
+        filter::{Filterable, ListFilter, highlight},
+        list::{MultiSelect, decorated_title},
+    },
+};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    text::Line,
+    widgets::{
+        Block, BorderType, Borders, Cell, HighlightSpacing, ListState, Row, StatefulWidget, Table,
+        TableState, Widget,
+    },
+};
+use serde::Deserialize;
+use spotify_rs::model::{
+    Image, PlayableItem,
+    artist::Artist,
+    playlist::{PlaylistItem, SimplifiedPlaylist},
+    track::Track,
+};
+use std::collections::HashMap;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const HIGHLIGHT_SYMBOL: &str = ">> ";
+const COLUMN_SPACING: u16 = 1;
+
+pub const PLAYLIST_COLUMNS: [Column; 4] =
+    [Column::Index, Column::Title, Column::Owner, Column::Tracks];
+pub const ARTIST_COLUMNS: [Column; 4] = [
+    Column::Index,
+    Column::Title,
+    Column::Genres,
+    Column::Popularity,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Column {
+    #[serde(rename = "#")]
+    Index,
+    Title,
+    Artists,
+    Album,
+    Duration,
+    #[serde(alias = "added-at")]
+    Added,
+    Popularity,
+    Liked,
+    Owner,
+    Tracks,
+    Genres,
+}
+
+impl Column {
+    pub const DEFAULT_TRACK_COLUMNS: [Column; 6] = [
+        Column::Index,
+        Column::Title,
+        Column::Artists,
+        Column::Album,
+        Column::Duration,
+        Column::Added,
+    ];
+
+    /// Name of the column in the config, also used as the CSV export header.
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Index => "#",
+            Column::Title => "title",
+            Column::Artists => "artists",
+            Column::Album => "album",
+            Column::Duration => "duration",
+            Column::Added => "added",
+            Column::Popularity => "popularity",
+            Column::Liked => "liked",
+            Column::Owner => "owner",
+            Column::Tracks => "tracks",
+            Column::Genres => "genres",
+        }
+    }
+
+    pub fn header(self) -> &'static str {
+        match self {
+            Column::Index => "#",
+            Column::Title => "Title",
+            Column::Artists => "Artists",
+            Column::Album => "Album",
+            Column::Duration => "Time",
+            Column::Added => "Added",
+            Column::Popularity => "Pop",
+            Column::Liked => "♥",
+            Column::Owner => "Owner",
+            Column::Tracks => "Tracks",
+            Column::Genres => "Genres",
+        }
+    }
+
+    /// Width the column gets (fixed columns) or needs at least (flexible columns).
+    fn min_width(self, rows: usize) -> u16 {
+        match self {
+            Column::Index => rows.max(1).to_string().len() as u16,
+            Column::Title => 12,
+            Column::Artists | Column::Album | Column::Owner | Column::Genres => 8,
+            Column::Duration => 5,
+            Column::Added => 10,
+            Column::Popularity => 3,
+            Column::Liked => 1,
+            Column::Tracks => 6,
+        }
+    }
+
+    /// Share of the leftover width for flexible columns, `None` for fixed ones.
+    fn fill(self) -> Option<u16> {
+        match self {
+            Column::Title => Some(3),
+            Column::Artists | Column::Album | Column::Genres => Some(2),
+            Column::Owner => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Columns with a lower value are dropped first when the table is too narrow.
+    fn priority(self) -> u8 {
+        match self {
+            Column::Title => u8::MAX,
+            Column::Artists | Column::Owner => 6,
+            Column::Index => 5,
+            Column::Duration | Column::Tracks => 4,
+            Column::Liked => 3,
+            Column::Album | Column::Genres => 2,
+            Column::Added => 1,
+            Column::Popularity => 0,
+        }
+    }
+}
+
+/// Column a list is sorted by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortOrder {
+    pub column: Column,
+    pub descending: bool,
+}
+
+/// Value a row is sorted by. Rows without a value sort first.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SortValue {
+    Missing,
+    Number(u64),
+    Text(String),
+}
+
+impl SortValue {
+    fn text(text: &str) -> Self {
+        SortValue::Text(text.to_lowercase())
+    }
+}
+
+/// A row type that can be shown in a [`TableWidget`].
+pub trait TableRow: Filterable {
+    /// Text of `column`, `None` if the row has no value for it.
+    fn cell(&self, column: Column, liked: &HashMap<String, bool>) -> Option<String>;
+
+    /// Value of `column` used for sorting.
+    fn sort_value(&self, column: Column) -> SortValue;
+
+    /// Index into [`Filterable::filter_fields`] shown by `column`, for match highlighting.
+    fn filter_field(column: Column) -> Option<usize>;
+
+    /// URL of the row's cover art or picture.
+    fn image_url(&self) -> Option<&str>;
+
+    /// Spotify ID of the row's item, used to find it again in a refetched list.
+    fn id(&self) -> &str;
+}
+
+/// The smallest of `images` at least 300 pixels wide, which Spotify lists
+/// largest first, or the largest if they're all smaller.
+fn medium_image(images: &[Image]) -> Option<&str> {
+    images
+        .iter()
+        .rev()
+        .find(|image| image.width.is_some_and(|width| width >= 300))
+        .or(images.first())
+        .map(|image| image.url.as_str())
+}
+
+pub fn format_duration(ms: u32) -> String {
+    let secs = ms / 1000;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+fn track_cell(track: &Track, column: Column, liked: &HashMap<String, bool>) -> Option<String> {
+    match column {
+        Column::Title => Some(track.name.clone()),
+        Column::Artists => Some(
+            track
+                .artists
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        Column::Album => Some(track.album.name.clone()),
+        Column::Duration => Some(format_duration(track.duration_ms)),
+        Column::Popularity => Some(track.popularity.to_string()),
+        Column::Liked => liked
+            .get(&track.id)
+            .map(|&liked| if liked { "♥" } else { "" }.to_string()),
+        _ => None,
+    }
+}
+
+fn track_sort_value(track: &Track, column: Column) -> SortValue {
+    match column {
+        Column::Title => SortValue::text(&track.name),
+        Column::Artists => track
+            .artists
+            .first()
+            .map_or(SortValue::Missing, |a| SortValue::text(&a.name)),
+        Column::Album => SortValue::text(&track.album.name),
+        Column::Duration => SortValue::Number(u64::from(track.duration_ms)),
+        Column::Popularity => SortValue::Number(u64::from(track.popularity)),
+        _ => SortValue::Missing,
+    }
+}
+
+fn track_filter_field(column: Column) -> Option<usize> {
+    match column {
+        Column::Title => Some(0),
+        Column::Artists => Some(1),
+        Column::Album => Some(2),
+        _ => None,
+    }
+}
+
+impl TableRow for Track {
+    fn cell(&self, column: Column, liked: &HashMap<String, bool>) -> Option<String> {
+        track_cell(self, column, liked)
+    }
+
+    fn sort_value(&self, column: Column) -> SortValue {
+        track_sort_value(self, column)
+    }
+
+    fn filter_field(column: Column) -> Option<usize> {
+        track_filter_field(column)
+    }
+
+    fn image_url(&self) -> Option<&str> {
+        medium_image(&self.album.images)
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl TableRow for PlaylistItem {
+    fn cell(&self, column: Column, liked: &HashMap<String, bool>) -> Option<String> {
+        if column == Column::Added {
+            return self.added_at.map(|at| at.format("%Y-%m-%d").to_string());
+        }
+        match &self.track {
+            PlayableItem::Track(track) => track_cell(track, column, liked),
+            PlayableItem::Episode(episode) => match column {
+                Column::Title => Some(episode.name.clone()),
+                Column::Artists => Some(episode.show.name.clone()),
+                Column::Duration => Some(format_duration(episode.duration_ms)),
+                _ => None,
+            },
+        }
+    }
+
+    fn sort_value(&self, column: Column) -> SortValue {
+        if column == Column::Added {
+            return self.added_at.map_or(SortValue::Missing, |at| {
+                SortValue::Number(at.timestamp() as u64)
+            });
+        }
+        match &self.track {
+            PlayableItem::Track(track) => track_sort_value(track, column),
+            PlayableItem::Episode(episode) => match column {
+                Column::Title => SortValue::text(&episode.name),
+                Column::Artists => SortValue::text(&episode.show.name),
+                Column::Duration => SortValue::Number(u64::from(episode.duration_ms)),
+                _ => SortValue::Missing,
+            },
+        }
+    }
+
+    fn filter_field(column: Column) -> Option<usize> {
+        track_filter_field(column)
+    }
+
+    fn image_url(&self) -> Option<&str> {
+        match &self.track {
+            PlayableItem::Track(track) => medium_image(&track.album.images),
+            PlayableItem::Episode(episode) => medium_image(&episode.images),
+        }
+    }
+
+    fn id(&self) -> &str {
+        match &self.track {
+            PlayableItem::Track(track) => &track.id,
+            PlayableItem::Episode(episode) => &episode.id,
+        }
+    }
+}
+
+impl TableRow for Artist {
+    fn cell(&self, column: Column, _liked: &HashMap<String, bool>) -> Option<String> {
+        match column {
+            Column::Title => Some(self.name.clone()),
+            Column::Genres => Some(self.genres.join(", ")),
+            Column::Popularity => Some(self.popularity.to_string()),
+            _ => None,
+        }
+    }
+
+    fn sort_value(&self, column: Column) -> SortValue {
+        match column {
+            Column::Title => SortValue::text(&self.name),
+            Column::Popularity => SortValue::Number(u64::from(self.popularity)),
+            _ => SortValue::Missing,
+        }
+    }
+
+    fn filter_field(column: Column) -> Option<usize> {
+        match column {
+            Column::Title => Some(0),
+            Column::Genres => Some(1),
+            _ => None,
+        }
+    }
+
+    fn image_url(&self) -> Option<&str> {
+        medium_image(&self.images)
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl TableRow for SimplifiedPlaylist {
+    fn cell(&self, column: Column, _liked: &HashMap<String, bool>) -> Option<String> {
+        match column {
+            Column::Title => Some(self.name.clone()),
+            Column::Owner => self.owner.display_name.clone(),
+            Column::Tracks => self.tracks.as_ref().map(|t| t.total.to_string()),
+            _ => None,
+        }
+    }
+
+    fn sort_value(&self, column: Column) -> SortValue {
+        match column {
+            Column::Title => SortValue::text(&self.name),
+            Column::Owner => self
+                .owner
+                .display_name
+                .as_deref()
+                .map_or(SortValue::Missing, SortValue::text),
+            Column::Tracks => self.tracks.as_ref().map_or(SortValue::Missing, |t| {
+                SortValue::Number(u64::from(t.total))
+            }),
+            _ => SortValue::Missing,
+        }
+    }
+
+    fn filter_field(column: Column) -> Option<usize> {
+        match column {
+            Column::Title => Some(0),
+            Column::Owner => Some(1),
+            _ => None,
+        }
+    }
+
+    fn image_url(&self) -> Option<&str> {
+        medium_image(&self.images)
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+/// The order `items` should be shown in when sorted by `sort`, as indices
+/// into `items`. Equal rows keep their relative order.
+pub fn sort_order<T: TableRow>(items: &[Option<T>], sort: SortOrder) -> Vec<usize> {
+    let values: Vec<SortValue> = items
+        .iter()
+        .map(|item| {
+            item.as_ref()
+                .map_or(SortValue::Missing, |item| item.sort_value(sort.column))
+        })
+        .collect();
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_by(|&a, &b| {
+        let ordering = values[a].cmp(&values[b]);
+        if sort.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+    order
+}
+
+/// Cuts `text` to `width` terminal cells, ending it with an ellipsis when it
+/// doesn't fit. Returns the text and the number of characters kept from the original.
+fn truncate(text: &str, width: usize) -> (String, usize) {
+    if text.width() <= width {
+        return (text.to_string(), text.chars().count());
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    let mut kept = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        out.push(c);
+        used += w;
+        kept += 1;
+    }
+    if width > 0 {
+        out.push('…');
+    }
+    (out, kept)
+}
+
+/// The columns that fit in `width`, dropping the least important ones first.
+fn fit_columns(columns: &[Column], rows: usize, width: u16) -> Vec<Column> {
+    let mut columns = columns.to_vec();
+    let needed = |columns: &[Column]| -> u16 {
+        columns.iter().map(|c| c.min_width(rows)).sum::<u16>()
+            + COLUMN_SPACING * columns.len().saturating_sub(1) as u16
+    };
+    while columns.len() > 1 && needed(&columns) > width {
+        let Some(drop) = columns
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, c)| c.priority())
+            .map(|(i, _)| i)
+        else {
+            break;
+        };
+        columns.remove(drop);
+    }
+    columns
+}
+
+/// A bordered table over one of the paged lists, with filtering, multi-select
+/// and columns that adapt to the available width.
+pub struct TableWidget<'a, T> {
+    pub title: String,
+    /// Title shown instead of the table when there are no items.
+    pub empty_title: &'a str,
+    pub items: &'a [Option<T>],
+    pub columns: &'a [Column],
+    pub list_state: &'a ListState,
+    pub selection: &'a MultiSelect,
+    pub filter: &'a ListFilter,
+    pub liked: &'a HashMap<String, bool>,
+    pub sort: Option<SortOrder>,
+    pub is_active: bool,
+    pub theme: &'a Theme,
+}
+
+impl<T: TableRow> Widget for TableWidget<'_, T> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let border_type = if self.is_active {
+            BorderType::Double
+        } else {
+            BorderType::Plain
+        };
+        if self.items.is_empty() {
+            Block::new()
+                .borders(Borders::ALL)
+                .border_type(border_type)
+                .border_style(self.theme.border(self.is_active))
+                .title(self.empty_title)
+                .title_style(self.theme.title)
+                .render(area, buf);
+            return;
+        }
+
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .border_type(border_type)
+            .border_style(self.theme.border(self.is_active))
+            .title(decorated_title(&self.title, self.selection, self.filter))
+            .title_style(self.theme.title);
+        let inner = block.inner(area);
+        let table_width = inner.width.saturating_sub(HIGHLIGHT_SYMBOL.len() as u16);
+        let columns = fit_columns(self.columns, self.items.len(), table_width);
+        let constraints: Vec<Constraint> = columns
+            .iter()
+            .map(|c| match c.fill() {
+                Some(weight) => Constraint::Fill(weight),
+                None => Constraint::Length(c.min_width(self.items.len())),
+            })
+            .collect();
+        let widths: Vec<u16> = Layout::horizontal(constraints)
+            .spacing(COLUMN_SPACING)
+            .split(Rect {
+                width: table_width,
+                ..inner
+            })
+            .iter()
+            .map(|r| r.width)
+            .collect();
+
+        let cursor = self.list_state.selected();
+        let visible: Vec<usize> = (0..self.items.len())
+            .filter(|&i| self.filter.is_visible(i))
+            .collect();
+        let rows = visible.iter().map(|&i| {
+            let cells = columns.iter().zip(&widths).map(|(&column, &width)| {
+                let text = match (column, &self.items[i]) {
+                    (Column::Index, _) => Some((i + 1).to_string()),
+                    (_, Some(item)) => item.cell(column, self.liked),
+                    (_, None) => None,
+                };
+                let (text, kept) = truncate(&text.unwrap_or_default(), usize::from(width));
+                let positions: Vec<usize> = T::filter_field(column)
+                    .map(|field| self.filter.highlights(i, field))
+                    .unwrap_or_default()
+                    .iter()
+                    .copied()
+                    .filter(|&p| p < kept)
+                    .collect();
+                let line: Line = highlight(&text, &positions, self.theme.filter_match);
+                Cell::from(if column == Column::Index {
+                    line.right_aligned()
+                } else {
+                    line
+                })
+            });
+            let row = Row::new(cells);
+            if self.selection.contains(i, cursor) {
+                row.style(self.theme.marked)
+            } else {
+                row
+            }
+        });
+        let header = Row::new(columns.iter().map(|&c| match self.sort {
+            Some(sort) if sort.column == c => {
+                format!("{} {}", c.header(), if sort.descending { "▼" } else { "▲" })
+            }
+            _ => c.header().to_string(),
+        }))
+        .style(self.theme.header);
+
+        // The cursor refers to the unfiltered list; render it at its visible row.
+        let mut state = TableState::default()
+            .with_offset(self.list_state.offset())
+            .with_selected(cursor.and_then(|c| visible.iter().position(|&r| r == c)));
+        let table = Table::new(rows, widths.iter().map(|&w| Constraint::Length(w)))
+            .header(header)
+            .block(block)
+            .column_spacing(COLUMN_SPACING)
+            .row_highlight_style(self.theme.highlight)
+            .highlight_symbol(HIGHLIGHT_SYMBOL)
+            .highlight_spacing(HighlightSpacing::Always);
+        StatefulWidget::render(table, area, buf, &mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A row with a name and an optional track count.
+    struct Item(&'static str, Option<u64>);
+
+    impl Filterable for Item {
+        fn filter_fields(&self) -> Vec<String> {
+            vec![self.0.to_string()]
+        }
+    }
+
+    impl TableRow for Item {
+        fn cell(&self, _: Column, _: &HashMap<String, bool>) -> Option<String> {
+            None
+        }
+
+        fn sort_value(&self, column: Column) -> SortValue {
+            match column {
+                Column::Title => SortValue::text(self.0),
+                Column::Tracks => self.1.map_or(SortValue::Missing, SortValue::Number),
+                _ => SortValue::Missing,
+            }
+        }
+
+        fn filter_field(_: Column) -> Option<usize> {
+            None
+        }
+
+        fn image_url(&self) -> Option<&str> {
+            None
+        }
+
+        fn id(&self) -> &str {
+            self.0
+        }
+    }
+
+    fn items() -> Vec<Option<Item>> {
+        vec![
+            Some(Item("b", Some(2))),
+            None,
+            Some(Item("a", None)),
+            Some(Item("C", Some(1))),
+            Some(Item("d", Some(2))),
+        ]
+    }
+
+    #[test]
+    fn sort_is_stable_with_missing_values_first() {
+        let sort = |column, descending| sort_order(&items(), SortOrder { column, descending });
+        assert_eq!(sort(Column::Tracks, false), [1, 2, 3, 0, 4]);
+        assert_eq!(sort(Column::Title, false), [1, 2, 0, 3, 4]);
+        // Descending reverses the values but not the order of ties.
+        assert_eq!(sort(Column::Tracks, true), [0, 4, 3, 1, 2]);
+        assert_eq!(sort(Column::Title, true), [4, 3, 0, 2, 1]);
+    }
+
+    #[test]
+    fn narrow_tables_drop_low_priority_columns() {
+        let fit = |width| fit_columns(&Column::DEFAULT_TRACK_COLUMNS, 100, width);
+        assert_eq!(fit(51), Column::DEFAULT_TRACK_COLUMNS);
+        assert_eq!(
+            fit(40),
+            [
+                Column::Index,
+                Column::Title,
+                Column::Artists,
+                Column::Album,
+                Column::Duration
+            ]
+        );
+        assert_eq!(
+            fit(39),
+            [
+                Column::Index,
+                Column::Title,
+                Column::Artists,
+                Column::Duration
+            ]
+        );
+        assert_eq!(fit(30), [Column::Index, Column::Title, Column::Artists]);
+        assert_eq!(fit(24), [Column::Title, Column::Artists]);
+        // The title is kept however narrow the table gets.
+        assert_eq!(fit(20), [Column::Title]);
+        assert_eq!(fit(0), [Column::Title]);
+    }
+
+    #[test]
+    fn truncate_counts_cells_not_chars() {
+        assert_eq!(truncate("abcdef", 6), ("abcdef".to_string(), 6));
+        assert_eq!(truncate("abcdef", 4), ("abc…".to_string(), 3));
+        assert_eq!(truncate("日本語", 6), ("日本語".to_string(), 3));
+        assert_eq!(truncate("日本語", 5), ("日本…".to_string(), 2));
+        // A wide character that would overflow is left out rather than split.
+        assert_eq!(truncate("日本語", 4), ("日…".to_string(), 1));
+        assert_eq!(truncate("日本語", 0), (String::new(), 0));
+    }
+}